@@ -7,7 +7,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 fn write_data() -> Result<(), Box<dyn std::error::Error>> {
     let env = rlmdb::DBEnvBuilder::new("test.mdb")
-        .set_map_size(1 * 1024 * 1024 * 1024) // 1GB
+        .set_map_size(1024 * 1024 * 1024) // 1GB
         .set_max_readers(10)
         .set_max_dbs(5)
         .open(None)?;
@@ -0,0 +1,95 @@
+use crate::{Database, LMDBError, PutFlags, Transaction, sys};
+
+/// A set of writes collected in memory and applied in one transaction by
+/// `DBEnv::apply_batch`.
+///
+/// Building a batch doesn't touch the environment, so no transaction is held
+/// open while it is assembled. Operations run in the order they were added.
+#[derive(Debug, Default)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+#[derive(Debug)]
+enum BatchOp {
+    Put {
+        dbi: sys::MDB_dbi,
+        key: Vec<u8>,
+        data: Vec<u8>,
+        flags: PutFlags,
+    },
+    Delete {
+        dbi: sys::MDB_dbi,
+        key: Vec<u8>,
+    },
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a put of `data` under `key`, with `flags` as for `Transaction::put`.
+    pub fn put<K, V>(
+        &mut self,
+        db: &Database<K, V>,
+        key: K,
+        data: V,
+        flags: Option<PutFlags>,
+    ) -> &mut Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.ops.push(BatchOp::Put {
+            dbi: db.id(),
+            key: key.as_ref().to_vec(),
+            data: data.as_ref().to_vec(),
+            flags: flags.unwrap_or_default(),
+        });
+        self
+    }
+
+    /// Queues a delete of `key`. Deleting a missing key is not an error.
+    pub fn delete<K, V>(&mut self, db: &Database<K, V>, key: K) -> &mut Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.ops.push(BatchOp::Delete {
+            dbi: db.id(),
+            key: key.as_ref().to_vec(),
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ops.clear();
+    }
+
+    /// Runs every queued operation in `txn`, stopping at the first error.
+    pub(crate) fn apply(&self, txn: &Transaction<'_>) -> Result<(), LMDBError> {
+        for op in &self.ops {
+            match op {
+                BatchOp::Put {
+                    dbi,
+                    key,
+                    data,
+                    flags,
+                } => txn.put_raw(*dbi, key, data, *flags)?,
+                BatchOp::Delete { dbi, key } => {
+                    txn.del_raw(*dbi, key, None)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    Database, LMDBError, PutFlags, Transaction,
+    cursor::{Cursor, ScanState},
+};
+
+/// Marks the end of each component.
+const TERMINATOR: [u8; 2] = [0x00, 0x01];
+
+/// Stands for a `0x00` byte inside a component.
+const ESCAPED_NUL: [u8; 2] = [0x00, 0xff];
+
+/// A key built from several byte-string components, encoded so that LMDB's
+/// bytewise key order is the same as comparing the components as a tuple.
+///
+/// Every component is written out with each `0x00` byte replaced by
+/// `0x00 0xff` and is followed by `0x00 0x01`. A component therefore sorts
+/// before any longer one it is a prefix of, and all keys sharing their first
+/// components are contiguous, so they can be scanned as a prefix. This only
+/// holds under the default key order, not with `MDB_REVERSEKEY`,
+/// `MDB_INTEGERKEY` or a custom comparator.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompositeKey {
+    encoded: Vec<u8>,
+}
+
+impl CompositeKey {
+    /// Encodes `parts` in order.
+    pub fn new<P: AsRef<[u8]>>(parts: &[P]) -> Self {
+        let mut encoded = Vec::new();
+        for part in parts {
+            for &byte in part.as_ref() {
+                if byte == 0 {
+                    encoded.extend_from_slice(&ESCAPED_NUL);
+                } else {
+                    encoded.push(byte);
+                }
+            }
+            encoded.extend_from_slice(&TERMINATOR);
+        }
+        Self { encoded }
+    }
+
+    /// Returns the encoded key as stored in the database.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.encoded
+    }
+
+    /// Splits an encoded key back into its components.
+    ///
+    /// Fails with `LMDBError::MalformedCompositeKey` if `encoded` wasn't
+    /// produced by `new`.
+    pub fn decode(encoded: &[u8]) -> Result<Vec<Vec<u8>>, LMDBError> {
+        let mut parts = Vec::new();
+        let mut part = Vec::new();
+        let mut bytes = encoded.iter();
+        while let Some(&byte) = bytes.next() {
+            if byte != 0 {
+                part.push(byte);
+                continue;
+            }
+            match bytes.next() {
+                Some(&0xff) => part.push(0),
+                Some(&0x01) => parts.push(std::mem::take(&mut part)),
+                _ => return Err(LMDBError::MalformedCompositeKey),
+            }
+        }
+
+        // A trailing component without its terminator
+        if !part.is_empty() {
+            return Err(LMDBError::MalformedCompositeKey);
+        }
+        Ok(parts)
+    }
+}
+
+impl AsRef<[u8]> for CompositeKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.encoded
+    }
+}
+
+impl<'env, K, V> Database<'env, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    /// Stores `value` under the composite key made of `parts`.
+    pub fn put_composite<P: AsRef<[u8]>>(
+        &self,
+        txn: &Transaction<'_>,
+        parts: &[P],
+        value: V,
+    ) -> Result<(), LMDBError> {
+        let key = CompositeKey::new(parts);
+        txn.put_raw(self.id(), key.as_bytes(), value.as_ref(), PutFlags::empty())
+    }
+
+    /// Returns the value stored under the composite key made of `parts`.
+    pub fn get_composite<P: AsRef<[u8]>>(
+        &self,
+        txn: &Transaction<'_>,
+        parts: &[P],
+    ) -> Result<Option<V>, LMDBError>
+    where
+        V: for<'a> From<&'a [u8]>,
+    {
+        let key = CompositeKey::new(parts);
+        Ok(txn.get_raw(self.id(), key.as_bytes())?.map(V::from))
+    }
+
+    /// Iterates, in tuple order, over the composite keys whose leading
+    /// components equal `prefix`, yielding every component of each key.
+    ///
+    /// An empty `prefix` visits the whole database, so every key in it must be
+    /// a composite key; one that isn't is returned as an `InvalidData` error
+    /// and the scan carries on after it.
+    pub fn scan_prefix_composite<'txn, P: AsRef<[u8]>>(
+        &self,
+        txn: &'txn Transaction<'txn>,
+        prefix: &[P],
+    ) -> Result<CompositePrefixIter<'txn, K, V>, LMDBError> {
+        Ok(CompositePrefixIter {
+            cursor: Cursor::new(txn, self)?,
+            prefix: CompositeKey::new(prefix),
+            state: ScanState::default(),
+        })
+    }
+}
+
+/// Iterator returned by [`Database::scan_prefix_composite`].
+pub struct CompositePrefixIter<'txn, K, V> {
+    cursor: Cursor<'txn, K, V>,
+
+    prefix: CompositeKey,
+
+    state: ScanState,
+}
+
+impl<'txn, K, V> Iterator for CompositePrefixIter<'txn, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(Vec<Vec<u8>>, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self
+            .cursor
+            .prefix_step(&mut self.state, self.prefix.as_bytes())?;
+
+        Some(entry.and_then(|(key, value)| {
+            CompositeKey::decode(key).map(|parts| (parts, V::from(value)))
+        }))
+    }
+}
@@ -1,12 +1,472 @@
-use std::ptr::NonNull;
+use std::{cell::Cell, cmp::Ordering, marker::PhantomData, ptr::NonNull};
 
-use crate::Transaction;
+use crate::{
+    DBEnv, DBFlags, Database, LMDBError, PutFlags, ReadOnlyTransaction, Transaction,
+    TransactionType, db, sys,
+    value::{self, to_mdb_val},
+};
 
+/// A key/data pair borrowed from the memory map.
+pub(crate) type RawEntry<'a> = (&'a [u8], &'a [u8]);
+
+/// A cursor over one database inside a transaction.
+///
+/// The cursor borrows its transaction, so the borrow checker rejects any code
+/// that commits, aborts or drops the transaction while the cursor is alive;
+/// the cursor is always closed first, whatever order the variables are
+/// declared in. A cursor can't outlive its transaction:
+///
+/// ```compile_fail
+/// # fn f(env: &rlmdb::DBEnv) -> Result<(), rlmdb::LMDBError> {
+/// let txn = env.begin_txn()?;
+/// let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None)?;
+/// let mut cursor = txn.cursor(&db)?;
+/// txn.commit()?;
+/// cursor.get_first()?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct Cursor<'txn, K, V> {
-    #[allow(dead_code)]
     ptr: NonNull<crate::sys::MDB_cursor>,
 
-    _marker: std::marker::PhantomData<(&'txn Transaction<'txn>, K, V)>,
+    /// Whether the transaction is read-only, so that slices of the map can be
+    /// handed out for its whole lifetime.
+    read_only: bool,
+
+    /// The transaction's changed flag, set by writes through the cursor.
+    /// `None` for read-only transactions and unbound cursors.
+    dirty: Option<&'txn Cell<bool>>,
+
+    // Ties the cursor to its transaction; together with the `Drop` impl this
+    // makes dropck require the transaction to outlive it
+    _marker: PhantomData<(&'txn Transaction<'txn>, K, V)>,
+}
+
+impl<'txn, K, V> Cursor<'txn, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    pub(crate) fn new(
+        txn: &'txn Transaction<'txn>,
+        db: &Database<K, V>,
+    ) -> Result<Self, LMDBError> {
+        let mut cursor = unsafe { Self::new_unbound(txn, db)? };
+        if txn.txn_type == TransactionType::ReadWrite {
+            cursor.dirty = Some(&txn.dirty);
+        }
+        Ok(cursor)
+    }
+
+    /// Opens a cursor whose lifetime is not tied to the borrow of `txn`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must drop the cursor before `txn` is committed, aborted or
+    /// dropped.
+    pub(crate) unsafe fn new_unbound(
+        txn: &Transaction<'_>,
+        db: &Database<K, V>,
+    ) -> Result<Self, LMDBError> {
+        let mut cursor_ptr: *mut sys::MDB_cursor = std::ptr::null_mut();
+
+        let ret = unsafe { sys::mdb_cursor_open(txn.as_raw_ptr(), db.id(), &mut cursor_ptr) };
+        LMDBError::from_mdb_error(ret)?;
+
+        let ptr = NonNull::new(cursor_ptr).ok_or_else(|| {
+            LMDBError::Io(std::io::Error::other(
+                "mdb_cursor_open succeeded but returned a null cursor pointer",
+            ))
+        })?;
+
+        Ok(Self {
+            ptr,
+            read_only: txn.txn_type == TransactionType::ReadOnly,
+            dirty: None,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the key and value sizes of the entry under the cursor without
+    /// decoding either, or `None` if the cursor is past the last entry.
+    ///
+    /// Fails with `EINVAL` if the cursor has not been positioned yet.
+    pub fn current_sizes(&self) -> Result<Option<(usize, usize)>, LMDBError> {
+        Ok(self
+            .current_raw()?
+            .map(|(key, data)| (key.len(), data.len())))
+    }
+
+    /// Returns the fixed item size of an `MDB_DUPFIXED` database, read from
+    /// the duplicate under the cursor, for splitting `MDB_GET_MULTIPLE` pages.
+    ///
+    /// Returns `None` if the database isn't `MDB_DUPFIXED` or the cursor is
+    /// past the last entry, and fails with `EINVAL` if it isn't positioned yet.
+    pub fn dup_item_size(&self) -> Result<Option<usize>, LMDBError> {
+        if !self.db_flags()?.contains(DBFlags::MDB_DUPFIXED) {
+            return Ok(None);
+        }
+
+        // Every duplicate of a DUPFIXED database has the same size
+        Ok(self.current_sizes()?.map(|(_, data_size)| data_size))
+    }
+
+    /// Returns the page of duplicates holding the value under the cursor in
+    /// an `MDB_DUPFIXED` database (`MDB_GET_MULTIPLE`), as one buffer of
+    /// back-to-back values, and leaves the cursor on the last of them.
+    ///
+    /// Split the buffer with `dup_item_size`. The page may start before the
+    /// cursor's value, so position the cursor with `seek_exact` or
+    /// `first_dup` to read a key's values from the start. Fails with `EINVAL`
+    /// if the cursor isn't positioned and with `MDB_INCOMPATIBLE` if the
+    /// database isn't `MDB_DUPFIXED`.
+    ///
+    /// The buffer points into the memory map for the whole transaction, so,
+    /// like other borrowed reads, this is only allowed in read-only
+    /// transactions; in a write transaction it fails with
+    /// `LMDBError::BorrowedReadInWriteTxn`.
+    pub fn get_multiple(&mut self) -> Result<Option<&'txn [u8]>, LMDBError> {
+        self.get_multiple_raw(sys::MDB_cursor_op::MDB_GET_MULTIPLE)
+    }
+
+    /// Moves on to the next page of the current key's duplicates
+    /// (`MDB_NEXT_MULTIPLE`) and returns it like `get_multiple`, or `None`
+    /// once the key has no more. On a fresh cursor this reads the first key.
+    pub fn next_multiple(&mut self) -> Result<Option<&'txn [u8]>, LMDBError> {
+        self.get_multiple_raw(sys::MDB_cursor_op::MDB_NEXT_MULTIPLE)
+    }
+
+    fn get_multiple_raw(
+        &mut self,
+        op: sys::MDB_cursor_op,
+    ) -> Result<Option<&'txn [u8]>, LMDBError> {
+        if !self.read_only {
+            return Err(LMDBError::BorrowedReadInWriteTxn);
+        }
+
+        let mut key = to_mdb_val(&[]);
+        let mut data = to_mdb_val(&[]);
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_cursor_get(self.ptr.as_ptr(), &mut key, &mut data, op)
+        });
+        if ret == sys::MDB_NOTFOUND {
+            return Ok(None);
+        }
+        LMDBError::from_mdb_error(ret)?;
+
+        // In a read-only transaction the page stays mapped and unchanged until
+        // the transaction ends, so it may outlive this borrow of the cursor
+        let page: &'txn [u8] = unsafe { value::from_mdb_val(&data) };
+        // LMDB can report success without filling in a page
+        Ok(Some(page).filter(|page| !page.is_empty()))
+    }
+
+    /// Returns how many duplicate values the key under the cursor has
+    /// (`mdb_cursor_count`), without stepping through them.
+    ///
+    /// Fails with `MDB_INCOMPATIBLE` if the database isn't `MDB_DUPSORT`, with
+    /// `EINVAL` if the cursor hasn't been positioned yet and with
+    /// `MDB_NOTFOUND` if it is past the last entry.
+    pub fn count(&self) -> Result<usize, LMDBError> {
+        let mut count: sys::mdb_size_t = 0;
+
+        let ret = unsafe { sys::mdb_cursor_count(self.ptr.as_ptr(), &mut count) };
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(count as usize)
+    }
+
+    /// Returns the `n`th (from 0) duplicate value of `key` in an `MDB_DUPSORT`
+    /// database, leaving the cursor on it, or `None` if `key` has fewer than
+    /// `n + 1` values.
+    pub fn nth_dup(&mut self, key: K, n: usize) -> Result<Option<V>, LMDBError>
+    where
+        V: for<'a> From<&'a [u8]>,
+    {
+        let mut entry = self.get_raw(sys::MDB_cursor_op::MDB_SET, Some(key.as_ref()), None)?;
+        for _ in 0..n {
+            if entry.is_none() {
+                break;
+            }
+            entry = self.get_raw(sys::MDB_cursor_op::MDB_NEXT_DUP, None, None)?;
+        }
+
+        Ok(entry.map(|(_, value)| V::from(value)))
+    }
+
+    /// Forgets the cursor's position, so the next step forward starts again
+    /// from the first entry, as if the cursor had just been opened.
+    ///
+    /// LMDB can't unposition a cursor in place, so this opens a fresh cursor on
+    /// the same transaction and database and closes the old one.
+    pub fn reset_position(&mut self) -> Result<(), LMDBError> {
+        // Dropping the old cursor closes it
+        *self = self.open_sibling()?;
+        Ok(())
+    }
+
+    /// Counts the entries after the one under the cursor, i.e. how many more
+    /// times `next` would return an entry. On a cursor that hasn't been
+    /// positioned yet this is every entry.
+    ///
+    /// LMDB keeps no rank information, so this walks to the end with a second
+    /// cursor and costs O(remaining entries). The cursor itself doesn't move.
+    pub fn remaining_exact(&self) -> Result<usize, LMDBError> {
+        let mut walker = self.open_sibling()?;
+        let start = match self.current_raw() {
+            Ok(Some((key, data))) => {
+                // Land on this exact duplicate, not just the first of its key
+                let op = if self.db_flags()?.contains(DBFlags::MDB_DUPSORT) {
+                    sys::MDB_cursor_op::MDB_GET_BOTH
+                } else {
+                    sys::MDB_cursor_op::MDB_SET
+                };
+                walker.get_raw(op, Some(key), Some(data))?;
+                sys::MDB_cursor_op::MDB_NEXT
+            }
+            Ok(None) => return Ok(0),
+            Err(e) if is_unpositioned(&e) => sys::MDB_cursor_op::MDB_FIRST,
+            Err(e) => return Err(e),
+        };
+
+        let mut count = 0;
+        let mut entry = walker.get_raw(start, None, None)?;
+        while entry.is_some() {
+            count += 1;
+            entry = walker.get_raw(sys::MDB_cursor_op::MDB_NEXT, None, None)?;
+        }
+        Ok(count)
+    }
+
+    /// Estimates `remaining_exact` in O(log n) by placing the current key
+    /// between the first and last keys, assuming keys are spread evenly in
+    /// bytewise order.
+    ///
+    /// Good enough for a progress bar over, say, sequential integer or
+    /// timestamp keys; skewed keys, duplicates or a custom key order make it
+    /// inaccurate. The cursor itself doesn't move.
+    pub fn remaining_estimate(&self) -> Result<usize, LMDBError> {
+        let mut stat = std::mem::MaybeUninit::<sys::MDB_stat>::uninit();
+        let ret = unsafe {
+            let txn = sys::mdb_cursor_txn(self.ptr.as_ptr());
+            let dbi = sys::mdb_cursor_dbi(self.ptr.as_ptr());
+            sys::mdb_stat(txn, dbi, stat.as_mut_ptr())
+        };
+        LMDBError::from_mdb_error(ret)?;
+        let entries = unsafe { stat.assume_init() }.ms_entries;
+
+        let key = match self.current_raw() {
+            Ok(Some((key, _))) => key,
+            Ok(None) => return Ok(0),
+            Err(e) if is_unpositioned(&e) => return Ok(entries),
+            Err(e) => return Err(e),
+        };
+
+        let mut walker = self.open_sibling()?;
+        let first = walker
+            .get_raw(sys::MDB_cursor_op::MDB_FIRST, None, None)?
+            .map(|(first, _)| first.to_vec())
+            .unwrap_or_default();
+        let last = walker
+            .get_raw(sys::MDB_cursor_op::MDB_LAST, None, None)?
+            .map_or(&[][..], |(last, _)| last);
+
+        let after = 1.0 - key_fraction(&first, last, key);
+        Ok((entries.saturating_sub(1) as f64 * after).round() as usize)
+    }
+
+    /// Opens a new, unpositioned cursor on the same transaction and database.
+    fn open_sibling(&self) -> Result<Self, LMDBError> {
+        let mut cursor_ptr: *mut sys::MDB_cursor = std::ptr::null_mut();
+
+        let ret = unsafe {
+            let txn = sys::mdb_cursor_txn(self.ptr.as_ptr());
+            let dbi = sys::mdb_cursor_dbi(self.ptr.as_ptr());
+            sys::mdb_cursor_open(txn, dbi, &mut cursor_ptr)
+        };
+        LMDBError::from_mdb_error(ret)?;
+
+        let ptr = NonNull::new(cursor_ptr).ok_or_else(|| {
+            LMDBError::Io(std::io::Error::other(
+                "mdb_cursor_open succeeded but returned a null cursor pointer",
+            ))
+        })?;
+
+        Ok(Self {
+            ptr,
+            read_only: self.read_only,
+            dirty: self.dirty,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Stores `data` under `key` (`mdb_cursor_put`) and leaves the cursor on
+    /// the new entry.
+    ///
+    /// Since the cursor stays at the tail, repeated puts with `MDB_APPEND` (or
+    /// `MDB_APPENDDUP` for duplicates) load already sorted data without
+    /// searching the tree. In a read-only transaction LMDB fails with `EACCES`.
+    pub fn put(&mut self, key: K, data: V, flags: Option<PutFlags>) -> Result<(), LMDBError> {
+        let mut key = to_mdb_val(key.as_ref());
+        let mut data = to_mdb_val(data.as_ref());
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_cursor_put(
+                self.ptr.as_ptr(),
+                &mut key,
+                &mut data,
+                flags.unwrap_or_default().bits(),
+            )
+        });
+        LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Deletes the entry under the cursor (`mdb_cursor_del`).
+    ///
+    /// In a `MDB_DUPSORT` database only the current duplicate is removed,
+    /// unless `flags` contains `MDB_NODUPDATA`, which removes every duplicate
+    /// of the current key; no other flag applies. Afterwards the cursor sits
+    /// where the deleted entry was, so `next` returns the entry that followed
+    /// it, not the one after that; call `next` rather than skipping ahead when
+    /// deleting in a loop. Fails with `EINVAL` if the cursor isn't on an entry.
+    pub fn del(&mut self, flags: Option<PutFlags>) -> Result<(), LMDBError> {
+        self.del_raw(flags.unwrap_or_default().bits())
+    }
+
+    /// Runs `f`, an LMDB call on this cursor, with the environment's user
+    /// context available to custom comparators.
+    fn with_compare_ctx<R>(&self, f: impl FnOnce() -> R) -> R {
+        unsafe { db::with_compare_ctx(sys::mdb_cursor_txn(self.ptr.as_ptr()), f) }
+    }
+
+    fn mark_dirty(&self) {
+        if let Some(dirty) = self.dirty {
+            dirty.set(true);
+        }
+    }
+
+    /// Turns the cursor into an iterator over every key, like `iter` but
+    /// without building a `V` for each entry.
+    pub fn iter_keys(self) -> Keys<'txn, K, V>
+    where
+        K: for<'a> From<&'a [u8]>,
+    {
+        Keys {
+            iter: Iter::from_cursor(self),
+        }
+    }
+
+    /// Turns the cursor into an iterator over every value in key order, like
+    /// `iter` but without building a `K` for each entry.
+    pub fn iter_values(self) -> Values<'txn, K, V>
+    where
+        V: for<'a> From<&'a [u8]>,
+    {
+        Values {
+            iter: Iter::from_cursor(self),
+        }
+    }
+
+    /// Returns the flags of the cursor's database.
+    fn db_flags(&self) -> Result<DBFlags, LMDBError> {
+        let mut flags: std::ffi::c_uint = 0;
+
+        let ret = unsafe {
+            let txn = sys::mdb_cursor_txn(self.ptr.as_ptr());
+            let dbi = sys::mdb_cursor_dbi(self.ptr.as_ptr());
+            sys::mdb_dbi_flags(txn, dbi, &mut flags)
+        };
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(DBFlags::from_bits_truncate(flags))
+    }
+
+    /// Returns the entry under the cursor without moving it (`MDB_GET_CURRENT`).
+    fn current_raw(&self) -> Result<Option<RawEntry<'_>>, LMDBError> {
+        let mut key = to_mdb_val(&[]);
+        let mut data = to_mdb_val(&[]);
+
+        let ret = unsafe {
+            sys::mdb_cursor_get(
+                self.ptr.as_ptr(),
+                &mut key,
+                &mut data,
+                sys::MDB_cursor_op::MDB_GET_CURRENT,
+            )
+        };
+        if ret == sys::MDB_NOTFOUND {
+            return Ok(None);
+        }
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(Some(unsafe {
+            (value::from_mdb_val(&key), value::from_mdb_val(&data))
+        }))
+    }
+
+    /// Steps a scan over the entries whose keys start with `prefix`; the raw
+    /// form of `prefix`, for iterators that decode entries their own way.
+    pub(crate) fn prefix_step(
+        &mut self,
+        state: &mut ScanState,
+        prefix: &[u8],
+    ) -> Option<Result<RawEntry<'_>, LMDBError>> {
+        state.step(
+            self,
+            |cursor| cursor.seek_raw(prefix),
+            |key| key.starts_with(prefix),
+        )
+    }
+
+    /// Positions at the first key greater than or equal to `key`.
+    ///
+    /// LMDB rejects zero-length keys, so an empty `key` goes to the first entry.
+    pub(crate) fn seek_raw(&mut self, key: &[u8]) -> Result<Option<RawEntry<'_>>, LMDBError> {
+        if key.is_empty() {
+            self.get_raw(sys::MDB_cursor_op::MDB_FIRST, None, None)
+        } else {
+            self.get_raw(sys::MDB_cursor_op::MDB_SET_RANGE, Some(key), None)
+        }
+    }
+
+    /// Deletes the entry under the cursor. A following `MDB_NEXT` moves to the
+    /// entry after the deleted one.
+    pub(crate) fn del_raw(&mut self, flags: std::ffi::c_uint) -> Result<(), LMDBError> {
+        let ret =
+            self.with_compare_ctx(|| unsafe { sys::mdb_cursor_del(self.ptr.as_ptr(), flags) });
+        LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Runs `mdb_cursor_get` with `op`, mapping `MDB_NOTFOUND` to `None`.
+    ///
+    /// The returned slices point into the memory map and are only valid until
+    /// the cursor is moved again or the transaction writes.
+    pub(crate) fn get_raw(
+        &mut self,
+        op: sys::MDB_cursor_op,
+        key: Option<&[u8]>,
+        data: Option<&[u8]>,
+    ) -> Result<Option<RawEntry<'_>>, LMDBError> {
+        let mut key = to_mdb_val(key.unwrap_or_default());
+        let mut data = to_mdb_val(data.unwrap_or_default());
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_cursor_get(self.ptr.as_ptr(), &mut key, &mut data, op)
+        });
+        if ret == sys::MDB_NOTFOUND {
+            return Ok(None);
+        }
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(Some(unsafe {
+            (value::from_mdb_val(&key), value::from_mdb_val(&data))
+        }))
+    }
 }
 
 impl<'txn, K, V> Cursor<'txn, K, V>
@@ -14,5 +474,436 @@ where
     K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
     V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
 {
-    // TODO
+    /// Turns the cursor into an iterator over every entry in key order,
+    /// starting from the first entry wherever the cursor is positioned now.
+    pub fn iter(self) -> CursorIter<'txn, K, V> {
+        Iter::from_cursor(self)
+    }
+
+    /// Iterates over the entries from the first key at or after `start` up to,
+    /// but not including, `end`.
+    ///
+    /// Keys are compared with the database's own ordering, so the bounds work
+    /// for `MDB_INTEGERKEY` and `MDB_REVERSEKEY` databases too. The cursor is
+    /// left on the last entry read.
+    pub fn range(&mut self, start: K, end: K) -> CursorRange<'_, 'txn, K, V> {
+        CursorRange {
+            cursor: self,
+            start,
+            end,
+            state: ScanState::default(),
+        }
+    }
+
+    /// Iterates over the entries whose keys start with the bytes of `prefix`,
+    /// in key order; an empty `prefix` visits the whole database.
+    ///
+    /// The cursor is left on the last entry read.
+    pub fn prefix(&mut self, prefix: K) -> impl Iterator<Item = Result<(K, V), LMDBError>> {
+        let mut state = ScanState::default();
+
+        std::iter::from_fn(move || {
+            self.prefix_step(&mut state, prefix.as_ref())
+                .map(|entry| entry.map(decode))
+        })
+    }
+
+    /// Moves to the first entry, or returns `None` if the database is empty.
+    pub fn get_first(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_FIRST)
+    }
+
+    /// Moves to the last entry, or returns `None` if the database is empty.
+    pub fn get_last(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_LAST)
+    }
+
+    /// Steps to the next entry, or returns `None` past the last one. On a
+    /// fresh cursor this is the first entry.
+    // Not `Iterator::next`: a cursor is stepped in both directions, and errors
+    // come first so `while let Some(..) = cursor.next()?` reads naturally
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_NEXT)
+    }
+
+    /// Steps to the previous entry, or returns `None` before the first one. On
+    /// a fresh cursor this is the last entry.
+    pub fn prev(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_PREV)
+    }
+
+    /// Moves to the first duplicate of the current key (`MDB_FIRST_DUP`).
+    ///
+    /// This and the other `_dup` methods are meant for `MDB_DUPSORT`
+    /// databases; `first_dup` and `last_dup` fail with `MDB_INCOMPATIBLE`
+    /// elsewhere, and the cursor must already be on a key.
+    pub fn first_dup(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_dup_edge(sys::MDB_cursor_op::MDB_FIRST_DUP)
+    }
+
+    /// Moves to the last duplicate of the current key (`MDB_LAST_DUP`).
+    pub fn last_dup(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_dup_edge(sys::MDB_cursor_op::MDB_LAST_DUP)
+    }
+
+    /// Steps to the next duplicate of the current key (`MDB_NEXT_DUP`), or
+    /// returns `None` after its last one, leaving the cursor there.
+    pub fn next_dup(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_NEXT_DUP)
+    }
+
+    /// Steps to the previous duplicate of the current key (`MDB_PREV_DUP`), or
+    /// returns `None` before its first one, leaving the cursor there.
+    pub fn prev_dup(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_PREV_DUP)
+    }
+
+    /// Skips the remaining duplicates and moves to the first value of the
+    /// next key (`MDB_NEXT_NODUP`), or returns `None` past the last key.
+    pub fn next_nodup(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_NEXT_NODUP)
+    }
+
+    /// Moves to the last value of the previous key (`MDB_PREV_NODUP`), or
+    /// returns `None` before the first key.
+    pub fn prev_nodup(&mut self) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded(sys::MDB_cursor_op::MDB_PREV_NODUP)
+    }
+
+    /// Moves to the first entry whose key is greater than or equal to `key`
+    /// (`MDB_SET_RANGE`), or returns `None` if there is none.
+    ///
+    /// In an `MDB_DUPSORT` database this lands on the first, smallest,
+    /// duplicate of that key; `next` then walks the remaining duplicates
+    /// before moving on to the following key.
+    pub fn seek_range(&mut self, key: K) -> Result<Option<(K, V)>, LMDBError> {
+        Ok(self.seek_raw(key.as_ref())?.map(decode))
+    }
+
+    /// Moves to the exact pair `key`/`data` (`MDB_GET_BOTH`), or returns
+    /// `None` if it isn't stored. Meant for `MDB_DUPSORT` databases, where it
+    /// checks for one value among a key's duplicates.
+    pub fn get_both(&mut self, key: K, data: V) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded_both(sys::MDB_cursor_op::MDB_GET_BOTH, key, data)
+    }
+
+    /// Moves to `key` itself and, among its duplicates, to the first one
+    /// greater than or equal to `data` (`MDB_GET_BOTH_RANGE`).
+    ///
+    /// Returns `None` if `key` is absent or all its duplicates sort before
+    /// `data`; unlike `seek_range` it never moves on to a later key. Meant for
+    /// `MDB_DUPSORT` databases.
+    pub fn get_both_range(&mut self, key: K, data: V) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_decoded_both(sys::MDB_cursor_op::MDB_GET_BOTH_RANGE, key, data)
+    }
+
+    /// Same as `get_both_range`.
+    pub fn seek_range_both(&mut self, key: K, data: V) -> Result<Option<(K, V)>, LMDBError> {
+        self.get_both_range(key, data)
+    }
+
+    /// Moves to `key` itself (`MDB_SET_KEY`), or returns `None` if it is absent.
+    pub fn seek_exact(&mut self, key: K) -> Result<Option<(K, V)>, LMDBError> {
+        Ok(self
+            .get_raw(sys::MDB_cursor_op::MDB_SET_KEY, Some(key.as_ref()), None)?
+            .map(decode))
+    }
+
+    fn get_decoded(&mut self, op: sys::MDB_cursor_op) -> Result<Option<(K, V)>, LMDBError> {
+        Ok(self.get_raw(op, None, None)?.map(decode))
+    }
+
+    fn get_decoded_both(
+        &mut self,
+        op: sys::MDB_cursor_op,
+        key: K,
+        data: V,
+    ) -> Result<Option<(K, V)>, LMDBError> {
+        Ok(self
+            .get_raw(op, Some(key.as_ref()), Some(data.as_ref()))?
+            .map(decode))
+    }
+
+    fn get_dup_edge(&mut self, op: sys::MDB_cursor_op) -> Result<Option<(K, V)>, LMDBError> {
+        // MDB_FIRST_DUP and MDB_LAST_DUP only fill in the data, so the key is
+        // read back from the new position
+        if self.get_raw(op, None, None)?.is_none() {
+            return Ok(None);
+        }
+        Ok(self.current_raw()?.map(decode))
+    }
+}
+
+/// Whether `err` is LMDB's `EINVAL` for a cursor that hasn't been positioned.
+fn is_unpositioned(err: &LMDBError) -> bool {
+    matches!(err, LMDBError::Io(e) if e.kind() == std::io::ErrorKind::InvalidInput)
+}
+
+/// Where `key` falls between `first` and `last`, from 0.0 to 1.0, comparing
+/// the eight bytes after their common prefix as big-endian numbers.
+fn key_fraction(first: &[u8], last: &[u8], key: &[u8]) -> f64 {
+    let common = first.iter().zip(last).take_while(|(a, b)| a == b).count();
+    let number = |bytes: &[u8]| {
+        let mut buf = [0; 8];
+        let tail = bytes.get(common..).unwrap_or_default();
+        let len = tail.len().min(8);
+        buf[..len].copy_from_slice(&tail[..len]);
+        u64::from_be_bytes(buf) as f64
+    };
+
+    let (first, last, key) = (number(first), number(last), number(key));
+    if last <= first {
+        return 0.0;
+    }
+    ((key - first) / (last - first)).clamp(0.0, 1.0)
+}
+
+/// Compares two keys using the ordering of `cursor`'s database.
+fn compare_keys(cursor: NonNull<sys::MDB_cursor>, a: &[u8], b: &[u8]) -> Ordering {
+    let a = to_mdb_val(a);
+    let b = to_mdb_val(b);
+
+    unsafe {
+        let txn = sys::mdb_cursor_txn(cursor.as_ptr());
+        let dbi = sys::mdb_cursor_dbi(cursor.as_ptr());
+        db::with_compare_ctx(txn, || sys::mdb_cmp(txn, dbi, &a, &b))
+    }
+    .cmp(&0)
+}
+
+fn decode<K, V>((key, value): RawEntry<'_>) -> (K, V)
+where
+    K: for<'a> From<&'a [u8]>,
+    V: for<'a> From<&'a [u8]>,
+{
+    (K::from(key), V::from(value))
+}
+
+/// Progress of a scan that positions a cursor once and then steps it forward
+/// with `MDB_NEXT`, shared by the iterators that walk a cursor.
+#[derive(Default)]
+pub(crate) struct ScanState {
+    started: bool,
+
+    done: bool,
+}
+
+impl ScanState {
+    /// Moves `cursor` to the scan's next entry: with `seek` on the first call
+    /// and `MDB_NEXT` after that. The scan ends past the last entry, at the
+    /// first key `keep` rejects, or after an error.
+    pub(crate) fn step<'c, 'txn, K, V>(
+        &mut self,
+        cursor: &'c mut Cursor<'txn, K, V>,
+        seek: impl FnOnce(&'c mut Cursor<'txn, K, V>) -> Result<Option<RawEntry<'c>>, LMDBError>,
+        keep: impl FnOnce(&[u8]) -> bool,
+    ) -> Option<Result<RawEntry<'c>, LMDBError>>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        if self.done {
+            return None;
+        }
+
+        let entry = if self.started {
+            cursor.get_raw(sys::MDB_cursor_op::MDB_NEXT, None, None)
+        } else {
+            self.started = true;
+            seek(cursor)
+        };
+
+        match entry {
+            Ok(Some(entry)) if keep(entry.0) => Some(Ok(entry)),
+            Ok(_) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'txn, K, V> Drop for Cursor<'txn, K, V> {
+    fn drop(&mut self) {
+        unsafe { sys::mdb_cursor_close(self.ptr.as_ptr()) }
+    }
+}
+
+/// An iterator over every entry of a database, in key order.
+///
+/// Returned by [`Database::iter`] and [`Cursor::iter`]; it borrows the
+/// transaction it reads from.
+pub struct Iter<'txn, K, V> {
+    cursor: Cursor<'txn, K, V>,
+
+    state: ScanState,
+}
+
+/// The iterator a [`Cursor`] turns into with [`Cursor::iter`].
+pub type CursorIter<'txn, K, V> = Iter<'txn, K, V>;
+
+impl<'txn, K, V> Iter<'txn, K, V> {
+    fn from_cursor(cursor: Cursor<'txn, K, V>) -> Self {
+        Self {
+            cursor,
+            state: ScanState::default(),
+        }
+    }
+}
+
+impl<'txn, K, V> Iter<'txn, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    pub(crate) fn new(
+        txn: &'txn Transaction<'txn>,
+        db: &Database<K, V>,
+    ) -> Result<Self, LMDBError> {
+        Ok(Self::from_cursor(Cursor::new(txn, db)?))
+    }
+
+    /// Steps to the next entry without decoding it.
+    pub(crate) fn next_raw(&mut self) -> Option<Result<RawEntry<'_>, LMDBError>> {
+        self.state.step(
+            &mut self.cursor,
+            |cursor| cursor.get_raw(sys::MDB_cursor_op::MDB_FIRST, None, None),
+            |_| true,
+        )
+    }
+}
+
+impl<'txn, K, V> Iterator for Iter<'txn, K, V>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(K, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw().map(|entry| entry.map(decode))
+    }
+}
+
+/// An iterator over the keys of a database, returned by
+/// [`Cursor::iter_keys`]. Values are never decoded.
+pub struct Keys<'txn, K, V> {
+    iter: Iter<'txn, K, V>,
+}
+
+impl<'txn, K, V> Iterator for Keys<'txn, K, V>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    V: AsRef<[u8]>,
+{
+    type Item = Result<K, LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next_raw()
+            .map(|entry| entry.map(|(key, _)| K::from(key)))
+    }
+}
+
+/// An iterator over the values of a database in key order, returned by
+/// [`Cursor::iter_values`]. Keys are never decoded.
+pub struct Values<'txn, K, V> {
+    iter: Iter<'txn, K, V>,
+}
+
+impl<'txn, K, V> Iterator for Values<'txn, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<V, LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next_raw()
+            .map(|entry| entry.map(|(_, value)| V::from(value)))
+    }
+}
+
+/// An iterator over a key range, returned by [`Cursor::range`].
+pub struct CursorRange<'c, 'txn, K, V> {
+    cursor: &'c mut Cursor<'txn, K, V>,
+
+    start: K,
+
+    /// Key to stop before.
+    end: K,
+
+    state: ScanState,
+}
+
+impl<'c, 'txn, K, V> Iterator for CursorRange<'c, 'txn, K, V>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(K, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ptr = self.cursor.ptr;
+        let (start, end) = (self.start.as_ref(), self.end.as_ref());
+
+        self.state
+            .step(
+                self.cursor,
+                |cursor| cursor.seek_raw(start),
+                |key| compare_keys(ptr, key, end) == Ordering::Less,
+            )
+            .map(|entry| entry.map(decode))
+    }
+}
+
+/// An iterator over a whole database that owns its read-only transaction.
+///
+/// Because it borrows nothing but the environment, it can be returned from a
+/// function. The transaction is aborted when the iterator is dropped.
+pub struct OwnedScan<'env, K, V> {
+    // Declared before `txn` so the cursor is closed before the txn is aborted
+    iter: Iter<'env, K, V>,
+
+    txn: ReadOnlyTransaction<'env>,
+}
+
+impl<'env, K, V> OwnedScan<'env, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    pub(crate) fn new(env: &'env DBEnv, db: &Database<K, V>) -> Result<Self, LMDBError> {
+        let txn = env.begin_txn_read_only()?;
+        // The cursor lives in the same struct as the txn and is dropped first
+        let cursor = unsafe { Cursor::new_unbound(&txn, db)? };
+
+        Ok(Self {
+            iter: Iter::from_cursor(cursor),
+            txn,
+        })
+    }
+
+    /// The read-only transaction the scan is reading from.
+    pub fn txn(&self) -> &ReadOnlyTransaction<'env> {
+        &self.txn
+    }
+}
+
+impl<'env, K, V> Iterator for OwnedScan<'env, K, V>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(K, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
 }
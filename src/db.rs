@@ -1,8 +1,26 @@
-use std::{ffi, marker::PhantomData};
+use std::{
+    any::Any,
+    cell::Cell,
+    cmp::Ordering,
+    ffi, fmt,
+    io::{self, BufRead, Write},
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    ptr, str,
+};
 
 use bitflags::bitflags;
 
-use crate::{DBEnv, sys};
+use crate::{
+    DBEnv, LMDBError, PutFlags, Transaction,
+    cursor::{Cursor, Iter, Keys, RawEntry, ScanState},
+    dbenv::UserCtx,
+    error::MDBError,
+    ndjson, sys, value,
+};
+
+/// A database handle that reads and writes plain byte slices.
+pub(crate) type RawDatabase<'a> = Database<'a, &'a [u8], &'a [u8]>;
 
 pub struct Database<'env, K, V> {
     /// The raw MDB_dbi handle from LMDB. It's a u32 (unsigned int) in C.
@@ -63,6 +81,13 @@ impl Default for DBFlags {
     }
 }
 
+impl fmt::Display for DBFlags {
+    /// Formats the set flags by name, e.g. `MDB_DUPSORT | MDB_CREATE`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        bitflags::parser::to_writer(self, f)
+    }
+}
+
 impl<'env, K, V> Database<'env, K, V>
 where
     K: AsRef<[u8]>,
@@ -83,4 +108,609 @@ where
     pub fn name(&self) -> Option<&str> {
         self.db_name.as_deref()
     }
+
+    /// Iterates over every entry in key order within `txn`.
+    pub fn iter<'txn>(&self, txn: &'txn Transaction<'_>) -> Result<Iter<'txn, K, V>, LMDBError>
+    where
+        K: for<'a> From<&'a [u8]>,
+        V: for<'a> From<&'a [u8]>,
+    {
+        Iter::new(txn, self)
+    }
+
+    /// Returns a 64-bit FNV-1a fingerprint of every key and value, in key order.
+    ///
+    /// Equal contents under the same key ordering always hash equal, so this
+    /// is a cheap way to compare two databases or spot changes between
+    /// snapshots. It is not collision resistant. This walks every entry.
+    pub fn content_hash(&self, txn: &Transaction<'_>) -> Result<u64, LMDBError> {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        fn fold(hash: u64, bytes: &[u8]) -> u64 {
+            bytes.iter().fold(hash, |hash, &b| {
+                (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+            })
+        }
+
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut iter = Iter::new(txn, self)?;
+        while let Some(entry) = iter.next_raw() {
+            let (key, value) = entry?;
+            // Length prefixes keep ("ab", "c") distinct from ("a", "bc")
+            for part in [key, value] {
+                hash = fold(hash, &(part.len() as u64).to_le_bytes());
+                hash = fold(hash, part);
+            }
+        }
+
+        Ok(hash)
+    }
+
+    /// Writes every entry to `out` in key order as newline-delimited JSON, one
+    /// `{"key":"<base64>","value":"<base64>"}` object per line, and returns
+    /// how many entries were written.
+    pub fn dump_ndjson<W: Write>(
+        &self,
+        txn: &Transaction<'_>,
+        mut out: W,
+    ) -> Result<usize, LMDBError> {
+        let mut count = 0;
+        let mut iter = Iter::new(txn, self)?;
+        while let Some(entry) = iter.next_raw() {
+            let (key, value) = entry?;
+            ndjson::write_entry(&mut out, key, value)?;
+            count += 1;
+        }
+
+        out.flush()?;
+        Ok(count)
+    }
+
+    /// Stores every entry read from `input` in the format written by
+    /// `dump_ndjson`, overwriting existing keys, and returns how many entries
+    /// were stored. Blank lines are skipped.
+    ///
+    /// A malformed line fails with `ErrorKind::InvalidData` naming its line
+    /// number; entries before it have been written, so abort the transaction
+    /// to discard a partial load.
+    pub fn load_ndjson<R: BufRead>(
+        &self,
+        txn: &Transaction<'_>,
+        input: R,
+    ) -> Result<usize, LMDBError> {
+        let mut count = 0;
+        for (index, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (key, value) = ndjson::parse_entry(&line).map_err(|msg| {
+                LMDBError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {msg}", index + 1),
+                ))
+            })?;
+            txn.put_raw(self.raw_dbi, &key, &value, PutFlags::empty())?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Returns a view that keeps its keys under `prefix`, so several logical
+    /// namespaces can share this database.
+    pub fn namespaced(&self, prefix: Vec<u8>) -> Namespaced<'_, 'env, K, V> {
+        Namespaced { db: self, prefix }
+    }
+
+    /// Returns a view of this database inside `txn` with `BTreeMap`-style
+    /// methods.
+    pub fn as_map<'a>(&'a self, txn: &'a Transaction<'a>) -> OrderedMap<'a, 'env, K, V> {
+        OrderedMap { db: self, txn }
+    }
+}
+
+/// A custom ordering for a database's keys or duplicates, registered with
+/// [`DBEnv::register_comparator`] or [`DBEnv::register_dup_comparator`].
+///
+/// LMDB calls comparators without any state of their own, so the ordering is
+/// a type rather than a closure. State it needs, such as collation tables, can
+/// be attached to the environment with [`DBEnv::set_userctx`] and arrives as
+/// `ctx` (`None` if no context is set). The ordering must be a total order,
+/// and every program opening the database must use the same one, or lookups
+/// will miss entries. A panic inside `compare` aborts the process.
+pub trait Comparator {
+    fn compare(ctx: Option<&dyn Any>, a: &[u8], b: &[u8]) -> Ordering;
+}
+
+thread_local! {
+    /// User context of the environment whose LMDB call is running on this
+    /// thread, for `compare_with`.
+    static COMPARE_CTX: Cell<*const UserCtx> = const { Cell::new(ptr::null()) };
+}
+
+/// Makes the user context of `txn`'s environment available to comparators
+/// while `f`, an LMDB call on `txn` that may compare keys, runs.
+///
+/// Transactions aren't `Send`, so LMDB runs the comparators on this thread.
+///
+/// # Safety
+///
+/// `txn` must be a live transaction.
+pub(crate) unsafe fn with_compare_ctx<R>(txn: *mut sys::MDB_txn, f: impl FnOnce() -> R) -> R {
+    let ctx = unsafe { sys::mdb_env_get_userctx(sys::mdb_txn_env(txn)) } as *const UserCtx;
+    let prev = COMPARE_CTX.replace(ctx);
+    // A panicking comparator aborts, so `f` always returns here
+    let result = f();
+    COMPARE_CTX.set(prev);
+    result
+}
+
+/// The `MDB_cmp_func` handed to LMDB for the comparator `C`.
+pub(crate) unsafe extern "C" fn compare_with<C: Comparator>(
+    a: *const sys::MDB_val,
+    b: *const sys::MDB_val,
+) -> ffi::c_int {
+    let (a, b) = unsafe { (value::from_mdb_val(&*a), value::from_mdb_val(&*b)) };
+    // The environment, and with it the context, outlives the running call
+    let ctx = unsafe { COMPARE_CTX.get().as_ref() }.map(|ctx| &**ctx as &dyn Any);
+    C::compare(ctx, a, b) as ffi::c_int
+}
+
+/// A database keyed by UTF-8 strings.
+///
+/// Keys are stored as their UTF-8 bytes. They may not contain NUL bytes, so
+/// any key can also be handed to LMDB where it expects a C string, such as a
+/// database name; one that does is rejected with `LMDBError::NulInStrKey`.
+pub struct StrDatabase<'env, V> {
+    db: Database<'env, String, V>,
+}
+
+impl<'env, V> StrDatabase<'env, V>
+where
+    V: AsRef<[u8]>,
+{
+    pub fn new(db: Database<'env, String, V>) -> Self {
+        Self { db }
+    }
+
+    pub fn inner(&self) -> &Database<'env, String, V> {
+        &self.db
+    }
+
+    pub fn into_inner(self) -> Database<'env, String, V> {
+        self.db
+    }
+
+    pub fn get(&self, txn: &Transaction<'_>, key: &str) -> Result<Option<V>, LMDBError>
+    where
+        V: for<'a> From<&'a [u8]>,
+    {
+        check_str_key(key)?;
+        Ok(txn.get_raw(self.db.id(), key.as_bytes())?.map(V::from))
+    }
+
+    pub fn put(&self, txn: &Transaction<'_>, key: &str, value: V) -> Result<(), LMDBError> {
+        check_str_key(key)?;
+        txn.put_raw(
+            self.db.id(),
+            key.as_bytes(),
+            value.as_ref(),
+            PutFlags::empty(),
+        )
+    }
+
+    /// Deletes `key`, returning whether it existed.
+    pub fn delete(&self, txn: &Transaction<'_>, key: &str) -> Result<bool, LMDBError> {
+        check_str_key(key)?;
+        txn.del_raw(self.db.id(), key.as_bytes(), None)
+    }
+
+    /// Iterates, in key order, over the entries whose keys start with `prefix`.
+    ///
+    /// A key that isn't valid UTF-8, written through the inner database, is
+    /// returned as an `InvalidData` error and the scan carries on after it.
+    pub fn scan_prefix<'txn>(
+        &self,
+        txn: &'txn Transaction<'txn>,
+        prefix: &str,
+    ) -> Result<StrPrefixIter<'txn, V>, LMDBError> {
+        check_str_key(prefix)?;
+
+        Ok(StrPrefixIter {
+            cursor: Cursor::new(txn, &self.db)?,
+            prefix: prefix.to_owned(),
+            state: ScanState::default(),
+        })
+    }
+}
+
+/// Iterator returned by [`StrDatabase::scan_prefix`].
+pub struct StrPrefixIter<'txn, V> {
+    cursor: Cursor<'txn, String, V>,
+
+    prefix: String,
+
+    state: ScanState,
+}
+
+impl<'txn, V> Iterator for StrPrefixIter<'txn, V>
+where
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(String, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self
+            .cursor
+            .prefix_step(&mut self.state, self.prefix.as_bytes())?;
+
+        Some(entry.and_then(|(key, value)| match str::from_utf8(key) {
+            Ok(key) => Ok((key.to_owned(), V::from(value))),
+            Err(e) => Err(LMDBError::Io(io::Error::new(io::ErrorKind::InvalidData, e))),
+        }))
+    }
+}
+
+/// A database used as a sorted set: keys are stored with empty values.
+pub struct SetDatabase<'env, K> {
+    db: Database<'env, K, [u8; 0]>,
+}
+
+impl<'env, K> SetDatabase<'env, K>
+where
+    K: AsRef<[u8]>,
+{
+    pub fn new(db: Database<'env, K, [u8; 0]>) -> Self {
+        Self { db }
+    }
+
+    pub fn inner(&self) -> &Database<'env, K, [u8; 0]> {
+        &self.db
+    }
+
+    pub fn into_inner(self) -> Database<'env, K, [u8; 0]> {
+        self.db
+    }
+
+    /// Adds `key`, returning `false` if it was already present.
+    pub fn insert(&self, txn: &Transaction<'_>, key: K) -> Result<bool, LMDBError> {
+        match txn.put_raw(self.db.id(), key.as_ref(), &[], PutFlags::MDB_NOOVERWRITE) {
+            Ok(()) => Ok(true),
+            Err(LMDBError::MDB(MDBError::KeyExists)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn contains(&self, txn: &Transaction<'_>, key: K) -> Result<bool, LMDBError> {
+        Ok(txn.get_raw(self.db.id(), key.as_ref())?.is_some())
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&self, txn: &Transaction<'_>, key: K) -> Result<bool, LMDBError> {
+        txn.del_raw(self.db.id(), key.as_ref(), None)
+    }
+
+    /// Iterates over the keys in sorted order.
+    pub fn iter<'txn>(&self, txn: &'txn Transaction<'txn>) -> Result<SetIter<'txn, K>, LMDBError>
+    where
+        K: for<'a> From<&'a [u8]>,
+    {
+        Ok(SetIter {
+            keys: Cursor::new(txn, &self.db)?.iter_keys(),
+        })
+    }
+}
+
+/// Iterator returned by [`SetDatabase::iter`].
+pub struct SetIter<'txn, K> {
+    keys: Keys<'txn, K, [u8; 0]>,
+}
+
+impl<'txn, K> Iterator for SetIter<'txn, K>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<K, LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.keys.next()
+    }
+}
+
+/// A view of a [`Database`] that prepends a fixed prefix to every key.
+///
+/// Keys passed in and handed back are relative to the namespace, and scans
+/// never leave it. Returned by [`Database::namespaced`].
+pub struct Namespaced<'db, 'env, K, V> {
+    db: &'db Database<'env, K, V>,
+
+    prefix: Vec<u8>,
+}
+
+impl<'db, 'env, K, V> Namespaced<'db, 'env, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn inner(&self) -> &'db Database<'env, K, V> {
+        self.db
+    }
+
+    pub fn get(&self, txn: &Transaction<'_>, key: K) -> Result<Option<V>, LMDBError>
+    where
+        V: for<'a> From<&'a [u8]>,
+    {
+        let key = self.full_key(key.as_ref());
+        Ok(txn.get_raw(self.db.id(), &key)?.map(V::from))
+    }
+
+    pub fn put(&self, txn: &Transaction<'_>, key: K, value: V) -> Result<(), LMDBError> {
+        let key = self.full_key(key.as_ref());
+        txn.put_raw(self.db.id(), &key, value.as_ref(), PutFlags::empty())
+    }
+
+    /// Deletes `key`, returning whether it existed.
+    pub fn delete(&self, txn: &Transaction<'_>, key: K) -> Result<bool, LMDBError> {
+        let key = self.full_key(key.as_ref());
+        txn.del_raw(self.db.id(), &key, None)
+    }
+
+    /// Iterates over every entry in the namespace in key order.
+    pub fn iter<'txn>(
+        &self,
+        txn: &'txn Transaction<'txn>,
+    ) -> Result<NamespacedIter<'txn, K, V>, LMDBError> {
+        self.scan(txn, &[], None)
+    }
+
+    /// Iterates over the entries in the namespace with keys in `[start, end)`.
+    pub fn range<'txn, Q>(
+        &self,
+        txn: &'txn Transaction<'txn>,
+        start: Q,
+        end: Q,
+    ) -> Result<NamespacedIter<'txn, K, V>, LMDBError>
+    where
+        Q: AsRef<[u8]>,
+    {
+        self.scan(txn, start.as_ref(), Some(end.as_ref()))
+    }
+
+    fn scan<'txn>(
+        &self,
+        txn: &'txn Transaction<'txn>,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<NamespacedIter<'txn, K, V>, LMDBError> {
+        Ok(NamespacedIter {
+            cursor: Cursor::new(txn, self.db)?,
+            txn,
+            dbi: self.db.id(),
+            prefix: self.prefix.clone(),
+            start: self.full_key(start),
+            end: end.map(|end| self.full_key(end)),
+            state: ScanState::default(),
+        })
+    }
+
+    fn full_key(&self, key: &[u8]) -> Vec<u8> {
+        [self.prefix.as_slice(), key].concat()
+    }
+}
+
+/// Iterator returned by [`Namespaced::iter`] and [`Namespaced::range`], yielding
+/// keys with the namespace prefix stripped.
+pub struct NamespacedIter<'txn, K, V> {
+    cursor: Cursor<'txn, K, V>,
+
+    txn: &'txn Transaction<'txn>,
+
+    dbi: sys::MDB_dbi,
+
+    prefix: Vec<u8>,
+
+    /// First full key to visit.
+    start: Vec<u8>,
+
+    /// Full key to stop before, if any.
+    end: Option<Vec<u8>>,
+
+    state: ScanState,
+}
+
+impl<'txn, K, V> Iterator for NamespacedIter<'txn, K, V>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(K, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (txn, dbi) = (self.txn, self.dbi);
+        let (prefix, start, end) = (&self.prefix, &self.start, &self.end);
+
+        let entry = self.state.step(
+            &mut self.cursor,
+            |cursor| cursor.seek_raw(start),
+            |key| {
+                key.starts_with(prefix)
+                    && end
+                        .as_ref()
+                        .is_none_or(|end| txn.compare_keys(dbi, key, end) == Ordering::Less)
+            },
+        )?;
+
+        Some(entry.map(|(key, value)| (K::from(&key[prefix.len()..]), V::from(value))))
+    }
+}
+
+/// A [`Database`] seen through one transaction as a sorted map, with methods
+/// named after their [`BTreeMap`](std::collections::BTreeMap) counterparts.
+///
+/// Meant for databases without `MDB_DUPSORT`; `remove` on a `MDB_DUPSORT`
+/// database deletes every duplicate of the key. Returned by
+/// [`Database::as_map`].
+pub struct OrderedMap<'a, 'env, K, V> {
+    db: &'a Database<'env, K, V>,
+
+    txn: &'a Transaction<'a>,
+}
+
+impl<'a, 'env, K, V> OrderedMap<'a, 'env, K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    pub fn inner(&self) -> &'a Database<'env, K, V> {
+        self.db
+    }
+
+    pub fn get(&self, key: K) -> Result<Option<V>, LMDBError> {
+        self.lookup(key.as_ref())
+    }
+
+    /// Stores `value` under `key`, returning the value it replaced.
+    pub fn insert(&self, key: K, value: V) -> Result<Option<V>, LMDBError> {
+        let old = self.lookup(key.as_ref())?;
+        self.txn.put_raw(
+            self.db.id(),
+            key.as_ref(),
+            value.as_ref(),
+            PutFlags::empty(),
+        )?;
+        Ok(old)
+    }
+
+    /// Deletes `key`, returning the value it had.
+    pub fn remove(&self, key: K) -> Result<Option<V>, LMDBError> {
+        let old = self.lookup(key.as_ref())?;
+        if old.is_some() {
+            self.txn.del_raw(self.db.id(), key.as_ref(), None)?;
+        }
+        Ok(old)
+    }
+
+    /// Returns the entry with the smallest key.
+    pub fn first_key_value(&self) -> Result<Option<(K, V)>, LMDBError> {
+        self.edge(sys::MDB_cursor_op::MDB_FIRST)
+    }
+
+    /// Returns the entry with the largest key.
+    pub fn last_key_value(&self) -> Result<Option<(K, V)>, LMDBError> {
+        self.edge(sys::MDB_cursor_op::MDB_LAST)
+    }
+
+    /// Iterates in key order over the entries whose keys fall in `range`.
+    ///
+    /// Bounds are compared with the database's key ordering. Unlike
+    /// `BTreeMap::range`, a range whose start is past its end is empty rather
+    /// than a panic.
+    pub fn range<Q, R>(&self, range: R) -> Result<MapRange<'a, K, V>, LMDBError>
+    where
+        Q: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let to_owned = |bound: Bound<&Q>| bound.map(|key| key.as_ref().to_vec());
+
+        Ok(MapRange {
+            cursor: Cursor::new(self.txn, self.db)?,
+            txn: self.txn,
+            dbi: self.db.id(),
+            start: to_owned(range.start_bound()),
+            end: to_owned(range.end_bound()),
+            state: ScanState::default(),
+        })
+    }
+
+    fn lookup(&self, key: &[u8]) -> Result<Option<V>, LMDBError> {
+        Ok(self.txn.get_raw(self.db.id(), key)?.map(V::from))
+    }
+
+    fn edge(&self, op: sys::MDB_cursor_op) -> Result<Option<(K, V)>, LMDBError> {
+        let mut cursor = Cursor::new(self.txn, self.db)?;
+        Ok(cursor
+            .get_raw(op, None, None)?
+            .map(|(key, value)| (K::from(key), V::from(value))))
+    }
+}
+
+/// Iterator returned by [`OrderedMap::range`].
+pub struct MapRange<'txn, K, V> {
+    cursor: Cursor<'txn, K, V>,
+
+    txn: &'txn Transaction<'txn>,
+
+    dbi: sys::MDB_dbi,
+
+    start: Bound<Vec<u8>>,
+
+    end: Bound<Vec<u8>>,
+
+    state: ScanState,
+}
+
+impl<'txn, K, V> Iterator for MapRange<'txn, K, V>
+where
+    K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    type Item = Result<(K, V), LMDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (txn, dbi) = (self.txn, self.dbi);
+        let (start, end) = (&self.start, &self.end);
+
+        let entry = self.state.step(
+            &mut self.cursor,
+            |cursor| seek_start(cursor, txn, dbi, start),
+            |key| match end {
+                Bound::Included(end) => txn.compare_keys(dbi, key, end) != Ordering::Greater,
+                Bound::Excluded(end) => txn.compare_keys(dbi, key, end) == Ordering::Less,
+                Bound::Unbounded => true,
+            },
+        )?;
+
+        Some(entry.map(|(key, value)| (K::from(key), V::from(value))))
+    }
+}
+
+/// Positions `cursor` on the first entry at or after `start`.
+fn seek_start<'c, K, V>(
+    cursor: &'c mut Cursor<'_, K, V>,
+    txn: &Transaction<'_>,
+    dbi: sys::MDB_dbi,
+    start: &Bound<Vec<u8>>,
+) -> Result<Option<RawEntry<'c>>, LMDBError>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let start = match start {
+        Bound::Included(start) => return cursor.seek_raw(start),
+        Bound::Excluded(start) => start,
+        Bound::Unbounded => return cursor.get_raw(sys::MDB_cursor_op::MDB_FIRST, None, None),
+    };
+
+    let found = cursor
+        .seek_raw(start)?
+        .map(|(key, _)| txn.compare_keys(dbi, key, start));
+    match found {
+        None => Ok(None),
+        Some(Ordering::Equal) => cursor.get_raw(sys::MDB_cursor_op::MDB_NEXT_NODUP, None, None),
+        Some(_) => cursor.get_raw(sys::MDB_cursor_op::MDB_GET_CURRENT, None, None),
+    }
+}
+
+fn check_str_key(key: &str) -> Result<(), LMDBError> {
+    if key.contains('\0') {
+        return Err(LMDBError::NulInStrKey);
+    }
+    Ok(())
 }
@@ -1,8 +1,30 @@
-use std::{ffi, fs, path::PathBuf, ptr::NonNull};
+use std::{
+    any::Any,
+    collections::HashMap,
+    env, ffi, fmt, fs,
+    mem::{self, MaybeUninit},
+    path::{Path, PathBuf},
+    process,
+    ptr::NonNull,
+    sync::{
+        Arc, Condvar, Mutex, PoisonError, RwLock, RwLockReadGuard,
+        atomic::{self, AtomicU64},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use bitflags::bitflags;
 
-use crate::{DBFlags, Database, LMDBError, Transaction, TransactionType, sys};
+use crate::{
+    Comparator, DBFlags, Database, LMDBError, ReadOnlyTransaction, Transaction, TransactionType,
+    WriteBatch,
+    cursor::{Iter, OwnedScan},
+    db::{self, RawDatabase},
+    error::MDBError,
+    sys,
+};
 
 bitflags! {
     /// Flags for the database environment.
@@ -23,46 +45,624 @@ bitflags! {
     }
 }
 
+/// How many times `begin_txn_read_only` retries a transient `MDB_BAD_RSLOT`.
+const READ_TXN_RETRIES: u32 = 3;
+
+/// Initial delay between those retries, doubled after each attempt.
+const READ_TXN_RETRY_BACKOFF: Duration = Duration::from_millis(1);
+
+/// How long `cached_stat` reuses a result unless the builder says otherwise.
+const DEFAULT_STAT_CACHE_TTL: Duration = Duration::from_secs(1);
+
 impl Default for EnvFlags {
     fn default() -> Self {
         EnvFlags::MDB_NOSUBDIR
     }
 }
 
+impl fmt::Display for EnvFlags {
+    /// Formats the set flags by name, e.g. `MDB_NOSUBDIR | MDB_RDONLY`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        bitflags::parser::to_writer(self, f)
+    }
+}
+
+/// An open LMDB environment.
+///
+/// Transactions, and through them cursors, borrow the environment, so it is
+/// only closed once everything opened from it has been dropped.
 pub struct DBEnv {
     ptr: NonNull<sys::MDB_env>,
+
+    /// Cached result of `mdb_env_get_maxkeysize`, which is fixed at compile time.
+    max_key_size: usize,
+
+    /// How long a snapshot in `stat_cache` stays fresh.
+    stat_cache_ttl: Duration,
+
+    /// Last result of `stat` taken by `cached_stat`, with the time it was taken.
+    stat_cache: Mutex<Option<(Instant, Stat)>>,
+
+    /// Lets concurrent forced syncs share one `mdb_env_sync` call.
+    sync_state: Mutex<SyncState>,
+
+    sync_done: Condvar,
+
+    /// Delete the environment's files once it is closed.
+    remove_on_drop: bool,
+
+    /// The settings the environment was opened with, reused by
+    /// `reopen_with_max_dbs`.
+    builder: DBEnvBuilder,
+
+    /// Pick up a map size grown by another process when `begin_txn` hits
+    /// `MDB_MAP_RESIZED`.
+    auto_adopt_map_size: bool,
+
+    /// Factor to grow the map by when `write_transaction` hits `MDB_MAP_FULL`.
+    auto_grow: Option<f64>,
+
+    /// Read-locked by every open top-level transaction, so the map is only
+    /// resized when none is.
+    map_lock: RwLock<()>,
+
+    /// Owns the value registered with `mdb_env_set_userctx`. Boxed twice so
+    /// LMDB can hold a thin pointer to it.
+    userctx: Option<Box<UserCtx>>,
+
+    /// Orderings registered with `register_comparator` and
+    /// `register_dup_comparator`, by database name (`None` for the main one).
+    comparators: HashMap<Option<String>, Comparators>,
+}
+
+pub(crate) type UserCtx = Box<dyn Any + Send + Sync>;
+
+// SAFETY: LMDB allows an environment handle to be shared between threads, and
+// every call made through `&DBEnv` is either thread-safe in LMDB or guarded
+// here: `mdb_dbi_open` goes through `dbi_open`, and map resizing takes
+// `map_lock` exclusively. Transactions carry their own thread restrictions.
+unsafe impl Send for DBEnv {}
+unsafe impl Sync for DBEnv {}
+
+/// Calls `mdb_dbi_open` while holding a process-wide lock.
+///
+/// LMDB forbids opening database handles from several transactions of one
+/// process at the same time, which `open_db(&self)` on a shared `DBEnv` would
+/// otherwise allow.
+///
+/// # Safety
+///
+/// `txn` must be a live transaction and `name` null or a NUL-terminated string.
+pub(crate) unsafe fn dbi_open(
+    txn: *mut sys::MDB_txn,
+    name: *const ffi::c_char,
+    flags: ffi::c_uint,
+    dbi: &mut sys::MDB_dbi,
+) -> ffi::c_int {
+    static DBI_OPEN: Mutex<()> = Mutex::new(());
+
+    let _guard = DBI_OPEN.lock().unwrap_or_else(PoisonError::into_inner);
+    unsafe { db::with_compare_ctx(txn, || sys::mdb_dbi_open(txn, name, flags, dbi)) }
+}
+
+/// B-tree statistics returned by [`DBEnv::stat`] and [`DBEnv::cached_stat`].
+#[derive(Debug, Clone, Copy)]
+pub struct Stat(sys::MDB_stat);
+
+impl Stat {
+    /// Size of a database page in bytes.
+    pub fn page_size(&self) -> u32 {
+        self.0.ms_psize
+    }
+
+    /// Depth (height) of the B-tree.
+    pub fn depth(&self) -> u32 {
+        self.0.ms_depth
+    }
+
+    /// Number of internal (non-leaf) pages.
+    pub fn branch_pages(&self) -> usize {
+        self.0.ms_branch_pages
+    }
+
+    /// Number of leaf pages.
+    pub fn leaf_pages(&self) -> usize {
+        self.0.ms_leaf_pages
+    }
+
+    /// Number of overflow pages, which hold values too large for a leaf page.
+    pub fn overflow_pages(&self) -> usize {
+        self.0.ms_overflow_pages
+    }
+
+    /// Number of data items.
+    pub fn entries(&self) -> usize {
+        self.0.ms_entries
+    }
+}
+
+/// Environment information returned by [`DBEnv::info`].
+#[derive(Debug, Clone, Copy)]
+pub struct EnvInfo {
+    map_size: usize,
+
+    last_pgno: usize,
+
+    last_txnid: usize,
+
+    max_readers: u32,
+
+    num_readers: u32,
+}
+
+impl EnvInfo {
+    /// Size of the memory map in bytes.
+    pub fn map_size(&self) -> usize {
+        self.map_size
+    }
+
+    /// Number of the last page in use in the data file.
+    pub fn last_pgno(&self) -> usize {
+        self.last_pgno
+    }
+
+    /// ID of the last committed write transaction. It only changes when a
+    /// write is committed, so it tells whether anything changed since.
+    pub fn last_txnid(&self) -> usize {
+        self.last_txnid
+    }
+
+    /// Number of slots in the reader table.
+    pub fn max_readers(&self) -> u32 {
+        self.max_readers
+    }
+
+    /// Number of reader slots used so far, including ones whose readers have
+    /// since finished.
+    pub fn num_readers(&self) -> u32 {
+        self.num_readers
+    }
+}
+
+/// Outcome of [`DBEnv::verify_integrity`].
+#[derive(Debug, Default)]
+pub struct IntegrityReport {
+    /// Number of databases scanned, counting the main one.
+    pub databases: usize,
+
+    /// Number of entries read before the scan finished or stopped.
+    pub entries: u64,
+
+    /// The first corruption found, if any. The scan stops there.
+    pub problem: Option<IntegrityProblem>,
+}
+
+impl IntegrityReport {
+    /// Whether every database was read end-to-end without corruption.
+    pub fn is_clean(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Where [`DBEnv::verify_integrity`] hit corruption.
+#[derive(Debug)]
+pub struct IntegrityProblem {
+    /// The database being scanned, or `None` for the main database.
+    pub database: Option<String>,
+
+    /// The last key read successfully before the error, or `None` if the
+    /// database couldn't be read at all.
+    pub last_good_key: Option<Vec<u8>>,
+
+    /// The `MDB_CORRUPTED` or `MDB_PAGE_NOTFOUND` error LMDB reported.
+    pub error: LMDBError,
+}
+
+/// Custom orderings applied to a database each time it is opened.
+#[derive(Clone, Copy, Default)]
+struct Comparators {
+    key: sys::MDB_cmp_func,
+
+    dup: sys::MDB_cmp_func,
+}
+
+/// Bookkeeping for coalesced forced syncs.
+#[derive(Default)]
+struct SyncState {
+    /// Number of forced syncs started so far.
+    started: u64,
+
+    /// Number of forced syncs finished so far.
+    finished: u64,
+
+    /// Whether a forced sync is in flight.
+    running: bool,
+
+    /// Return code of the most recently finished forced sync.
+    last_ret: i32,
+
+    /// Callers currently waiting for a flush in flight, so tests can tell
+    /// when they have all queued up.
+    #[cfg(test)]
+    waiting: usize,
+}
+
+/// Runs `attempt`, retrying up to `READ_TXN_RETRIES` times with a doubling
+/// backoff while it fails with `MDB_BAD_RSLOT`.
+fn retry_bad_rslot<T>(mut attempt: impl FnMut() -> Result<T, LMDBError>) -> Result<T, LMDBError> {
+    let mut backoff = READ_TXN_RETRY_BACKOFF;
+    for _ in 0..READ_TXN_RETRIES {
+        match attempt() {
+            Err(LMDBError::MDB(MDBError::BadRslot)) => {
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+    attempt()
+}
+
+/// Callback set with `DBEnvBuilder::on_dirty_drop`.
+#[derive(Clone)]
+struct DirtyDropHook(Arc<dyn Fn(usize) + Send + Sync>);
+
+impl fmt::Debug for DirtyDropHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DirtyDropHook")
+    }
 }
 
 #[allow(unused)]
 impl DBEnv {
-    pub(super) fn from_ptr(ptr: NonNull<sys::MDB_env>) -> Self {
-        Self { ptr }
+    pub(super) fn from_ptr(ptr: NonNull<sys::MDB_env>, builder: DBEnvBuilder) -> Self {
+        let max_key_size = unsafe { sys::mdb_env_get_maxkeysize(ptr.as_ptr()) } as usize;
+
+        Self {
+            ptr,
+            max_key_size,
+            stat_cache_ttl: DEFAULT_STAT_CACHE_TTL,
+            stat_cache: Mutex::new(None),
+            sync_state: Mutex::new(SyncState::default()),
+            sync_done: Condvar::new(),
+            remove_on_drop: false,
+            builder,
+            auto_adopt_map_size: false,
+            auto_grow: None,
+            map_lock: RwLock::new(()),
+            userctx: None,
+            comparators: HashMap::new(),
+        }
+    }
+
+    /// The callback set with `DBEnvBuilder::on_dirty_drop`, if any.
+    pub(crate) fn dirty_drop_hook(&self) -> Option<&(dyn Fn(usize) + Send + Sync)> {
+        self.builder.dirty_drop_hook.as_ref().map(|hook| &*hook.0)
+    }
+
+    /// Returns the maximum size of a key (and of data in `MDB_DUPSORT` databases).
+    pub fn max_key_size(&self) -> usize {
+        self.max_key_size
     }
 
+    /// Flushes the data buffers to disk.
+    ///
+    /// Concurrent forced syncs are coalesced: each caller waits for a flush
+    /// that started after it called, and callers arriving while one is in
+    /// flight share the next flush and its result instead of queueing their own.
     pub fn sync(&self, force: bool) -> Result<(), LMDBError> {
-        let force = if force { 1 } else { 0 };
+        if !force {
+            let ret = unsafe { sys::mdb_env_sync(self.as_raw_ptr(), 0) };
+            return LMDBError::from_mdb_error(ret);
+        }
+
+        let mut state = self
+            .sync_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // A flush already in flight may have started before our writes
+        let target = state.started + 1;
+
+        while state.finished < target {
+            if state.running {
+                #[cfg(test)]
+                {
+                    state.waiting += 1;
+                }
+                state = self
+                    .sync_done
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                #[cfg(test)]
+                {
+                    state.waiting -= 1;
+                }
+                continue;
+            }
+
+            state.started += 1;
+            state.running = true;
+            drop(state);
+
+            let ret = unsafe { sys::mdb_env_sync(self.as_raw_ptr(), 1) };
+
+            state = self
+                .sync_state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            state.finished = state.started;
+            state.running = false;
+            state.last_ret = ret;
+            self.sync_done.notify_all();
+        }
+
+        LMDBError::from_mdb_error(state.last_ret)
+    }
+
+    /// Returns the flags the environment was opened with.
+    pub fn flags(&self) -> Result<EnvFlags, LMDBError> {
+        let mut flags: ffi::c_uint = 0;
+
+        let ret = unsafe { sys::mdb_env_get_flags(self.as_raw_ptr(), &mut flags) };
+        LMDBError::from_mdb_error(ret)?;
 
-        let ret = unsafe { sys::mdb_env_sync(self.as_raw_ptr(), force) };
+        Ok(EnvFlags::from_bits_truncate(flags))
+    }
+
+    /// Returns statistics for the environment's main database.
+    pub fn stat(&self) -> Result<Stat, LMDBError> {
+        let mut stat = MaybeUninit::<sys::MDB_stat>::uninit();
+
+        let ret = unsafe { sys::mdb_env_stat(self.as_raw_ptr(), stat.as_mut_ptr()) };
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(Stat(unsafe { stat.assume_init() }))
+    }
+
+    /// Returns information about the environment (`mdb_env_info`).
+    pub fn info(&self) -> Result<EnvInfo, LMDBError> {
+        let info = self.raw_info()?;
+
+        Ok(EnvInfo {
+            map_size: info.me_mapsize,
+            last_pgno: info.me_last_pgno,
+            last_txnid: info.me_last_txnid,
+            max_readers: info.me_maxreaders,
+            num_readers: info.me_numreaders,
+        })
+    }
+
+    /// Like `stat`, but reuses the previous result until it is older than the
+    /// TTL set with `DBEnvBuilder::set_stat_cache_ttl` (one second by default).
+    ///
+    /// Meant for monitoring loops that poll far more often than the numbers
+    /// meaningfully change.
+    pub fn cached_stat(&self) -> Result<Stat, LMDBError> {
+        let mut cache = self
+            .stat_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Some((taken_at, stat)) = *cache
+            && taken_at.elapsed() < self.stat_cache_ttl
+        {
+            return Ok(stat);
+        }
+
+        let stat = self.stat()?;
+        *cache = Some((Instant::now(), stat));
+        Ok(stat)
+    }
+
+    /// Forces the next `cached_stat` call to query LMDB again.
+    pub fn invalidate_stat(&self) {
+        *self
+            .stat_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Returns the snapshot id held by the oldest active reader, or `None` if
+    /// no read transaction is open.
+    ///
+    /// Pages freed after this snapshot can't be reused while it is held, so an
+    /// old reader is the usual reason a database keeps growing.
+    pub fn oldest_reader_txnid(&self) -> Result<Option<usize>, LMDBError> {
+        Ok(self.reader_txnids()?.into_iter().min())
+    }
+
+    /// Returns how many transactions the oldest active reader is behind the
+    /// last committed one, or `None` if no read transaction is open.
+    pub fn lag_vs_latest(&self) -> Result<Option<usize>, LMDBError> {
+        let Some(oldest) = self.oldest_reader_txnid()? else {
+            return Ok(None);
+        };
+
+        let last_txnid = self.raw_info()?.me_last_txnid;
+        Ok(Some(last_txnid.saturating_sub(oldest)))
+    }
+
+    /// Estimates how long until the memory map fills up if the data keeps
+    /// growing by `growth_bytes_per_sec`.
+    ///
+    /// Used space counts every page up to the highest one in use, including
+    /// pages on the freelist, so this errs on the early side. Returns `None`
+    /// if the growth rate is not positive.
+    pub fn projected_full(&self, growth_bytes_per_sec: f64) -> Result<Option<Duration>, LMDBError> {
+        if growth_bytes_per_sec.is_nan() || growth_bytes_per_sec <= 0.0 {
+            return Ok(None);
+        }
+
+        let info = self.raw_info()?;
+        let page_size = self.stat()?.page_size() as usize;
+        let used = (info.me_last_pgno + 1).saturating_mul(page_size);
+        let free = info.me_mapsize.saturating_sub(used);
+
+        Ok(Duration::try_from_secs_f64(free as f64 / growth_bytes_per_sec).ok())
+    }
+
+    /// Begins a write transaction.
+    ///
+    /// With `DBEnvBuilder::auto_adopt_map_size`, an `MDB_MAP_RESIZED` error,
+    /// meaning another process grew the map, is handled by adopting the new
+    /// size and retrying once. That is only possible while this handle has no
+    /// other transaction open; otherwise the error is returned.
+    pub fn begin_txn(&self) -> Result<Transaction<'_>, LMDBError> {
+        self.begin_top_level(TransactionType::ReadWrite)
+    }
+
+    /// Begins a transaction with no parent, adopting a resized map if enabled.
+    fn begin_top_level(&self, txn_type: TransactionType) -> Result<Transaction<'_>, LMDBError> {
+        match Transaction::new(self, None, txn_type) {
+            Err(LMDBError::MDB(MDBError::MapResized)) if self.auto_adopt_map_size => {
+                self.adopt_map_size()?;
+                Transaction::new(self, None, txn_type)
+            }
+            result => result,
+        }
+    }
+
+    /// Takes a share of the map lock for a new top-level transaction to hold
+    /// while it is open.
+    pub(crate) fn map_share(&self) -> RwLockReadGuard<'_, ()> {
+        self.map_lock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Changes the size of the memory map (`mdb_env_set_mapsize`).
+    ///
+    /// A size smaller than the data in use is raised to fit it, and zero adopts
+    /// the size last recorded in the data file. Remapping would pull the map
+    /// out from under open transactions, so this fails with
+    /// `LMDBError::MapResizeBusy` while any transaction of this handle is
+    /// open.
+    pub fn set_map_size(&self, size: usize) -> Result<(), LMDBError> {
+        let Ok(_guard) = self.map_lock.try_write() else {
+            return Err(LMDBError::MapResizeBusy);
+        };
+
+        let ret = unsafe { sys::mdb_env_set_mapsize(self.as_raw_ptr(), size) };
         LMDBError::from_mdb_error(ret)
     }
 
-    pub fn stat(&self) -> Result<sys::MDB_stat, LMDBError> {
-        todo!()
+    /// Grows the map by the `auto_grow` factor, returning whether it did. It
+    /// doesn't while other transactions are open.
+    fn grow_map(&self) -> Result<bool, LMDBError> {
+        let Some(factor) = self.auto_grow else {
+            return Ok(false);
+        };
+        let Ok(_guard) = self.map_lock.try_write() else {
+            return Ok(false);
+        };
+
+        let size = self.raw_info()?.me_mapsize;
+        let grown = ((size as f64 * factor) as usize).max(size + 1);
+        let ret = unsafe { sys::mdb_env_set_mapsize(self.as_raw_ptr(), grown) };
+        LMDBError::from_mdb_error(ret)?;
+        Ok(true)
+    }
+
+    /// Resizes the map to the size recorded in the data file by whichever
+    /// process last grew it.
+    fn adopt_map_size(&self) -> Result<(), LMDBError> {
+        // Remapping would leave open transactions pointing into the old map
+        let Ok(_guard) = self.map_lock.try_write() else {
+            return Err(LMDBError::MDB(MDBError::MapResized));
+        };
+
+        let ret = unsafe { sys::mdb_env_set_mapsize(self.as_raw_ptr(), 0) };
+        LMDBError::from_mdb_error(ret)
     }
 
-    pub fn begin_txn(&self) -> Result<Transaction, LMDBError> {
-        Transaction::new(self, None, TransactionType::ReadWrite)
+    /// Begins a read-only transaction.
+    ///
+    /// The transaction reads from one snapshot of the whole environment, so
+    /// every database it reads reflects the same commit, whatever writers do
+    /// meanwhile.
+    ///
+    /// With `MDB_NOTLS`, a transient `MDB_BAD_RSLOT` from a recycled reader
+    /// slot is retried a few times with a short backoff before being
+    /// returned. Without it, `MDB_BAD_RSLOT` means the thread's reader slot
+    /// is still taken by another read transaction, so it fails immediately
+    /// with `LMDBError::ReadTxnActive`, since retrying can't resolve it.
+    /// `MDB_MAP_RESIZED` is handled as for `begin_txn`.
+    pub fn begin_txn_read_only(&self) -> Result<ReadOnlyTransaction<'_>, LMDBError> {
+        retry_bad_rslot(|| self.begin_top_level(TransactionType::ReadOnly))
+            .map(ReadOnlyTransaction::new)
     }
 
-    pub fn begin_txn_read_only(&self) -> Result<Transaction, LMDBError> {
-        Transaction::new(self, None, TransactionType::ReadOnly)
+    /// Applies every operation in `batch` in a single write transaction.
+    ///
+    /// Either all of them take effect or, if any fails, none do. The map is
+    /// grown as for `write_transaction`.
+    pub fn apply_batch(&self, batch: &WriteBatch) -> Result<(), LMDBError> {
+        self.write_transaction(|txn| batch.apply(txn))
+    }
+
+    /// Runs `f` in a new write transaction and commits it, returning what `f`
+    /// returned. If `f` fails, the transaction is aborted.
+    ///
+    /// With `DBEnvBuilder::auto_grow`, an `MDB_MAP_FULL` error from `f` or the
+    /// commit grows the map and runs `f` again in a fresh transaction, so `f`
+    /// should have no effects outside it. The map can't grow while another
+    /// transaction of this handle is open; the error is returned instead.
+    pub fn write_transaction<R, F>(&self, mut f: F) -> Result<R, LMDBError>
+    where
+        F: FnMut(&Transaction<'_>) -> Result<R, LMDBError>,
+    {
+        loop {
+            let txn = self.begin_txn()?;
+            let result = match f(&txn) {
+                Ok(value) => txn.commit().map(|()| value),
+                Err(e) => {
+                    txn.abort();
+                    Err(e)
+                }
+            };
+
+            match result {
+                Err(e) if e.is_map_full() && self.grow_map()? => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Opens the named databases `names` in one read-only transaction and
+    /// passes them, in the same order, to `f` along with the transaction.
+    ///
+    /// Everything `f` reads comes from a single consistent snapshot across
+    /// all of the databases. The transaction is aborted when `f` returns.
+    /// Databases are opened, not created, so a name that doesn't exist fails
+    /// with `MDB_NOTFOUND`.
+    pub fn read_transaction<K, V, R, F>(&self, names: &[&str], f: F) -> Result<R, LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        F: FnOnce(&ReadOnlyTransaction<'_>, &[Database<'_, K, V>]) -> Result<R, LMDBError>,
+    {
+        let txn = self.begin_txn_read_only()?;
+        let dbs = names
+            .iter()
+            .map(|name| self.open_named_db(&txn, name, Some(DBFlags::empty())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        f(&txn, &dbs)
+    }
+
+    /// Starts a scan over every entry of `db` in a new read-only transaction
+    /// owned by the returned iterator.
+    pub fn scan<K, V>(&self, db: &Database<K, V>) -> Result<OwnedScan<'_, K, V>, LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        OwnedScan::new(self, db)
     }
 
     pub fn open_db<K, V>(
         &self,
         txn: &'_ Transaction,
         flags: Option<DBFlags>,
-    ) -> Result<Database<K, V>, LMDBError>
+    ) -> Result<Database<'_, K, V>, LMDBError>
     where
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
@@ -75,7 +675,7 @@ impl DBEnv {
         txn: &'_ Transaction,
         name: S,
         flags: Option<DBFlags>,
-    ) -> Result<Database<K, V>, LMDBError>
+    ) -> Result<Database<'_, K, V>, LMDBError>
     where
         S: AsRef<str>,
         K: AsRef<[u8]>,
@@ -84,18 +684,33 @@ impl DBEnv {
         self.open_db_internal(txn, Some(name), flags)
     }
 
+    /// Opens every database in `specs`, creating any that don't exist, in one
+    /// write transaction that is committed before returning.
+    ///
+    /// Run this once at startup to declare the schema. Because creation
+    /// happens under LMDB's single writer lock, threads that later open these
+    /// databases never race to create them.
+    pub fn ensure_databases(&self, specs: &[(Option<&str>, DBFlags)]) -> Result<(), LMDBError> {
+        let txn = self.begin_txn()?;
+        for &(name, flags) in specs {
+            let _: RawDatabase<'_> =
+                self.open_db_internal(&txn, name, Some(flags | DBFlags::MDB_CREATE))?;
+        }
+        txn.commit()
+    }
+
     fn open_db_internal<S, K, V>(
         &self,
         txn: &'_ Transaction,
         name: Option<S>,
         flags: Option<DBFlags>,
-    ) -> Result<Database<K, V>, LMDBError>
+    ) -> Result<Database<'_, K, V>, LMDBError>
     where
         S: AsRef<str>,
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
-        let flags = flags.unwrap_or(DBFlags::default());
+        let flags = flags.unwrap_or_default();
 
         let name_cstr = name
             .map(|n| {
@@ -111,26 +726,465 @@ impl DBEnv {
 
         let mut dbi: sys::MDB_dbi = Default::default();
 
-        let ret = unsafe { sys::mdb_dbi_open(txn.as_raw_ptr(), name_ptr, flags.bits(), &mut dbi) };
+        let ret = unsafe { dbi_open(txn.as_raw_ptr(), name_ptr, flags.bits(), &mut dbi) };
         LMDBError::from_mdb_error(ret)?;
 
-        Ok(Database::from_dbi(
-            dbi,
-            name_cstr.map(|s| s.into_string().unwrap()),
-        ))
+        let name = name_cstr.map(|s| s.into_string().unwrap());
+        self.apply_comparators(txn, dbi, &name)?;
+
+        Ok(Database::from_dbi(dbi, name))
+    }
+
+    /// Installs the orderings registered for the database `name` on `dbi`,
+    /// which has just been opened in `txn`.
+    fn apply_comparators(
+        &self,
+        txn: &Transaction<'_>,
+        dbi: sys::MDB_dbi,
+        name: &Option<String>,
+    ) -> Result<(), LMDBError> {
+        let Some(comparators) = self.comparators.get(name) else {
+            return Ok(());
+        };
+
+        if comparators.key.is_some() {
+            let ret = unsafe { sys::mdb_set_compare(txn.as_raw_ptr(), dbi, comparators.key) };
+            LMDBError::from_mdb_error(ret)?;
+        }
+        if comparators.dup.is_some() {
+            let ret = unsafe { sys::mdb_set_dupsort(txn.as_raw_ptr(), dbi, comparators.dup) };
+            LMDBError::from_mdb_error(ret)?;
+        }
+        Ok(())
+    }
+
+    /// Sums the sizes of every key and value in the main database, giving the
+    /// logical data size as opposed to the pages the map actually uses.
+    ///
+    /// With `include_named`, records in the main database that are named
+    /// databases are replaced by the total of that database's own entries.
+    /// Those databases are opened (but not created) to read them, which counts
+    /// against `max_dbs`. This walks every entry, so it costs O(n).
+    pub fn total_data_size(
+        &self,
+        txn: &Transaction<'_>,
+        include_named: bool,
+    ) -> Result<u64, LMDBError> {
+        let main: RawDatabase<'_> = self.open_db(txn, Some(DBFlags::empty()))?;
+        let mut total = 0;
+
+        let mut iter = Iter::new(txn, &main)?;
+        while let Some(entry) = iter.next_raw() {
+            let (key, value) = entry?;
+            let named = if include_named {
+                self.open_existing_db(txn, key)?
+            } else {
+                None
+            };
+            total += match named {
+                Some(db) => Self::entry_sizes(txn, &db)?,
+                None => (key.len() + value.len()) as u64,
+            };
+        }
+
+        Ok(total)
     }
 
-    pub fn drop_db<S>(&self, name: Option<S>) -> Result<(), LMDBError>
+    /// Returns the average number of entries on each leaf page of `db`, from
+    /// its `mdb_stat`, or `0.0` if it has no leaf pages yet.
+    ///
+    /// Low values mean large keys or values are leaving pages mostly empty.
+    pub fn avg_entries_per_leaf<K, V>(
+        &self,
+        txn: &Transaction<'_>,
+        db: &Database<K, V>,
+    ) -> Result<f64, LMDBError>
     where
-        S: AsRef<str>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
     {
-        todo!()
+        let stat = txn.db_stat_raw(db.id())?;
+        if stat.ms_leaf_pages == 0 {
+            return Ok(0.0);
+        }
+
+        Ok(stat.ms_entries as f64 / stat.ms_leaf_pages as f64)
+    }
+
+    /// Opens the named database `name` if a database by that name exists.
+    fn open_existing_db<'txn>(
+        &self,
+        txn: &'txn Transaction<'_>,
+        name: &[u8],
+    ) -> Result<Option<RawDatabase<'txn>>, LMDBError> {
+        // A key holding a NUL byte can't be a database name
+        let Ok(name) = ffi::CString::new(name) else {
+            return Ok(None);
+        };
+        let mut dbi: sys::MDB_dbi = Default::default();
+
+        let ret = unsafe { dbi_open(txn.as_raw_ptr(), name.as_ptr(), 0, &mut dbi) };
+        if ret == sys::MDB_NOTFOUND || ret == sys::MDB_INCOMPATIBLE {
+            return Ok(None);
+        }
+        LMDBError::from_mdb_error(ret)?;
+
+        let name = name.into_string().ok();
+        self.apply_comparators(txn, dbi, &name)?;
+
+        Ok(Some(Database::from_dbi(dbi, name)))
+    }
+
+    /// Sums key and value sizes over every entry of `db`.
+    fn entry_sizes(txn: &Transaction<'_>, db: &RawDatabase<'_>) -> Result<u64, LMDBError> {
+        let mut total = 0;
+
+        let mut iter = Iter::new(txn, db)?;
+        while let Some(entry) = iter.next_raw() {
+            let (key, value) = entry?;
+            total += (key.len() + value.len()) as u64;
+        }
+
+        Ok(total)
+    }
+
+    /// Reads every entry of the main database and of each named database in
+    /// a fresh read-only transaction, reporting the first `MDB_CORRUPTED` or
+    /// `MDB_PAGE_NOTFOUND` hit along the way.
+    ///
+    /// This touches every page in use, so it costs O(total data) in time and
+    /// I/O; schedule it like a backup rather than calling it on a hot path.
+    /// Named databases are opened to read them, which counts against
+    /// `max_dbs`. Errors other than corruption are returned as-is.
+    pub fn verify_integrity(&self) -> Result<IntegrityReport, LMDBError> {
+        let txn = self.begin_txn_read_only()?;
+        let mut report = IntegrityReport::default();
+        let mut position = (None, None);
+
+        match self.scan_integrity(&txn, &mut report, &mut position) {
+            Ok(()) => {}
+            Err(error @ LMDBError::MDB(MDBError::Corrupted | MDBError::PageNotFound)) => {
+                let (database, last_good_key) = position;
+                report.problem = Some(IntegrityProblem {
+                    database,
+                    last_good_key,
+                    error,
+                });
+            }
+            Err(err) => return Err(err),
+        }
+
+        Ok(report)
+    }
+
+    /// Walks the main database and every named database in it for
+    /// `verify_integrity`, keeping `position` at the last key read.
+    fn scan_integrity(
+        &self,
+        txn: &Transaction<'_>,
+        report: &mut IntegrityReport,
+        position: &mut (Option<String>, Option<Vec<u8>>),
+    ) -> Result<(), LMDBError> {
+        let main: RawDatabase<'_> = self.open_db(txn, Some(DBFlags::empty()))?;
+        report.databases += 1;
+
+        let mut iter = Iter::new(txn, &main)?;
+        while let Some(entry) = iter.next_raw() {
+            let (key, _) = entry?;
+            report.entries += 1;
+            *position = (None, Some(key.to_vec()));
+
+            if let Some(db) = self.open_existing_db(txn, key)? {
+                report.databases += 1;
+                *position = (db.name().map(str::to_owned), None);
+
+                let mut inner = Iter::new(txn, &db)?;
+                while let Some(item) = inner.next_raw() {
+                    let (key, _) = item?;
+                    report.entries += 1;
+                    position.1 = Some(key.to_vec());
+                }
+
+                *position = (None, Some(key.to_vec()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn raw_info(&self) -> Result<sys::MDB_envinfo, LMDBError> {
+        let mut info = MaybeUninit::<sys::MDB_envinfo>::uninit();
+
+        let ret = unsafe { sys::mdb_env_info(self.as_raw_ptr(), info.as_mut_ptr()) };
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(unsafe { info.assume_init() })
+    }
+
+    /// Clears reader slots left behind by dead processes or threads
+    /// (`mdb_reader_check`) and returns how many were cleared.
+    ///
+    /// Such slots pin old snapshots and eventually cause `MDB_READERS_FULL`.
+    pub fn reader_check(&self) -> Result<usize, LMDBError> {
+        let mut dead: ffi::c_int = 0;
+
+        let ret = unsafe { sys::mdb_reader_check(self.as_raw_ptr(), &mut dead) };
+        LMDBError::from_mdb_error(ret)?;
+
+        Ok(dead as usize)
+    }
+
+    /// Starts a background thread that clears stale reader slots every
+    /// `interval` until the returned handle is dropped.
+    ///
+    /// Stale slots are left by processes that died with a read transaction
+    /// open and pin old snapshots. The thread only holds a weak reference, so
+    /// it stops on its own once the last `Arc<DBEnv>` is gone.
+    pub fn spawn_reader_reaper(self: &Arc<Self>, interval: Duration) -> ReaperHandle {
+        let env = Arc::downgrade(self);
+        let passes = Arc::new(AtomicU64::new(0));
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let thread_passes = Arc::clone(&passes);
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let Some(env) = env.upgrade() else {
+                    break;
+                };
+                // A failed pass is retried at the next interval
+                let _ = env.reader_check();
+                thread_passes.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+        });
+
+        ReaperHandle {
+            stop: Some(stop_tx),
+            thread: Some(thread),
+            passes,
+        }
+    }
+
+    /// Returns the reader table as LMDB prints it (`mdb_reader_list`), the
+    /// same listing as `mdb_stat -r`.
+    ///
+    /// After a header line, each slot gets a line with the process id, thread
+    /// id and snapshot id of its reader, with `-` for a slot not in a
+    /// transaction.
+    pub fn reader_list(&self) -> Result<String, LMDBError> {
+        unsafe extern "C" fn collect(msg: *const ffi::c_char, ctx: *mut ffi::c_void) -> ffi::c_int {
+            let out = unsafe { &mut *(ctx as *mut String) };
+            out.push_str(&unsafe { ffi::CStr::from_ptr(msg) }.to_string_lossy());
+            0
+        }
+
+        let mut out = String::new();
+        let ret = unsafe {
+            sys::mdb_reader_list(
+                self.as_raw_ptr(),
+                Some(collect),
+                &mut out as *mut String as *mut ffi::c_void,
+            )
+        };
+        if ret < 0 {
+            return Err(LMDBError::Io(std::io::Error::other(
+                "mdb_reader_list failed to list the reader table",
+            )));
+        }
+
+        Ok(out)
+    }
+
+    /// Collects the snapshot ids of all active readers from the reader table.
+    fn reader_txnids(&self) -> Result<Vec<usize>, LMDBError> {
+        // The header, idle slots and "(no active readers)" don't parse as ids
+        let txnids = self
+            .reader_list()?
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(2)?.parse().ok())
+            .collect();
+        Ok(txnids)
+    }
+
+    /// Writes a consistent copy of the environment to `path` while it stays in
+    /// use, e.g. for a hot backup (`mdb_env_copy`).
+    ///
+    /// `path` is a directory that must exist and hold no LMDB files, or, if
+    /// the environment was opened with `MDB_NOSUBDIR`, the data file to
+    /// create. The copy reads from a snapshot, so writers carry on meanwhile.
+    pub fn copy_to<P: AsRef<Path>>(&self, path: P) -> Result<(), LMDBError> {
+        self.copy_with_flags(path.as_ref(), 0)
+    }
+
+    /// Like `copy_to`, but compacts the copy (`MDB_CP_COMPACT`): free pages
+    /// are left out and the rest renumbered, so the backup is smaller, at the
+    /// cost of more work while copying.
+    pub fn copy_to_compact<P: AsRef<Path>>(&self, path: P) -> Result<(), LMDBError> {
+        self.copy_with_flags(path.as_ref(), sys::MDB_CP_COMPACT)
+    }
+
+    fn copy_with_flags(&self, path: &Path, flags: ffi::c_uint) -> Result<(), LMDBError> {
+        let path_cstr = ffi::CString::new(path.to_string_lossy().as_bytes()).map_err(|_| {
+            LMDBError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invalid path for LMDB environment copy",
+            ))
+        })?;
+
+        let ret = unsafe { sys::mdb_env_copy2(self.as_raw_ptr(), path_cstr.as_ptr(), flags) };
+        LMDBError::from_mdb_error(ret)
+    }
+
+    /// Closes the environment now instead of when it is dropped.
+    ///
+    /// All transactions and databases borrow the environment, so they must be
+    /// gone before this can be called. Once it returns, the data and lock files
+    /// are no longer held open and may be removed.
+    pub fn close(self) {
+        drop(self);
+    }
+
+    /// Closes the environment and opens the same path again with room for
+    /// `new_max` named databases, keeping its flags, current map size and
+    /// every `DBEnvBuilder` setting it was opened with, along with the user
+    /// context and registered comparators.
+    ///
+    /// This is the way out of `MDB_DBS_FULL`, since `max_dbs` can only be set
+    /// before an environment is opened. If reopening fails, the old
+    /// environment is already closed.
+    pub fn reopen_with_max_dbs(mut self, new_max: usize) -> Result<DBEnv, LMDBError> {
+        let flags = self.flags()?;
+        let map_size = self.raw_info()?.me_mapsize;
+
+        let mut builder = self.builder.clone();
+        builder.set_map_size(map_size).set_max_dbs(new_max);
+
+        // The files must survive closing the old handle
+        self.remove_on_drop = false;
+        let comparators = mem::take(&mut self.comparators);
+        let userctx = self.userctx.take();
+        drop(self);
+
+        let mut env = builder.open(Some(flags))?;
+        env.comparators = comparators;
+        if let Some(ctx) = userctx {
+            env.install_userctx(ctx)?;
+        }
+        Ok(env)
+    }
+
+    /// Orders the keys of the database `name` (`None` for the main database)
+    /// with `C` instead of LMDB's bytewise comparison.
+    ///
+    /// LMDB forgets custom orderings when a database handle is closed, so the
+    /// ordering is kept here and installed every time `open_db`,
+    /// `open_named_db` and friends open that database. Register it before the
+    /// database is first opened; a handle opened earlier keeps the ordering it
+    /// was opened with.
+    pub fn register_comparator<C: Comparator>(&mut self, name: Option<&str>) {
+        let comparators = self.comparators.entry(name.map(str::to_owned)).or_default();
+        comparators.key = Some(db::compare_with::<C>);
+    }
+
+    /// Like `register_comparator`, but orders the duplicates of each key in an
+    /// `MDB_DUPSORT` database.
+    pub fn register_dup_comparator<C: Comparator>(&mut self, name: Option<&str>) {
+        let comparators = self.comparators.entry(name.map(str::to_owned)).or_default();
+        comparators.dup = Some(db::compare_with::<C>);
+    }
+
+    /// Attaches `ctx` to the environment as its LMDB user context, replacing
+    /// (and dropping) any previous one. It is freed when the environment is.
+    ///
+    /// Registered [`Comparator`]s receive it as their `ctx` argument, and
+    /// callbacks that LMDB hands the `MDB_env` pointer, such as the assert
+    /// callback, can get it back with `userctx_from_raw`.
+    pub fn set_userctx<T: Any + Send + Sync>(&mut self, ctx: T) -> Result<(), LMDBError> {
+        self.install_userctx(Box::new(Box::new(ctx)))
+    }
+
+    fn install_userctx(&mut self, ctx: Box<UserCtx>) -> Result<(), LMDBError> {
+        let ctx_ptr = &*ctx as *const UserCtx as *mut ffi::c_void;
+        let ret = unsafe { sys::mdb_env_set_userctx(self.as_raw_ptr(), ctx_ptr) };
+        LMDBError::from_mdb_error(ret)?;
+
+        self.userctx = Some(ctx);
+        Ok(())
+    }
+
+    /// Returns the user context if one is set and it is a `T`.
+    pub fn get_userctx<T: Any>(&self) -> Option<&T> {
+        self.userctx.as_ref()?.downcast_ref()
+    }
+
+    /// Returns the user context of the environment behind a raw pointer, for
+    /// use inside LMDB callbacks, if one is set and it is a `T`.
+    ///
+    /// # Safety
+    ///
+    /// `env` must belong to a live `DBEnv` whose user context was only ever set
+    /// through `set_userctx`, and the result must not outlive that `DBEnv` or
+    /// the next `set_userctx` call on it.
+    pub unsafe fn userctx_from_raw<'a, T: Any>(env: *mut sys::MDB_env) -> Option<&'a T> {
+        let ctx = unsafe { sys::mdb_env_get_userctx(env) } as *const UserCtx;
+        if ctx.is_null() {
+            return None;
+        }
+        unsafe { &*ctx }.downcast_ref()
+    }
+
+    /// Returns the path the environment was opened with.
+    pub fn path(&self) -> Result<PathBuf, LMDBError> {
+        let mut path: *const ffi::c_char = std::ptr::null();
+
+        let ret = unsafe { sys::mdb_env_get_path(self.as_raw_ptr(), &mut path) };
+        LMDBError::from_mdb_error(ret)?;
+        if path.is_null() {
+            return Err(LMDBError::Io(std::io::Error::other(
+                "the environment has not been opened",
+            )));
+        }
+
+        let path = unsafe { ffi::CStr::from_ptr(path) };
+        Ok(PathBuf::from(path.to_string_lossy().into_owned()))
+    }
+
+    /// Returns the path of the lock file LMDB keeps next to the data, or `None`
+    /// if the environment was opened with `MDB_NOLOCK` and has none.
+    ///
+    /// With `MDB_NOSUBDIR` the lock file is `<path>-lock` beside the data file;
+    /// otherwise it is `lock.mdb` inside the environment directory. LMDB
+    /// creates it on open and never deletes it: it is safe to remove once no
+    /// process has the environment open, or use
+    /// `DBEnvBuilder::set_remove_on_drop` to have it removed on close.
+    pub fn lock_file_path(&self) -> Option<PathBuf> {
+        self.file_paths().and_then(|(_, lock)| lock)
+    }
+
+    /// Paths of the data file and, unless `MDB_NOLOCK` is set, the lock file.
+    fn file_paths(&self) -> Option<(PathBuf, Option<PathBuf>)> {
+        let flags = self.flags().ok()?;
+        let path = self.path().ok()?;
+
+        let (data, lock) = if flags.contains(EnvFlags::MDB_NOSUBDIR) {
+            let mut lock = path.clone().into_os_string();
+            lock.push("-lock");
+            (path, PathBuf::from(lock))
+        } else {
+            (path.join("data.mdb"), path.join("lock.mdb"))
+        };
+        let lock = (!flags.contains(EnvFlags::MDB_NOLOCK)).then_some(lock);
+
+        Some((data, lock))
     }
 
     pub fn as_ptr(&self) -> NonNull<sys::MDB_env> {
         self.ptr
     }
 
+    /// # Safety
+    ///
+    /// The returned pointer is only valid while this `DBEnv` is alive and must
+    /// not be passed to `mdb_env_close`.
     pub unsafe fn as_raw_ptr(&self) -> *mut sys::MDB_env {
         self.ptr.as_ptr()
     }
@@ -138,9 +1192,51 @@ impl DBEnv {
 
 impl Drop for DBEnv {
     fn drop(&mut self) {
+        let files = if self.remove_on_drop {
+            self.file_paths()
+        } else {
+            None
+        };
+
         unsafe {
             sys::mdb_env_close(self.ptr.as_ptr());
         }
+
+        // Best effort: there is no way to report a failure from `drop`
+        if let Some((data, lock)) = files {
+            let _ = fs::remove_file(&data);
+            if let Some(lock) = lock {
+                let _ = fs::remove_file(lock);
+            }
+        }
+    }
+}
+
+/// Handle to the thread started by `DBEnv::spawn_reader_reaper`.
+///
+/// Dropping it stops the thread and waits for it to exit.
+pub struct ReaperHandle {
+    stop: Option<mpsc::Sender<()>>,
+
+    thread: Option<thread::JoinHandle<()>>,
+
+    passes: Arc<AtomicU64>,
+}
+
+impl ReaperHandle {
+    /// Number of reader checks the thread has run so far.
+    pub fn passes(&self) -> u64 {
+        self.passes.load(atomic::Ordering::Relaxed)
+    }
+}
+
+impl Drop for ReaperHandle {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the thread and ends its loop
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
     }
 }
 
@@ -155,6 +1251,28 @@ pub struct DBEnvBuilder {
     max_readers: Option<usize>,
 
     max_dbs: Option<usize>,
+
+    write_map: bool,
+
+    stat_cache_ttl: Option<Duration>,
+
+    /// Flags always added to the ones passed to `open`.
+    extra_flags: EnvFlags,
+
+    remove_on_drop: bool,
+
+    allow_remote_fs: bool,
+
+    /// Mount table consulted for the `allow_remote_fs` check, so tests can
+    /// use a synthetic listing.
+    #[cfg(test)]
+    mounts_file: PathBuf,
+
+    auto_adopt_map_size: bool,
+
+    auto_grow: Option<f64>,
+
+    dirty_drop_hook: Option<DirtyDropHook>,
 }
 
 impl DBEnvBuilder {
@@ -165,9 +1283,47 @@ impl DBEnvBuilder {
             map_size: None,
             max_readers: None,
             max_dbs: None,
+            write_map: false,
+            stat_cache_ttl: None,
+            extra_flags: EnvFlags::empty(),
+            remove_on_drop: false,
+            allow_remote_fs: false,
+            #[cfg(test)]
+            mounts_file: PathBuf::from(MOUNTS_FILE),
+            auto_adopt_map_size: false,
+            auto_grow: None,
+            dirty_drop_hook: None,
         }
     }
 
+    /// A builder for a throwaway environment in a fresh file under the system
+    /// temp directory, tuned for speed over durability.
+    ///
+    /// It uses `MDB_WRITEMAP`, `MDB_NOSYNC` and `MDB_NOMETASYNC`, so a crash can
+    /// lose or corrupt recent writes. Meant for tests and caches that are
+    /// rebuilt on restart. The files are removed when the environment is
+    /// closed; call `set_remove_on_drop(false)` to keep them.
+    pub fn ephemeral() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let name = format!(
+            "rlmdb-ephemeral-{}-{}-{}.mdb",
+            process::id(),
+            nanos,
+            NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed)
+        );
+
+        let mut builder = Self::new(env::temp_dir().join(name));
+        builder.write_map = true;
+        builder.remove_on_drop = true;
+        builder.extra_flags =
+            EnvFlags::MDB_NOSUBDIR | EnvFlags::MDB_NOSYNC | EnvFlags::MDB_NOMETASYNC;
+        builder
+    }
+
     pub fn set_file_mode(&mut self, mode: fs::Permissions) -> &mut Self {
         self.file_mode = Some(mode);
         self
@@ -188,9 +1344,110 @@ impl DBEnvBuilder {
         self
     }
 
+    /// Opens the environment with `MDB_WRITEMAP`, writing through a writable
+    /// memory map instead of `write(2)` calls.
+    ///
+    /// This is faster, but a stray write through a pointer into the map can
+    /// corrupt the database, and nested write transactions are rejected with
+    /// `LMDBError::NestedTxnUnsupported`. Combined with `MDB_RDONLY`, `open`
+    /// fails with `LMDBError::WriteMapReadOnly`.
+    pub fn set_write_map(&mut self, enabled: bool) -> &mut Self {
+        self.write_map = enabled;
+        self
+    }
+
+    /// Sets how long `DBEnv::cached_stat` reuses a previous result.
+    pub fn set_stat_cache_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.stat_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Deletes the data and lock files when the environment is dropped or
+    /// closed. The directory of a non-`MDB_NOSUBDIR` environment is kept.
+    ///
+    /// Only for throwaway stores: nothing stops another process that still has
+    /// the environment open from losing its data.
+    pub fn set_remove_on_drop(&mut self, enabled: bool) -> &mut Self {
+        self.remove_on_drop = enabled;
+        self
+    }
+
+    /// Makes `DBEnv::begin_txn` and `DBEnv::begin_txn_read_only` adopt a map
+    /// size grown by another process and retry, instead of failing with
+    /// `MDB_MAP_RESIZED`.
+    ///
+    /// The map is never resized under an open transaction of this handle.
+    pub fn auto_adopt_map_size(&mut self, enabled: bool) -> &mut Self {
+        self.auto_adopt_map_size = enabled;
+        self
+    }
+
+    /// Makes `DBEnv::write_transaction` and `DBEnv::apply_batch` multiply the
+    /// map size by `factor` and retry when a write fails with `MDB_MAP_FULL`.
+    ///
+    /// `factor` must be greater than 1, or `open` fails with
+    /// `LMDBError::InvalidGrowFactor`.
+    pub fn auto_grow(&mut self, factor: f64) -> &mut Self {
+        self.auto_grow = Some(factor);
+        self
+    }
+
+    /// Lets `open` proceed on a network filesystem such as NFS or CIFS.
+    ///
+    /// LMDB's locking and memory map are not reliable over the network, so a
+    /// shared environment there can be silently corrupted. Only enable this
+    /// when a single process uses the environment, e.g. with `MDB_NOLOCK`.
+    pub fn allow_remote_fs(&mut self, allow: bool) -> &mut Self {
+        self.allow_remote_fs = allow;
+        self
+    }
+
+    /// Calls `hook` with the transaction id whenever a write transaction is
+    /// dropped with uncommitted changes, which is usually a forgotten
+    /// `commit`. Transactions ended with `commit` or `abort`, or dropped
+    /// during a panic, don't trigger it.
+    pub fn on_dirty_drop<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.dirty_drop_hook = Some(DirtyDropHook(Arc::new(hook)));
+        self
+    }
+
+    /// The mount table to check `db_path` against.
+    fn mounts_file(&self) -> &Path {
+        #[cfg(test)]
+        return &self.mounts_file;
+        #[cfg(not(test))]
+        Path::new(MOUNTS_FILE)
+    }
+
     /// Builds the `DBEnv` with the specified flags.
+    ///
+    /// Fails with `LMDBError::RemoteFilesystem` if the environment is on a
+    /// network filesystem, unless `allow_remote_fs` is set. The check is best
+    /// effort and currently Linux only.
     pub fn open(&self, flags: Option<EnvFlags>) -> Result<DBEnv, LMDBError> {
-        let flags = flags.unwrap_or_else(|| EnvFlags::default());
+        let mut flags = flags.unwrap_or_default() | self.extra_flags;
+        if !self.allow_remote_fs
+            && let Some(fs_type) = remote_fs_type(&self.db_path, self.mounts_file())
+        {
+            return Err(LMDBError::RemoteFilesystem {
+                path: self.db_path.clone(),
+                fs_type,
+            });
+        }
+        if let Some(factor) = self.auto_grow
+            && !(factor > 1.0 && factor.is_finite())
+        {
+            return Err(LMDBError::InvalidGrowFactor { factor });
+        }
+        if self.write_map {
+            if flags.contains(EnvFlags::MDB_RDONLY) {
+                return Err(LMDBError::WriteMapReadOnly);
+            }
+            flags |= EnvFlags::MDB_WRITEMAP;
+        }
 
         let path_cstr =
             ffi::CString::new(self.db_path.to_string_lossy().as_bytes()).map_err(|_| {
@@ -205,8 +1462,7 @@ impl DBEnvBuilder {
         let ret = unsafe { sys::mdb_env_create(&mut env_ptr) };
         LMDBError::from_mdb_error(ret)?;
         let env_ptr = NonNull::new(env_ptr).ok_or_else(|| {
-            LMDBError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            LMDBError::Io(std::io::Error::other(
                 "mdb_env_create succeeded but returned a null environment pointer",
             ))
         })?;
@@ -226,7 +1482,13 @@ impl DBEnvBuilder {
             LMDBError::from_mdb_error(ret)?;
         }
 
-        let env = DBEnv::from_ptr(env_ptr);
+        let mut env = DBEnv::from_ptr(env_ptr, self.clone());
+        if let Some(ttl) = self.stat_cache_ttl {
+            env.stat_cache_ttl = ttl;
+        }
+        env.remove_on_drop = self.remove_on_drop;
+        env.auto_adopt_map_size = self.auto_adopt_map_size;
+        env.auto_grow = self.auto_grow;
 
         #[cfg(unix)]
         let file_mode = {
@@ -244,8 +1506,256 @@ impl DBEnvBuilder {
                 file_mode,
             )
         };
-        LMDBError::from_mdb_error(ret)?;
+        match ret {
+            sys::MDB_VERSION_MISMATCH => Err(LMDBError::EnvVersionMismatch {
+                path: self.db_path.clone(),
+            }),
+            sys::MDB_INVALID => Err(LMDBError::EnvInvalid {
+                path: self.db_path.clone(),
+            }),
+            _ => LMDBError::from_mdb_error(ret).map(|()| env),
+        }
+    }
+}
 
-        Ok(env)
+/// Filesystem types, as named in `/proc/mounts`, that are served over the network.
+const REMOTE_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afs",
+    "ceph",
+    "9p",
+    "fuse.sshfs",
+    "fuse.glusterfs",
+];
+
+/// Where Linux lists the mounts visible to this process.
+const MOUNTS_FILE: &str = "/proc/self/mounts";
+
+/// Returns the type of the network filesystem `path` is on, according to the
+/// mount table in `mounts_file`, if any. Without a mount table, as on systems
+/// other than Linux, nothing is reported.
+fn remote_fs_type(path: &Path, mounts_file: &Path) -> Option<String> {
+    // The data file may not exist yet, but its directory must
+    let path = match path.canonicalize() {
+        Ok(path) => path,
+        Err(_) => {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+            dir.unwrap_or(Path::new(".")).canonicalize().ok()?
+        }
+    };
+    let mounts = fs::read_to_string(mounts_file).ok()?;
+    remote_fs_in_mounts(&mounts, &path)
+}
+
+/// Finds the mount holding the absolute `path` in a `/proc/mounts` listing and
+/// returns its type if it is a network filesystem.
+fn remote_fs_in_mounts(mounts: &str, path: &Path) -> Option<String> {
+    let mut best: Option<(usize, &str)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(_source), Some(mount_point), Some(fs_type)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        let mount_point = PathBuf::from(unescape_mount_field(mount_point));
+        if !path.starts_with(&mount_point) {
+            continue;
+        }
+        // Later mounts on the same point shadow earlier ones
+        let depth = mount_point.components().count();
+        if best.is_none_or(|(best_depth, _)| depth >= best_depth) {
+            best = Some((depth, fs_type));
+        }
+    }
+
+    best.map(|(_, fs_type)| fs_type)
+        .filter(|fs_type| REMOTE_FS_TYPES.contains(fs_type))
+        .map(str::to_owned)
+}
+
+/// Decodes the `\ooo` octal escapes `/proc/mounts` uses for whitespace and `\\`.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+        match escape.and_then(|digits| u8::from_str_radix(str::from_utf8(digits).ok()?, 8).ok()) {
+            Some(b) => {
+                out.push(b);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("rlmdb-unit-{}-{name}.mdb", process::id()))
+    }
+
+    fn temp_env(name: &str) -> DBEnv {
+        let mut builder = DBEnvBuilder::new(temp_path(name));
+        builder.set_remove_on_drop(true);
+        builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap()
+    }
+
+    #[test]
+    fn concurrent_syncs_share_one_flush() {
+        let env = temp_env("sync_coalesce");
+        let txn = env.begin_txn().unwrap();
+        let db = env.open_db::<String, String>(&txn, None).unwrap();
+        for i in 0..8 {
+            txn.put(&db, format!("k{i}"), "v".to_owned(), None).unwrap();
+        }
+        txn.commit().unwrap();
+
+        // Pretend a flush is in flight so every caller queues behind it
+        {
+            let mut state = env.sync_state.lock().unwrap();
+            state.started = 1;
+            state.running = true;
+        }
+        thread::scope(|s| {
+            let callers: Vec<_> = (0..8).map(|_| s.spawn(|| env.sync(true))).collect();
+
+            // Finish the pretend flush once all eight are waiting on it
+            let mut state = env.sync_state.lock().unwrap();
+            while state.waiting < 8 {
+                drop(state);
+                thread::yield_now();
+                state = env.sync_state.lock().unwrap();
+            }
+            state.finished = 1;
+            state.running = false;
+            env.sync_done.notify_all();
+            drop(state);
+
+            for caller in callers {
+                caller.join().unwrap().unwrap();
+            }
+        });
+
+        // One real flush covered all eight callers
+        assert_eq!(env.sync_state.lock().unwrap().started, 2);
+        assert_eq!(env.stat().unwrap().entries(), 8);
+    }
+
+    #[test]
+    fn bad_rslot_is_retried_a_bounded_number_of_times() {
+        let mut attempts = 0;
+        let result = retry_bad_rslot(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(LMDBError::MDB(MDBError::BadRslot))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_bad_rslot(|| {
+            attempts += 1;
+            Err(LMDBError::MDB(MDBError::BadRslot))
+        });
+        assert!(matches!(result, Err(LMDBError::MDB(MDBError::BadRslot))));
+        assert_eq!(attempts, READ_TXN_RETRIES + 1);
+
+        // Other errors are returned at once
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_bad_rslot(|| {
+            attempts += 1;
+            Err(LMDBError::ReadTxnActive)
+        });
+        assert!(matches!(result, Err(LMDBError::ReadTxnActive)));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn remote_fs_in_mounts_uses_the_deepest_mount() {
+        let mounts = "\
+server:/export / nfs4 rw,relatime 0 0
+/dev/sda1 /local ext4 rw,relatime 0 0
+//host/share /local/share cifs rw 0 0
+/dev/sdb1 /with\\040space ext4 rw 0 0
+";
+        let fs_type = |path| remote_fs_in_mounts(mounts, Path::new(path));
+
+        assert_eq!(fs_type("/data/env.mdb").as_deref(), Some("nfs4"));
+        assert_eq!(fs_type("/local/env.mdb"), None);
+        assert_eq!(fs_type("/local/share/env.mdb").as_deref(), Some("cifs"));
+        assert_eq!(fs_type("/localish/env.mdb").as_deref(), Some("nfs4"));
+        assert_eq!(fs_type("/with space/env.mdb"), None);
+        assert_eq!(remote_fs_in_mounts("", Path::new("/env.mdb")), None);
+    }
+
+    #[test]
+    fn open_refuses_remote_fs_unless_allowed() {
+        let mounts_file = temp_path("nfs_mounts");
+        fs::write(&mounts_file, "server:/export / nfs rw,relatime 0 0\n").unwrap();
+
+        let path = temp_path("remote_fs");
+        let mut builder = DBEnvBuilder::new(&path);
+        builder.set_remove_on_drop(true);
+        builder.mounts_file = mounts_file.clone();
+
+        let err = builder.open(Some(EnvFlags::MDB_NOSUBDIR)).err().unwrap();
+        assert!(
+            matches!(
+                &err,
+                LMDBError::RemoteFilesystem { path: p, fs_type } if *p == path && fs_type == "nfs"
+            ),
+            "{err:?}"
+        );
+
+        builder.allow_remote_fs(true);
+        builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap();
+        fs::remove_file(mounts_file).unwrap();
+    }
+
+    #[test]
+    fn reopen_with_max_dbs_keeps_builder_settings() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mounts_file = temp_path("nfs_mounts_reopen");
+        fs::write(&mounts_file, "server:/export / nfs rw,relatime 0 0\n").unwrap();
+
+        let path = temp_path("reopen_remote_fs");
+        let mut builder = DBEnvBuilder::new(&path);
+        builder
+            .set_remove_on_drop(true)
+            .set_file_mode(fs::Permissions::from_mode(0o600))
+            .set_max_readers(7)
+            .allow_remote_fs(true);
+        builder.mounts_file = mounts_file.clone();
+        let env = builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap();
+
+        let env = env.reopen_with_max_dbs(4).unwrap();
+        assert_eq!(env.builder.max_dbs, Some(4));
+        assert_eq!(env.builder.file_mode.as_ref().unwrap().mode(), 0o600);
+        assert!(env.builder.allow_remote_fs);
+        assert_eq!(env.builder.mounts_file, mounts_file);
+        let mut max_readers: ffi::c_uint = 0;
+        let ret = unsafe { sys::mdb_env_get_maxreaders(env.as_raw_ptr(), &mut max_readers) };
+        LMDBError::from_mdb_error(ret).unwrap();
+        assert_eq!(max_readers, 7);
+        drop(env);
+        assert!(!path.exists());
+        fs::remove_file(mounts_file).unwrap();
     }
 }
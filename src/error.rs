@@ -1,4 +1,4 @@
-use std::io;
+use std::{ffi::CStr, io, path::PathBuf};
 
 use crate::sys;
 
@@ -11,6 +11,179 @@ pub enum LMDBError {
     /// An underlying I/O error occurred (mapped from standard C errno).
     #[error(transparent)]
     Io(#[from] io::Error),
+
+    /// A read-only transaction is already active on this thread, and the
+    /// environment was opened without `MDB_NOTLS` so LMDB ties each thread
+    /// to a single reader slot.
+    #[error(
+        "a read-only transaction is already active on this thread; open the environment with MDB_NOTLS to allow more than one"
+    )]
+    ReadTxnActive,
+
+    /// The key is longer than the environment's maximum key size.
+    #[error("key is {len} bytes, which exceeds the maximum key size of {max} bytes")]
+    KeyTooLarge { len: usize, max: usize },
+
+    /// An `MDB_APPEND` or `MDB_APPENDDUP` write hit an entry that doesn't sort
+    /// after the ones already stored. `position` is the entry's index in the
+    /// input and `key` its key bytes.
+    #[error(
+        "entry {position} (key \"{}\") is out of order for MDB_APPEND; sort the input in database key order",
+        .key.escape_ascii()
+    )]
+    AppendOutOfOrder { position: usize, key: Vec<u8> },
+
+    /// `mdb_env_open` returned `MDB_VERSION_MISMATCH` for the file at `path`.
+    #[error(
+        "{}: environment was created by an incompatible LMDB version (MDB_VERSION_MISMATCH); open it with a matching LMDB build, or dump and reload it",
+        .path.display()
+    )]
+    EnvVersionMismatch { path: PathBuf },
+
+    /// `mdb_env_open` returned `MDB_INVALID` for the file at `path`.
+    #[error(
+        "{}: not an LMDB environment (MDB_INVALID); check the path, and that MDB_NOSUBDIR matches how the environment was created",
+        .path.display()
+    )]
+    EnvInvalid { path: PathBuf },
+
+    /// The environment at `path` is on a network filesystem of type `fs_type`,
+    /// and `DBEnvBuilder::allow_remote_fs` was not set.
+    #[error(
+        "{}: environment is on a {fs_type} network filesystem, where LMDB's locking is unreliable; use a local disk, or allow_remote_fs(true) if only one process opens it",
+        .path.display()
+    )]
+    RemoteFilesystem { path: PathBuf, fs_type: String },
+
+    /// A nested write transaction was requested in an environment opened with
+    /// `MDB_WRITEMAP`, which LMDB doesn't support.
+    #[error(
+        "nested write transactions are not supported with MDB_WRITEMAP; use a flat transaction or open the environment without it"
+    )]
+    NestedTxnUnsupported,
+
+    /// `Transaction::begin_child` was called on a read-only transaction; LMDB
+    /// only nests transactions inside write transactions.
+    #[error("child transactions can only be nested in a write transaction")]
+    ChildOfReadOnlyTxn,
+
+    /// `Transaction::put_versioned` expected the key to be at version
+    /// `expected`, but it is at `actual`.
+    #[error("version conflict: expected version {expected}, found {actual}")]
+    VersionConflict { expected: u64, actual: u64 },
+
+    /// `PutPolicy::NoDupData` was used on a database opened without
+    /// `MDB_DUPSORT`, where LMDB would silently ignore it.
+    #[error("PutPolicy::NoDupData requires a database opened with MDB_DUPSORT")]
+    DupSortRequired,
+
+    /// `Transaction::append_auto` was used on a database opened without
+    /// `MDB_INTEGERKEY`.
+    #[error("append_auto requires a database opened with MDB_INTEGERKEY")]
+    NotIntegerKeyDb,
+
+    /// `Transaction::append_auto` found a last key of `len` bytes where it
+    /// expected an 8-byte integer.
+    #[error("last key is {len} bytes, expected an 8-byte integer")]
+    MalformedIntegerKey { len: usize },
+
+    /// `Transaction::append_auto` found `u64::MAX` already in use, so there
+    /// is no next key.
+    #[error("integer key space exhausted")]
+    IntegerKeySpaceExhausted,
+
+    /// `Transaction::put_chunks` was given chunks adding up to `actual`
+    /// bytes with a `total_len` of `expected`.
+    #[error("chunks add up to {actual} bytes, but total_len is {expected}")]
+    ChunkLengthMismatch { expected: usize, actual: usize },
+
+    /// `DBEnv::set_map_size` was called while transactions of the same handle
+    /// were open; remapping would pull the map out from under them.
+    #[error("the map can't be resized while transactions are open")]
+    MapResizeBusy,
+
+    /// `DBEnvBuilder::auto_grow` was given a `factor` that isn't a finite
+    /// number above 1.
+    #[error("auto_grow factor must be a finite number above 1, not {factor}")]
+    InvalidGrowFactor { factor: f64 },
+
+    /// `DBEnvBuilder::set_write_map` was combined with `MDB_RDONLY`, which
+    /// LMDB can't map writably.
+    #[error("MDB_WRITEMAP cannot be used with a read-only environment")]
+    WriteMapReadOnly,
+
+    /// A borrowed read was attempted in a write transaction, whose pages can
+    /// be rewritten while the borrow is alive; read into an owned type.
+    #[error("borrowed reads are only allowed in read-only transactions; read into an owned type")]
+    BorrowedReadInWriteTxn,
+
+    /// A `StrDatabase` key contains a NUL byte, which string keys may not.
+    #[error("string keys must not contain NUL bytes")]
+    NulInStrKey,
+
+    /// A value read by `Transaction::get_versioned` or `put_versioned` is
+    /// `len` bytes, too short to carry the 8-byte version `put_versioned`
+    /// writes.
+    #[error("stored value is {len} bytes, too short to carry a version number")]
+    MissingVersion { len: usize },
+
+    /// `CompositeKey::decode` was given bytes that `CompositeKey::new`
+    /// couldn't have produced.
+    #[error("malformed composite key")]
+    MalformedCompositeKey,
+}
+
+/// Broad class of an [`LMDBError`], for deciding whether to retry, alert or
+/// pass the error on. Returned by [`LMDBError::category`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The database files are damaged or not LMDB files; alert an operator.
+    Corruption,
+
+    /// A resource limit was hit; retrying after growing the map, shrinking
+    /// the transaction or waiting for readers may succeed.
+    Transient,
+
+    /// The request itself was wrong, e.g. a missing or duplicate key or an
+    /// oversized key.
+    UserError,
+
+    /// Anything else, including I/O errors.
+    Other,
+}
+
+/// A system error code (C `errno`) returned by an LMDB call.
+///
+/// Unknown codes surface as `LMDBError::Io` wrapping this type, so the
+/// operating system's description stays reachable through `source()`. The
+/// wrapping `io::Error` isn't built from the raw code, so its
+/// `raw_os_error()` is `None`; use [`LMDBError::raw_os_error`] instead. The
+/// message includes `mdb_strerror`'s text, which also covers LMDB codes newer
+/// than [`MDBError`].
+#[derive(Debug, thiserror::Error)]
+#[error("LMDB call failed with error code {code}: {message}")]
+pub struct ErrnoError {
+    code: i32,
+
+    message: String,
+
+    #[source]
+    source: io::Error,
+}
+
+impl ErrnoError {
+    fn new(code: i32) -> Self {
+        Self {
+            code,
+            message: strerror(code),
+            source: io::Error::from_raw_os_error(code),
+        }
+    }
+
+    /// The raw `errno` value.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
 }
 
 /// LMDB ffi error type.
@@ -102,38 +275,176 @@ pub enum MDBError {
     BadDbi,
 }
 
+impl MDBError {
+    /// Returns the LMDB return code for this error, e.g. `MDB_NOTFOUND`.
+    pub fn code(&self) -> i32 {
+        match self {
+            MDBError::KeyExists => sys::MDB_KEYEXIST,
+            MDBError::NotFound => sys::MDB_NOTFOUND,
+            MDBError::PageNotFound => sys::MDB_PAGE_NOTFOUND,
+            MDBError::Corrupted => sys::MDB_CORRUPTED,
+            MDBError::Panic => sys::MDB_PANIC,
+            MDBError::VersionMismatch => sys::MDB_VERSION_MISMATCH,
+            MDBError::Invalid => sys::MDB_INVALID,
+            MDBError::MapFull => sys::MDB_MAP_FULL,
+            MDBError::DbsFull => sys::MDB_DBS_FULL,
+            MDBError::ReadersFull => sys::MDB_READERS_FULL,
+            MDBError::TlsFull => sys::MDB_TLS_FULL,
+            MDBError::TxnFull => sys::MDB_TXN_FULL,
+            MDBError::CursorFull => sys::MDB_CURSOR_FULL,
+            MDBError::PageFull => sys::MDB_PAGE_FULL,
+            MDBError::MapResized => sys::MDB_MAP_RESIZED,
+            MDBError::Incompatible => sys::MDB_INCOMPATIBLE,
+            MDBError::BadRslot => sys::MDB_BAD_RSLOT,
+            MDBError::BadTxn => sys::MDB_BAD_TXN,
+            MDBError::BadValSize => sys::MDB_BAD_VALSIZE,
+            MDBError::BadDbi => sys::MDB_BAD_DBI,
+        }
+    }
+}
+
 impl LMDBError {
+    /// Classifies the error; see [`ErrorCategory`].
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            LMDBError::MDB(err) => match err {
+                MDBError::Corrupted
+                | MDBError::PageNotFound
+                | MDBError::Panic
+                | MDBError::Invalid => ErrorCategory::Corruption,
+                MDBError::MapFull
+                | MDBError::MapResized
+                | MDBError::TxnFull
+                | MDBError::ReadersFull => ErrorCategory::Transient,
+                MDBError::KeyExists | MDBError::NotFound | MDBError::BadValSize => {
+                    ErrorCategory::UserError
+                }
+                _ => ErrorCategory::Other,
+            },
+            LMDBError::EnvInvalid { .. } => ErrorCategory::Corruption,
+            LMDBError::MapResizeBusy => ErrorCategory::Transient,
+            LMDBError::ReadTxnActive
+            | LMDBError::KeyTooLarge { .. }
+            | LMDBError::AppendOutOfOrder { .. }
+            | LMDBError::RemoteFilesystem { .. }
+            | LMDBError::NestedTxnUnsupported
+            | LMDBError::ChildOfReadOnlyTxn
+            | LMDBError::VersionConflict { .. }
+            | LMDBError::DupSortRequired
+            | LMDBError::NotIntegerKeyDb
+            | LMDBError::ChunkLengthMismatch { .. }
+            | LMDBError::InvalidGrowFactor { .. }
+            | LMDBError::WriteMapReadOnly
+            | LMDBError::BorrowedReadInWriteTxn
+            | LMDBError::NulInStrKey => ErrorCategory::UserError,
+            LMDBError::Io(_)
+            | LMDBError::EnvVersionMismatch { .. }
+            | LMDBError::MalformedIntegerKey { .. }
+            | LMDBError::IntegerKeySpaceExhausted
+            | LMDBError::MissingVersion { .. }
+            | LMDBError::MalformedCompositeKey => ErrorCategory::Other,
+        }
+    }
+
+    /// Whether this is `MDB_NOTFOUND`, i.e. the key or entry is absent.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, LMDBError::MDB(MDBError::NotFound))
+    }
+
+    /// Whether this is `MDB_KEYEXIST`, e.g. from an `MDB_NOOVERWRITE` put.
+    pub fn is_key_exists(&self) -> bool {
+        matches!(self, LMDBError::MDB(MDBError::KeyExists))
+    }
+
+    /// Whether this is `MDB_MAP_FULL`, meaning the map size must grow.
+    pub fn is_map_full(&self) -> bool {
+        matches!(self, LMDBError::MDB(MDBError::MapFull))
+    }
+
+    /// Returns the system error code (C `errno`) behind an `Io` error, like
+    /// [`io::Error::raw_os_error`], but also for codes returned by LMDB calls,
+    /// whose `io::Error` wraps an [`ErrnoError`].
+    pub fn raw_os_error(&self) -> Option<i32> {
+        let LMDBError::Io(err) = self else {
+            return None;
+        };
+        err.get_ref()
+            .and_then(|inner| inner.downcast_ref::<ErrnoError>())
+            .map(ErrnoError::code)
+            .or_else(|| err.raw_os_error())
+    }
+
+    /// Returns the LMDB or system error code behind the error, if it came from
+    /// one.
+    ///
+    /// `EnvVersionMismatch` and `EnvInvalid` report the LMDB code they were
+    /// raised for; errors detected by this crate itself return `None`.
+    pub fn raw_code(&self) -> Option<i32> {
+        match self {
+            LMDBError::MDB(err) => Some(err.code()),
+            LMDBError::Io(_) => self.raw_os_error(),
+            LMDBError::EnvVersionMismatch { .. } => Some(sys::MDB_VERSION_MISMATCH),
+            LMDBError::EnvInvalid { .. } => Some(sys::MDB_INVALID),
+            _ => None,
+        }
+    }
+
+    /// Describes the error in LMDB's own words (`mdb_strerror`) when it came
+    /// from an error code, so the text matches the linked LMDB release; other
+    /// errors use their `Display` text.
+    pub fn description(&self) -> String {
+        match self.raw_code() {
+            Some(code) => strerror(code),
+            None => self.to_string(),
+        }
+    }
+
+    /// Builds the error for a nonzero LMDB or system error code, the inverse
+    /// of [`raw_code`](Self::raw_code).
+    pub fn from_raw(code: i32) -> Self {
+        let mdb_err = match code {
+            sys::MDB_KEYEXIST => MDBError::KeyExists,
+            sys::MDB_NOTFOUND => MDBError::NotFound,
+            sys::MDB_PAGE_NOTFOUND => MDBError::PageNotFound,
+            sys::MDB_CORRUPTED => MDBError::Corrupted,
+            sys::MDB_PANIC => MDBError::Panic,
+            sys::MDB_VERSION_MISMATCH => MDBError::VersionMismatch,
+            sys::MDB_INVALID => MDBError::Invalid,
+            sys::MDB_MAP_FULL => MDBError::MapFull,
+            sys::MDB_DBS_FULL => MDBError::DbsFull,
+            sys::MDB_READERS_FULL => MDBError::ReadersFull,
+            sys::MDB_TLS_FULL => MDBError::TlsFull,
+            sys::MDB_TXN_FULL => MDBError::TxnFull,
+            sys::MDB_CURSOR_FULL => MDBError::CursorFull,
+            sys::MDB_PAGE_FULL => MDBError::PageFull,
+            sys::MDB_MAP_RESIZED => MDBError::MapResized,
+            sys::MDB_INCOMPATIBLE => MDBError::Incompatible,
+            sys::MDB_BAD_RSLOT => MDBError::BadRslot,
+            sys::MDB_BAD_TXN => MDBError::BadTxn,
+            sys::MDB_BAD_VALSIZE => MDBError::BadValSize,
+            sys::MDB_BAD_DBI => MDBError::BadDbi,
+            _ => {
+                let errno = ErrnoError::new(code);
+                let io_err = io::Error::new(errno.source.kind(), errno);
+                return LMDBError::Io(io_err);
+            }
+        };
+        LMDBError::MDB(mdb_err)
+    }
+
     pub fn from_mdb_error(err_code: i32) -> Result<(), Self> {
         if err_code == sys::MDB_SUCCESS as i32 {
             Ok(())
         } else {
-            let mdb_err = match err_code {
-                sys::MDB_KEYEXIST => MDBError::KeyExists,
-                sys::MDB_NOTFOUND => MDBError::NotFound,
-                sys::MDB_PAGE_NOTFOUND => MDBError::PageNotFound,
-                sys::MDB_CORRUPTED => MDBError::Corrupted,
-                sys::MDB_PANIC => MDBError::Panic,
-                sys::MDB_VERSION_MISMATCH => MDBError::VersionMismatch,
-                sys::MDB_INVALID => MDBError::Invalid,
-                sys::MDB_MAP_FULL => MDBError::MapFull,
-                sys::MDB_DBS_FULL => MDBError::DbsFull,
-                sys::MDB_READERS_FULL => MDBError::ReadersFull,
-                sys::MDB_TLS_FULL => MDBError::TlsFull,
-                sys::MDB_TXN_FULL => MDBError::TxnFull,
-                sys::MDB_CURSOR_FULL => MDBError::CursorFull,
-                sys::MDB_PAGE_FULL => MDBError::PageFull,
-                sys::MDB_MAP_RESIZED => MDBError::MapResized,
-                sys::MDB_INCOMPATIBLE => MDBError::Incompatible,
-                sys::MDB_BAD_RSLOT => MDBError::BadRslot,
-                sys::MDB_BAD_TXN => MDBError::BadTxn,
-                sys::MDB_BAD_VALSIZE => MDBError::BadValSize,
-                sys::MDB_BAD_DBI => MDBError::BadDbi,
-                _ => {
-                    let io_err = io::Error::from_raw_os_error(err_code);
-                    return Err(LMDBError::Io(io_err));
-                }
-            };
-            Err(LMDBError::MDB(mdb_err))
+            Err(Self::from_raw(err_code))
         }
     }
 }
+
+/// Returns LMDB's description of `code`, which may be an LMDB or system code.
+fn strerror(code: i32) -> String {
+    // mdb_strerror returns a static string, or strerror's for system codes
+    unsafe { CStr::from_ptr(sys::mdb_strerror(code)) }
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,84 @@
+use crate::{DBFlags, Database, LMDBError, PutFlags, Transaction, error::MDBError};
+
+/// Keeps a secondary index (value → primary key) in step with a primary database.
+///
+/// Every write goes through both databases in the caller's transaction, so the
+/// index can't drift from the primary data as long as all writes to the primary
+/// database use this helper. If the index database was opened with
+/// `MDB_DUPSORT`, several primary keys may share a value; otherwise values must
+/// be unique and `put` fails with `MDB_KEYEXIST` when a value is already
+/// indexed under another key.
+///
+/// An error may leave the transaction partially updated, so abort it on failure.
+pub struct SecondaryIndex<'env, K, V> {
+    primary: &'env Database<'env, K, V>,
+
+    index: &'env Database<'env, V, K>,
+}
+
+impl<'env, K, V> SecondaryIndex<'env, K, V>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    pub fn new(primary: &'env Database<'env, K, V>, index: &'env Database<'env, V, K>) -> Self {
+        Self { primary, index }
+    }
+
+    pub fn primary(&self) -> &'env Database<'env, K, V> {
+        self.primary
+    }
+
+    pub fn index(&self) -> &'env Database<'env, V, K> {
+        self.index
+    }
+
+    /// Stores `value` under `key` and indexes `key` under `value`, replacing the
+    /// index entry for any previous value of `key`.
+    pub fn put(&self, txn: &Transaction<'env>, key: K, value: V) -> Result<(), LMDBError> {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        let dupsort = txn.db_flags(self.index)?.contains(DBFlags::MDB_DUPSORT);
+
+        if !dupsort
+            && let Some(owner) = txn.get_raw(self.index.id(), value)?
+            && owner != key
+        {
+            return Err(LMDBError::MDB(MDBError::KeyExists));
+        }
+
+        // Copy the old value out of the map before the writes below invalidate it
+        let old_value = txn.get_raw(self.primary.id(), key)?.map(<[u8]>::to_vec);
+        if old_value.as_deref() == Some(value) {
+            return Ok(());
+        }
+        if let Some(old_value) = old_value {
+            txn.del_raw(self.index.id(), &old_value, Some(key))?;
+        }
+
+        txn.put_raw(self.primary.id(), key, value, PutFlags::empty())?;
+        txn.put_raw(self.index.id(), value, key, PutFlags::empty())
+    }
+
+    /// Deletes `key` from the primary database along with its index entry.
+    ///
+    /// Returns `Ok(false)` if `key` was not present.
+    pub fn delete(&self, txn: &Transaction<'env>, key: K) -> Result<bool, LMDBError> {
+        let key = key.as_ref();
+
+        let Some(old_value) = txn.get_raw(self.primary.id(), key)?.map(<[u8]>::to_vec) else {
+            return Ok(false);
+        };
+
+        txn.del_raw(self.primary.id(), key, None)?;
+        txn.del_raw(self.index.id(), &old_value, Some(key))?;
+        Ok(true)
+    }
+
+    /// Returns the (first) primary key indexed under `value`.
+    pub fn lookup(&self, txn: &Transaction<'env>, value: V) -> Result<Option<K>, LMDBError>
+    where
+        K: for<'a> From<&'a [u8]>,
+    {
+        Ok(txn.get_raw(self.index.id(), value.as_ref())?.map(K::from))
+    }
+}
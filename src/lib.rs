@@ -1,16 +1,33 @@
+pub mod batch;
+pub mod composite;
 pub mod cursor;
 pub mod db;
 pub mod dbenv;
 pub mod error;
+pub mod index;
+mod ndjson;
 pub mod txn;
+pub mod value;
 
+pub use batch::*;
+pub use composite::*;
 pub use db::*;
 pub use dbenv::*;
 pub use error::LMDBError;
+pub use index::*;
 pub use txn::*;
+pub use value::{DecodeInto, FromVal, FromValRef, ValueGuard};
+
+#[cfg(miri)]
+mod sys_mock;
 
 pub mod sys {
     #![allow(non_camel_case_types)]
 
+    #[cfg(not(miri))]
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+    // Miri can't call into C, so it runs against an in-memory stand-in
+    #[cfg(miri)]
+    pub use crate::sys_mock::*;
 }
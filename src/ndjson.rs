@@ -0,0 +1,93 @@
+use std::io;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Writes one entry as a `{"key":"<base64>","value":"<base64>"}` line, using
+/// standard padded base64.
+pub(crate) fn write_entry<W: io::Write>(out: &mut W, key: &[u8], value: &[u8]) -> io::Result<()> {
+    writeln!(
+        out,
+        r#"{{"key":"{}","value":"{}"}}"#,
+        encode(key),
+        encode(value)
+    )
+}
+
+/// Parses a line written by `write_entry`. Whitespace between tokens and
+/// either field order are accepted.
+pub(crate) fn parse_entry(line: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let body = line
+        .trim()
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        .ok_or("expected a JSON object")?;
+
+    let (mut key, mut value) = (None, None);
+    // Base64 never contains ',' or ':', so the object splits cleanly
+    for field in body.split(',') {
+        let (name, data) = field
+            .split_once(':')
+            .ok_or_else(|| format!("malformed field `{}`", field.trim()))?;
+        let data = decode(unquote(data)?)?;
+        match unquote(name)? {
+            "key" => key = Some(data),
+            "value" => value = Some(data),
+            other => return Err(format!("unexpected field `{other}`")),
+        }
+    }
+
+    match (key, value) {
+        (Some(key), Some(value)) => Ok((key, value)),
+        _ => Err("expected both \"key\" and \"value\"".to_owned()),
+    }
+}
+
+fn unquote(s: &str) -> Result<&str, String> {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a string, found `{s}`"))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn decode(s: &str) -> Result<Vec<u8>, String> {
+    let invalid = || format!("invalid base64 `{s}`");
+    if !s.len().is_multiple_of(4) {
+        return Err(invalid());
+    }
+
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let chunks = s.as_bytes().chunks(4);
+    let last = chunks.len().saturating_sub(1);
+    for (index, chunk) in chunks.enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && index != last) {
+            return Err(invalid());
+        }
+
+        let mut n = 0u32;
+        for (i, &c) in chunk[..4 - padding].iter().enumerate() {
+            let digit = ALPHABET.iter().position(|&a| a == c).ok_or_else(invalid)?;
+            n |= (digit as u32) << (18 - 6 * i);
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(out)
+}
@@ -0,0 +1,1324 @@
+//! An in-memory stand-in for the LMDB C library, used when the crate is built
+//! under Miri, which can't call foreign functions.
+//!
+//! It implements the functions the wrapper calls, with enough of LMDB's
+//! behaviour (snapshots, nested transactions, sorted duplicates, custom
+//! comparators, cursor positioning and the usual error codes) for the safe
+//! wrapper's pointer and slice handling to be checked. It is not a faithful
+//! reimplementation: nothing touches the disk, data lives until the process
+//! exits, the map never fills up, and a second writer doesn't block.
+//!
+//! Values handed out point into buffers owned by the transaction, which stay
+//! put until that exact value is overwritten or the transaction ends, so Miri
+//! reports the same misuse LMDB would punish.
+//!
+//! Opening an environment still looks up its path in the mount table, so run
+//! with isolation off. `tests/miri.rs` covers the wrapper's reads, writes and
+//! cursor scans:
+//! `MIRIFLAGS=-Zmiri-disable-isolation cargo +nightly miri test --test miri`.
+
+#![allow(
+    non_camel_case_types,
+    non_upper_case_globals,
+    non_snake_case,
+    dead_code
+)]
+// The functions mirror the C declarations, whose contracts are LMDB's
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ffi::{CStr, CString, c_char, c_int, c_uint, c_void},
+    process, ptr, slice,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+    thread::{self, ThreadId},
+};
+
+mod ffi {
+    #![allow(
+        non_camel_case_types,
+        non_upper_case_globals,
+        non_snake_case,
+        dead_code
+    )]
+
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+// Types and constants come from the real bindings; the functions defined below
+// take precedence over the glob
+pub use ffi::*;
+
+const EINVAL: c_int = 22;
+const EACCES: c_int = 13;
+
+const MAIN_DBI: MDB_dbi = 1;
+
+/// `mdb_env_get_maxkeysize` of a default LMDB build.
+const MAX_KEY_SIZE: usize = 511;
+
+const PAGE_SIZE: usize = 4096;
+
+/// Flags stored with a database, as opposed to `MDB_CREATE`.
+const PERSISTENT_FLAGS: c_uint =
+    MDB_REVERSEKEY | MDB_DUPSORT | MDB_INTEGERKEY | MDB_DUPFIXED | MDB_INTEGERDUP | MDB_REVERSEDUP;
+
+/// The record a named database leaves in the main database, standing in for
+/// LMDB's `MDB_db`.
+const DB_RECORD: [u8; 48] = [0; 48];
+
+/// Committed state, shared by every environment opened on the same path.
+#[derive(Default)]
+struct State {
+    dbs: Vec<Option<Db>>,
+
+    names: HashMap<Vec<u8>, MDB_dbi>,
+
+    last_txnid: usize,
+
+    /// Open read-only transactions: (txn address, thread, snapshot id).
+    readers: Vec<(usize, ThreadId, usize)>,
+}
+
+#[derive(Clone, Default)]
+struct Db {
+    flags: c_uint,
+
+    /// Sorted by key; `dups` holds one value unless the db is `MDB_DUPSORT`,
+    /// and is then sorted too.
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    key: Vec<u8>,
+
+    dups: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Comparators {
+    key: MDB_cmp_func,
+
+    dup: MDB_cmp_func,
+}
+
+#[derive(Default)]
+struct Config {
+    flags: c_uint,
+
+    map_size: usize,
+
+    max_dbs: MDB_dbi,
+
+    max_readers: c_uint,
+
+    path: Option<CString>,
+
+    userctx: usize,
+
+    comparators: HashMap<MDB_dbi, Comparators>,
+}
+
+struct Env {
+    config: Mutex<Config>,
+
+    shared: OnceLock<Arc<Mutex<State>>>,
+}
+
+struct Txn {
+    env: *mut Env,
+
+    parent: *mut Txn,
+
+    read_only: bool,
+
+    id: usize,
+
+    dbs: Vec<Option<Db>>,
+
+    /// Buffers built for `MDB_GET_MULTIPLE`, kept alive with the transaction.
+    pages: Vec<Vec<u8>>,
+}
+
+/// Where a cursor is, by content rather than index, so that it survives
+/// writes elsewhere in the database. If its pair is deleted, stepping forward
+/// lands on whatever took its place, as in LMDB.
+#[derive(Clone)]
+enum Pos {
+    Unset,
+
+    At {
+        key: Vec<u8>,
+        dup: Vec<u8>,
+    },
+
+    /// Past the last entry, after a failed lookup.
+    End,
+}
+
+struct Cursor {
+    txn: *mut Txn,
+
+    dbi: MDB_dbi,
+
+    pos: Pos,
+}
+
+/// Environments by path, so reopening a path sees what was committed there.
+type Registry = Mutex<HashMap<Vec<u8>, Arc<Mutex<State>>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn val(bytes: &[u8]) -> MDB_val {
+    MDB_val {
+        mv_size: bytes.len(),
+        mv_data: bytes.as_ptr() as *mut c_void,
+    }
+}
+
+unsafe fn bytes<'a>(val: *const MDB_val) -> &'a [u8] {
+    let val = unsafe { &*val };
+    if val.mv_size == 0 {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(val.mv_data as *const u8, val.mv_size) }
+    }
+}
+
+unsafe fn set(out: *mut MDB_val, bytes: &[u8]) {
+    if !out.is_null() {
+        unsafe { *out = val(bytes) };
+    }
+}
+
+fn compare_default(
+    flags: c_uint,
+    integer: c_uint,
+    reverse: c_uint,
+    a: &[u8],
+    b: &[u8],
+) -> Ordering {
+    let number = |bytes: &[u8]| match bytes.len() {
+        4 => Some(u64::from(u32::from_ne_bytes(bytes.try_into().unwrap()))),
+        8 => Some(u64::from_ne_bytes(bytes.try_into().unwrap())),
+        _ => None,
+    };
+    if flags & integer != 0
+        && let (Some(a), Some(b)) = (number(a), number(b))
+    {
+        a.cmp(&b)
+    } else if flags & reverse != 0 {
+        a.iter().rev().cmp(b.iter().rev())
+    } else {
+        a.cmp(b)
+    }
+}
+
+fn call(
+    cmp: unsafe extern "C" fn(*const MDB_val, *const MDB_val) -> c_int,
+    a: &[u8],
+    b: &[u8],
+) -> Ordering {
+    let (a, b) = (val(a), val(b));
+    unsafe { cmp(&a, &b) }.cmp(&0)
+}
+
+impl Txn {
+    fn env(&self) -> &Env {
+        unsafe { &*self.env }
+    }
+
+    fn comparators(&self, dbi: MDB_dbi) -> Comparators {
+        let config = lock(&self.env().config);
+        config.comparators.get(&dbi).copied().unwrap_or_default()
+    }
+
+    fn db(&self, dbi: MDB_dbi) -> Result<&Db, c_int> {
+        self.dbs
+            .get(dbi as usize)
+            .and_then(Option::as_ref)
+            .ok_or(EINVAL)
+    }
+
+    fn db_mut(&mut self, dbi: MDB_dbi) -> Result<&mut Db, c_int> {
+        self.dbs
+            .get_mut(dbi as usize)
+            .and_then(Option::as_mut)
+            .ok_or(EINVAL)
+    }
+
+    fn cmp_key(&self, dbi: MDB_dbi, a: &[u8], b: &[u8]) -> Ordering {
+        let flags = self.db(dbi).map(|db| db.flags).unwrap_or_default();
+        match self.comparators(dbi).key {
+            Some(cmp) => call(cmp, a, b),
+            None => compare_default(flags, MDB_INTEGERKEY, MDB_REVERSEKEY, a, b),
+        }
+    }
+
+    fn cmp_dup(&self, dbi: MDB_dbi, a: &[u8], b: &[u8]) -> Ordering {
+        let flags = self.db(dbi).map(|db| db.flags).unwrap_or_default();
+        match self.comparators(dbi).dup {
+            Some(cmp) => call(cmp, a, b),
+            None => compare_default(flags, MDB_INTEGERDUP, MDB_REVERSEDUP, a, b),
+        }
+    }
+
+    /// Binary search for `key`: `Ok(index)` if present, else where it goes.
+    fn find(&self, dbi: MDB_dbi, key: &[u8]) -> Result<Result<usize, usize>, c_int> {
+        let db = self.db(dbi)?;
+        Ok(db
+            .entries
+            .binary_search_by(|entry| self.cmp_key(dbi, &entry.key, key)))
+    }
+
+    fn find_dup(&self, dbi: MDB_dbi, index: usize, dup: &[u8]) -> Result<usize, usize> {
+        let dups = &self.dbs[dbi as usize].as_ref().unwrap().entries[index].dups;
+        dups.binary_search_by(|stored| self.cmp_dup(dbi, stored, dup))
+    }
+
+    fn is_dupsort(&self, dbi: MDB_dbi) -> bool {
+        self.db(dbi).is_ok_and(|db| db.flags & MDB_DUPSORT != 0)
+    }
+
+    fn put(&mut self, dbi: MDB_dbi, key: &[u8], data: &mut MDB_val, flags: c_uint) -> c_int {
+        if self.read_only {
+            return EACCES;
+        }
+        if key.is_empty() || key.len() > MAX_KEY_SIZE {
+            return MDB_BAD_VALSIZE;
+        }
+        let found = match self.find(dbi, key) {
+            Ok(found) => found,
+            Err(err) => return err,
+        };
+        let dupsort = self.is_dupsort(dbi);
+        let value = if flags & MDB_RESERVE != 0 {
+            vec![0; data.mv_size]
+        } else {
+            unsafe { bytes(data) }.to_vec()
+        };
+        if dupsort && value.len() > MAX_KEY_SIZE {
+            return MDB_BAD_VALSIZE;
+        }
+
+        if flags & MDB_APPEND != 0
+            && let Some(last) = self.db(dbi).unwrap().entries.last()
+        {
+            match self.cmp_key(dbi, key, &last.key) {
+                Ordering::Greater => {}
+                Ordering::Equal if dupsort && flags & MDB_APPENDDUP != 0 => {
+                    let last_dup = last.dups.last().unwrap();
+                    if self.cmp_dup(dbi, &value, last_dup) != Ordering::Greater {
+                        return MDB_KEYEXIST;
+                    }
+                }
+                _ => return MDB_KEYEXIST,
+            }
+        }
+
+        let index = match found {
+            Ok(index) => {
+                if flags & MDB_NOOVERWRITE != 0 {
+                    let existing = &self.db(dbi).unwrap().entries[index].dups[0];
+                    unsafe { set(data, existing) };
+                    return MDB_KEYEXIST;
+                }
+                index
+            }
+            Err(index) => {
+                let entry = Entry {
+                    key: key.to_vec(),
+                    dups: Vec::new(),
+                };
+                self.db_mut(dbi).unwrap().entries.insert(index, entry);
+                index
+            }
+        };
+
+        let dup_index = if dupsort {
+            match self.find_dup(dbi, index, &value) {
+                Ok(_) if flags & MDB_NODUPDATA != 0 => return MDB_KEYEXIST,
+                Ok(dup_index) => dup_index,
+                Err(dup_index) => {
+                    let db = self.db_mut(dbi).unwrap();
+                    let dups = &mut db.entries[index].dups;
+                    if db.flags & MDB_DUPFIXED != 0
+                        && dups.first().is_some_and(|d| d.len() != value.len())
+                    {
+                        return MDB_BAD_VALSIZE;
+                    }
+                    dups.insert(dup_index, value);
+                    dup_index
+                }
+            }
+        } else {
+            let dups = &mut self.db_mut(dbi).unwrap().entries[index].dups;
+            *dups = vec![value];
+            0
+        };
+
+        if flags & MDB_RESERVE != 0 {
+            let stored = &mut self.db_mut(dbi).unwrap().entries[index].dups[dup_index];
+            data.mv_data = stored.as_mut_ptr() as *mut c_void;
+        }
+        MDB_SUCCESS as c_int
+    }
+
+    /// Removes `dup` of the key at `index`, or every value if `dup` is `None`.
+    fn remove(&mut self, dbi: MDB_dbi, index: usize, dup: Option<usize>) {
+        let db = self.db_mut(dbi).unwrap();
+        match dup {
+            Some(dup) if db.entries[index].dups.len() > 1 => {
+                db.entries[index].dups.remove(dup);
+            }
+            _ => {
+                db.entries.remove(index);
+            }
+        }
+    }
+}
+
+pub unsafe extern "C" fn mdb_strerror(err: c_int) -> *mut c_char {
+    let text = match err {
+        0 => c"Successful return: 0",
+        MDB_KEYEXIST => c"MDB_KEYEXIST: Key/data pair already exists",
+        MDB_NOTFOUND => c"MDB_NOTFOUND: No matching key/data pair found",
+        MDB_MAP_FULL => c"MDB_MAP_FULL: Environment mapsize limit reached",
+        MDB_INCOMPATIBLE => c"MDB_INCOMPATIBLE: Operation and DB incompatible, or DB flags changed",
+        MDB_BAD_VALSIZE => {
+            c"MDB_BAD_VALSIZE: Unsupported size of key/DB name/data, or wrong DUPFIXED size"
+        }
+        EINVAL => c"Invalid argument",
+        EACCES => c"Permission denied",
+        _ => c"Unknown error",
+    };
+    text.as_ptr() as *mut c_char
+}
+
+pub unsafe extern "C" fn mdb_env_create(env: *mut *mut MDB_env) -> c_int {
+    let config = Config {
+        map_size: 1 << 20,
+        max_readers: 126,
+        ..Default::default()
+    };
+    let boxed = Box::new(Env {
+        config: Mutex::new(config),
+        shared: OnceLock::new(),
+    });
+    unsafe { *env = Box::into_raw(boxed) as *mut MDB_env };
+    0
+}
+
+unsafe fn env_ref<'a>(env: *mut MDB_env) -> &'a Env {
+    unsafe { &*(env as *const Env) }
+}
+
+pub unsafe extern "C" fn mdb_env_open(
+    env: *mut MDB_env,
+    path: *const c_char,
+    flags: c_uint,
+    _mode: mdb_mode_t,
+) -> c_int {
+    let env = unsafe { env_ref(env) };
+    let path = unsafe { CStr::from_ptr(path) }.to_owned();
+
+    let shared = lock(registry())
+        .entry(path.as_bytes().to_vec())
+        .or_insert_with(|| {
+            Arc::new(Mutex::new(State {
+                dbs: vec![None, Some(Db::default())],
+                ..Default::default()
+            }))
+        })
+        .clone();
+    if env.shared.set(shared).is_err() {
+        return EINVAL;
+    }
+
+    let mut config = lock(&env.config);
+    config.flags |= flags;
+    config.path = Some(path);
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_close(env: *mut MDB_env) {
+    drop(unsafe { Box::from_raw(env as *mut Env) });
+}
+
+pub unsafe extern "C" fn mdb_env_sync(_env: *mut MDB_env, _force: c_int) -> c_int {
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_get_flags(env: *mut MDB_env, flags: *mut c_uint) -> c_int {
+    unsafe { *flags = lock(&env_ref(env).config).flags };
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_get_path(env: *mut MDB_env, path: *mut *const c_char) -> c_int {
+    let config = lock(unsafe { &env_ref(env).config });
+    // The CString lives as long as the environment
+    let ptr = config
+        .path
+        .as_ref()
+        .map_or(ptr::null(), |path| path.as_ptr());
+    unsafe { *path = ptr };
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_set_mapsize(env: *mut MDB_env, size: mdb_size_t) -> c_int {
+    let mut config = lock(unsafe { &env_ref(env).config });
+    // Zero adopts the current size, which the mock never changes behind our back
+    if size != 0 {
+        config.map_size = size;
+    }
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_set_maxreaders(env: *mut MDB_env, readers: c_uint) -> c_int {
+    let env = unsafe { env_ref(env) };
+    if env.shared.get().is_some() {
+        return EINVAL;
+    }
+    lock(&env.config).max_readers = readers;
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_get_maxreaders(env: *mut MDB_env, readers: *mut c_uint) -> c_int {
+    unsafe { *readers = lock(&env_ref(env).config).max_readers };
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_set_maxdbs(env: *mut MDB_env, dbs: MDB_dbi) -> c_int {
+    let env = unsafe { env_ref(env) };
+    if env.shared.get().is_some() {
+        return EINVAL;
+    }
+    lock(&env.config).max_dbs = dbs;
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_get_maxkeysize(_env: *mut MDB_env) -> c_int {
+    MAX_KEY_SIZE as c_int
+}
+
+pub unsafe extern "C" fn mdb_env_set_userctx(env: *mut MDB_env, ctx: *mut c_void) -> c_int {
+    lock(unsafe { &env_ref(env).config }).userctx = ctx as usize;
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_get_userctx(env: *mut MDB_env) -> *mut c_void {
+    lock(unsafe { &env_ref(env).config }).userctx as *mut c_void
+}
+
+fn stat_of(db: Option<&Db>) -> MDB_stat {
+    let (entries, size) = db.map_or((0, 0), |db| {
+        db.entries.iter().fold((0, 0), |(entries, size), entry| {
+            let values: usize = entry.dups.iter().map(|dup| dup.len()).sum();
+            (entries + entry.dups.len(), size + entry.key.len() + values)
+        })
+    });
+    MDB_stat {
+        ms_psize: PAGE_SIZE as c_uint,
+        ms_depth: (entries > 0) as c_uint,
+        ms_branch_pages: 0,
+        ms_leaf_pages: size.div_ceil(PAGE_SIZE),
+        ms_overflow_pages: 0,
+        ms_entries: entries,
+    }
+}
+
+pub unsafe extern "C" fn mdb_env_stat(env: *mut MDB_env, stat: *mut MDB_stat) -> c_int {
+    let Some(shared) = (unsafe { env_ref(env) }).shared.get() else {
+        return EINVAL;
+    };
+    let state = lock(shared);
+    unsafe { *stat = stat_of(state.dbs[MAIN_DBI as usize].as_ref()) };
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_copy2(
+    env: *mut MDB_env,
+    path: *const c_char,
+    _flags: c_uint,
+) -> c_int {
+    const EEXIST: c_int = 17;
+
+    let env = unsafe { env_ref(env) };
+    let Some(shared) = env.shared.get() else {
+        return EINVAL;
+    };
+    let path = unsafe { CStr::from_ptr(path) }.to_bytes().to_vec();
+    // Compaction makes no difference to the in-memory state
+    let copy = {
+        let state = lock(shared);
+        State {
+            dbs: state.dbs.clone(),
+            names: state.names.clone(),
+            last_txnid: state.last_txnid,
+            readers: Vec::new(),
+        }
+    };
+
+    let mut registry = lock(registry());
+    if registry.contains_key(&path) {
+        return EEXIST;
+    }
+    registry.insert(path, Arc::new(Mutex::new(copy)));
+    0
+}
+
+pub unsafe extern "C" fn mdb_env_info(env: *mut MDB_env, info: *mut MDB_envinfo) -> c_int {
+    let env = unsafe { env_ref(env) };
+    let Some(shared) = env.shared.get() else {
+        return EINVAL;
+    };
+    let config = lock(&env.config);
+    let state = lock(shared);
+    let pages: usize = state
+        .dbs
+        .iter()
+        .map(|db| {
+            let stat = stat_of(db.as_ref());
+            stat.ms_leaf_pages + stat.ms_depth as usize
+        })
+        .sum();
+
+    unsafe {
+        *info = MDB_envinfo {
+            me_mapaddr: ptr::null_mut(),
+            me_mapsize: config.map_size,
+            me_last_pgno: pages + 1,
+            me_last_txnid: state.last_txnid,
+            me_maxreaders: config.max_readers,
+            me_numreaders: state.readers.len() as c_uint,
+        }
+    };
+    0
+}
+
+pub unsafe extern "C" fn mdb_reader_list(
+    env: *mut MDB_env,
+    func: MDB_msg_func,
+    ctx: *mut c_void,
+) -> c_int {
+    let Some(shared) = (unsafe { env_ref(env) }).shared.get() else {
+        return -1;
+    };
+    let Some(func) = func else {
+        return -1;
+    };
+    let mut lines = vec!["    pid     thread     txnid\n".to_owned()];
+    let readers = lock(shared).readers.clone();
+    for (txn, _, txnid) in &readers {
+        lines.push(format!("{:10} {:x} {}\n", process::id(), txn, txnid));
+    }
+    if readers.is_empty() {
+        lines = vec!["(no active readers)\n".to_owned()];
+    }
+
+    for line in lines {
+        let line = CString::new(line).unwrap();
+        let ret = unsafe { func(line.as_ptr(), ctx) };
+        if ret < 0 {
+            return ret;
+        }
+    }
+    0
+}
+
+pub unsafe extern "C" fn mdb_reader_check(_env: *mut MDB_env, dead: *mut c_int) -> c_int {
+    if !dead.is_null() {
+        unsafe { *dead = 0 };
+    }
+    0
+}
+
+pub unsafe extern "C" fn mdb_txn_begin(
+    env: *mut MDB_env,
+    parent: *mut MDB_txn,
+    flags: c_uint,
+    txn: *mut *mut MDB_txn,
+) -> c_int {
+    let env_ptr = env as *mut Env;
+    let env = unsafe { env_ref(env) };
+    let Some(shared) = env.shared.get() else {
+        return EINVAL;
+    };
+    let config = lock(&env.config);
+    let read_only = flags & MDB_RDONLY != 0;
+    if !read_only && config.flags & MDB_RDONLY != 0 {
+        return EACCES;
+    }
+    let mut state = lock(shared);
+
+    let (dbs, id) = if let Some(parent) = unsafe { (parent as *mut Txn).as_ref() } {
+        (parent.dbs.clone(), parent.id)
+    } else if read_only {
+        let this_thread = thread::current().id();
+        if config.flags & MDB_NOTLS == 0 && state.readers.iter().any(|r| r.1 == this_thread) {
+            return MDB_BAD_RSLOT;
+        }
+        if state.readers.len() >= config.max_readers as usize {
+            return MDB_READERS_FULL;
+        }
+        (state.dbs.clone(), state.last_txnid)
+    } else {
+        (state.dbs.clone(), state.last_txnid + 1)
+    };
+
+    let boxed = Box::into_raw(Box::new(Txn {
+        env: env_ptr,
+        parent: parent as *mut Txn,
+        read_only,
+        id,
+        dbs,
+        pages: Vec::new(),
+    }));
+    if read_only {
+        state
+            .readers
+            .push((boxed as usize, thread::current().id(), id));
+    }
+    unsafe { *txn = boxed as *mut MDB_txn };
+    0
+}
+
+unsafe fn txn_mut<'a>(txn: *mut MDB_txn) -> &'a mut Txn {
+    unsafe { &mut *(txn as *mut Txn) }
+}
+
+pub unsafe extern "C" fn mdb_txn_env(txn: *mut MDB_txn) -> *mut MDB_env {
+    unsafe { txn_mut(txn) }.env as *mut MDB_env
+}
+
+pub unsafe extern "C" fn mdb_txn_id(txn: *mut MDB_txn) -> mdb_size_t {
+    unsafe { txn_mut(txn) }.id
+}
+
+/// Frees `txn`, releasing its reader slot.
+unsafe fn end_txn(txn: *mut MDB_txn) -> Box<Txn> {
+    let txn = unsafe { Box::from_raw(txn as *mut Txn) };
+    if txn.read_only
+        && let Some(shared) = txn.env().shared.get()
+    {
+        let address = &*txn as *const Txn as usize;
+        lock(shared).readers.retain(|reader| reader.0 != address);
+    }
+    txn
+}
+
+pub unsafe extern "C" fn mdb_txn_commit(txn: *mut MDB_txn) -> c_int {
+    let txn = unsafe { end_txn(txn) };
+    if txn.read_only {
+        return 0;
+    }
+
+    let Txn {
+        env,
+        parent,
+        id,
+        dbs,
+        ..
+    } = *txn;
+    if let Some(parent) = unsafe { parent.as_mut() } {
+        parent.dbs = dbs;
+    } else {
+        let mut state = lock(unsafe { &*env }.shared.get().unwrap());
+        state.dbs = dbs;
+        state.last_txnid = id;
+    }
+    0
+}
+
+pub unsafe extern "C" fn mdb_txn_abort(txn: *mut MDB_txn) {
+    drop(unsafe { end_txn(txn) });
+}
+
+pub unsafe extern "C" fn mdb_dbi_open(
+    txn: *mut MDB_txn,
+    name: *const c_char,
+    flags: c_uint,
+    dbi: *mut MDB_dbi,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    let create = flags & MDB_CREATE != 0;
+
+    if name.is_null() {
+        let main = txn.db_mut(MAIN_DBI).unwrap();
+        if create && main.entries.is_empty() {
+            main.flags = flags & PERSISTENT_FLAGS;
+        }
+        unsafe { *dbi = MAIN_DBI };
+        return 0;
+    }
+
+    let name = unsafe { CStr::from_ptr(name) }.to_bytes().to_vec();
+    let in_main = txn.find(MAIN_DBI, &name).unwrap().is_ok();
+    let max_dbs = lock(&txn.env().config).max_dbs;
+    let shared = txn.env().shared.get().unwrap().clone();
+    let mut state = lock(&shared);
+    let existing = state.names.get(&name).copied();
+
+    if in_main {
+        return match existing {
+            Some(found) if txn.db(found).is_ok() => {
+                unsafe { *dbi = found };
+                0
+            }
+            // A plain record, not a database
+            _ => MDB_INCOMPATIBLE,
+        };
+    }
+    if !create {
+        return MDB_NOTFOUND;
+    }
+    if txn.read_only {
+        return EACCES;
+    }
+
+    let slot = match existing {
+        Some(slot) => slot,
+        None => {
+            if state.names.len() >= max_dbs as usize {
+                return MDB_DBS_FULL;
+            }
+            let slot = state.names.len() as MDB_dbi + 2;
+            state.names.insert(name.clone(), slot);
+            slot
+        }
+    };
+    drop(state);
+
+    if txn.dbs.len() <= slot as usize {
+        txn.dbs.resize(slot as usize + 1, None);
+    }
+    txn.dbs[slot as usize] = Some(Db {
+        flags: flags & PERSISTENT_FLAGS,
+        entries: Vec::new(),
+    });
+    let mut record = val(&DB_RECORD);
+    let ret = txn.put(MAIN_DBI, &name, &mut record, 0);
+    if ret != 0 {
+        return ret;
+    }
+
+    unsafe { *dbi = slot };
+    0
+}
+
+pub unsafe extern "C" fn mdb_dbi_flags(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    flags: *mut c_uint,
+) -> c_int {
+    match unsafe { txn_mut(txn) }.db(dbi) {
+        Ok(db) => {
+            unsafe { *flags = db.flags };
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+pub unsafe extern "C" fn mdb_stat(txn: *mut MDB_txn, dbi: MDB_dbi, stat: *mut MDB_stat) -> c_int {
+    match unsafe { txn_mut(txn) }.db(dbi) {
+        Ok(db) => {
+            unsafe { *stat = stat_of(Some(db)) };
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+pub unsafe extern "C" fn mdb_drop(txn: *mut MDB_txn, dbi: MDB_dbi, del: c_int) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    if txn.read_only {
+        return EACCES;
+    }
+    if let Err(err) = txn.db_mut(dbi) {
+        return err;
+    }
+
+    if del == 0 || dbi == MAIN_DBI {
+        txn.db_mut(dbi).unwrap().entries.clear();
+        return 0;
+    }
+
+    txn.dbs[dbi as usize] = None;
+    let state = lock(txn.env().shared.get().unwrap());
+    let name = state
+        .names
+        .iter()
+        .find(|&(_, &slot)| slot == dbi)
+        .map(|(name, _)| name.clone());
+    drop(state);
+    if let Some(name) = name
+        && let Ok(Ok(index)) = txn.find(MAIN_DBI, &name)
+    {
+        txn.remove(MAIN_DBI, index, None);
+    }
+    0
+}
+
+pub unsafe extern "C" fn mdb_set_compare(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    cmp: MDB_cmp_func,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    lock(&txn.env().config)
+        .comparators
+        .entry(dbi)
+        .or_default()
+        .key = cmp;
+    0
+}
+
+pub unsafe extern "C" fn mdb_set_dupsort(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    cmp: MDB_cmp_func,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    lock(&txn.env().config)
+        .comparators
+        .entry(dbi)
+        .or_default()
+        .dup = cmp;
+    0
+}
+
+pub unsafe extern "C" fn mdb_cmp(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    a: *const MDB_val,
+    b: *const MDB_val,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    txn.cmp_key(dbi, unsafe { bytes(a) }, unsafe { bytes(b) }) as c_int
+}
+
+pub unsafe extern "C" fn mdb_get(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    key: *mut MDB_val,
+    data: *mut MDB_val,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    let key = unsafe { bytes(key) };
+    if key.is_empty() || key.len() > MAX_KEY_SIZE {
+        return MDB_BAD_VALSIZE;
+    }
+    match txn.find(dbi, key) {
+        Ok(Ok(index)) => {
+            unsafe { set(data, &txn.db(dbi).unwrap().entries[index].dups[0]) };
+            0
+        }
+        Ok(Err(_)) => MDB_NOTFOUND,
+        Err(err) => err,
+    }
+}
+
+pub unsafe extern "C" fn mdb_put(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    key: *mut MDB_val,
+    data: *mut MDB_val,
+    flags: c_uint,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    let key = unsafe { bytes(key) }.to_vec();
+    txn.put(dbi, &key, unsafe { &mut *data }, flags)
+}
+
+pub unsafe extern "C" fn mdb_del(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    key: *mut MDB_val,
+    data: *mut MDB_val,
+) -> c_int {
+    let txn = unsafe { txn_mut(txn) };
+    if txn.read_only {
+        return EACCES;
+    }
+    let key = unsafe { bytes(key) };
+    if key.is_empty() || key.len() > MAX_KEY_SIZE {
+        return MDB_BAD_VALSIZE;
+    }
+    let index = match txn.find(dbi, key) {
+        Ok(Ok(index)) => index,
+        Ok(Err(_)) => return MDB_NOTFOUND,
+        Err(err) => return err,
+    };
+
+    let dup = if txn.is_dupsort(dbi) && !data.is_null() {
+        match txn.find_dup(dbi, index, unsafe { bytes(data) }) {
+            Ok(dup) => Some(dup),
+            Err(_) => return MDB_NOTFOUND,
+        }
+    } else {
+        None
+    };
+    txn.remove(dbi, index, dup);
+    0
+}
+
+unsafe fn cursor_mut<'a>(cursor: *mut MDB_cursor) -> &'a mut Cursor {
+    unsafe { &mut *(cursor as *mut Cursor) }
+}
+
+pub unsafe extern "C" fn mdb_cursor_open(
+    txn: *mut MDB_txn,
+    dbi: MDB_dbi,
+    cursor: *mut *mut MDB_cursor,
+) -> c_int {
+    if let Err(err) = unsafe { txn_mut(txn) }.db(dbi) {
+        return err;
+    }
+    let boxed = Box::new(Cursor {
+        txn: txn as *mut Txn,
+        dbi,
+        pos: Pos::Unset,
+    });
+    unsafe { *cursor = Box::into_raw(boxed) as *mut MDB_cursor };
+    0
+}
+
+pub unsafe extern "C" fn mdb_cursor_close(cursor: *mut MDB_cursor) {
+    drop(unsafe { Box::from_raw(cursor as *mut Cursor) });
+}
+
+pub unsafe extern "C" fn mdb_cursor_txn(cursor: *mut MDB_cursor) -> *mut MDB_txn {
+    unsafe { cursor_mut(cursor) }.txn as *mut MDB_txn
+}
+
+pub unsafe extern "C" fn mdb_cursor_dbi(cursor: *mut MDB_cursor) -> MDB_dbi {
+    unsafe { cursor_mut(cursor) }.dbi
+}
+
+/// A resolved cursor position: entry index and duplicate index.
+type Slot = (usize, usize);
+
+impl Cursor {
+    fn txn(&self) -> &Txn {
+        unsafe { &*self.txn }
+    }
+
+    fn entries(&self) -> &[Entry] {
+        &self.txn().db(self.dbi).unwrap().entries
+    }
+
+    /// The first pair at or after `key`/`dup` in (key, dup) order.
+    fn lower_bound(&self, key: &[u8], dup: &[u8]) -> Slot {
+        let txn = self.txn();
+        match txn.find(self.dbi, key).unwrap() {
+            Ok(index) => match txn.find_dup(self.dbi, index, dup) {
+                Ok(dup) | Err(dup) if dup < self.entries()[index].dups.len() => (index, dup),
+                _ => (index + 1, 0),
+            },
+            Err(index) => (index, 0),
+        }
+    }
+
+    /// The slot the cursor is on, if its pair still exists.
+    fn slot(&self) -> Option<Slot> {
+        let Pos::At { key, dup } = &self.pos else {
+            return None;
+        };
+        let txn = self.txn();
+        let index = txn.find(self.dbi, key).ok()?.ok()?;
+        let dup = txn.find_dup(self.dbi, index, dup).ok()?;
+        Some((index, dup))
+    }
+
+    fn is_key(&self, (index, _): Slot, key: &[u8]) -> bool {
+        self.entries()
+            .get(index)
+            .is_some_and(|entry| self.txn().cmp_key(self.dbi, &entry.key, key) == Ordering::Equal)
+    }
+
+    fn go(&mut self, (index, dup): Slot) -> Option<Slot> {
+        let entry = self.entries().get(index)?;
+        let value = entry.dups.get(dup)?.clone();
+        self.pos = Pos::At {
+            key: entry.key.clone(),
+            dup: value,
+        };
+        Some((index, dup))
+    }
+
+    fn first(&mut self) -> Option<Slot> {
+        self.go((0, 0))
+    }
+
+    fn last(&mut self) -> Option<Slot> {
+        let index = self.entries().len().checked_sub(1)?;
+        let dup = self.entries()[index].dups.len() - 1;
+        self.go((index, dup))
+    }
+
+    /// Steps forward; `within_key` stops at the current key's last duplicate
+    /// and `skip_dups` jumps to the next key.
+    fn next(&mut self, within_key: bool, skip_dups: bool) -> Option<Slot> {
+        let (key, dup) = match &self.pos {
+            Pos::Unset => return self.first(),
+            Pos::End => return None,
+            Pos::At { key, dup } => (key.clone(), dup.clone()),
+        };
+
+        let mut target = match self.slot() {
+            Some((index, dup)) if !skip_dups && dup + 1 < self.entries()[index].dups.len() => {
+                (index, dup + 1)
+            }
+            Some((index, _)) => (index + 1, 0),
+            // The pair is gone, so whatever took its place comes next
+            None => self.lower_bound(&key, &dup),
+        };
+        if skip_dups && self.is_key(target, &key) {
+            target = (target.0 + 1, 0);
+        }
+        if within_key && !self.is_key(target, &key) {
+            return None;
+        }
+        self.go(target)
+    }
+
+    /// Steps back; the flags work as in `next`.
+    fn prev(&mut self, within_key: bool, skip_dups: bool) -> Option<Slot> {
+        let (key, dup) = match &self.pos {
+            Pos::Unset | Pos::End => return self.last(),
+            Pos::At { key, dup } => (key.clone(), dup.clone()),
+        };
+
+        let (index, dup) = self.slot().unwrap_or_else(|| self.lower_bound(&key, &dup));
+        let target = if !skip_dups && dup > 0 {
+            (index, dup - 1)
+        } else {
+            let index = index.checked_sub(1)?;
+            (index, self.entries()[index].dups.len() - 1)
+        };
+        if within_key && !self.is_key(target, &key) {
+            return None;
+        }
+        self.go(target)
+    }
+
+    /// Looks `key` up for the `MDB_SET` family; `dup` narrows it to a
+    /// duplicate for `MDB_GET_BOTH` (`range` false) or `MDB_GET_BOTH_RANGE`.
+    fn seek(&mut self, key: &[u8], dup: Option<(&[u8], bool)>) -> Option<Slot> {
+        let txn = self.txn();
+        let found = txn.find(self.dbi, key).unwrap().ok().and_then(|index| {
+            let dup_index = match dup {
+                None => 0,
+                Some((dup, range)) if txn.is_dupsort(self.dbi) => {
+                    match txn.find_dup(self.dbi, index, dup) {
+                        Ok(found) => found,
+                        Err(found) if range && found < self.entries()[index].dups.len() => found,
+                        Err(_) => return None,
+                    }
+                }
+                Some((dup, range)) => {
+                    let stored = &self.entries()[index].dups[0];
+                    match txn.cmp_dup(self.dbi, dup, stored) {
+                        Ordering::Equal => 0,
+                        Ordering::Less if range => 0,
+                        _ => return None,
+                    }
+                }
+            };
+            Some((index, dup_index))
+        });
+
+        match found {
+            Some(slot) => self.go(slot),
+            None => {
+                self.pos = Pos::End;
+                None
+            }
+        }
+    }
+}
+
+pub unsafe extern "C" fn mdb_cursor_get(
+    cursor: *mut MDB_cursor,
+    key: *mut MDB_val,
+    data: *mut MDB_val,
+    op: MDB_cursor_op,
+) -> c_int {
+    use MDB_cursor_op::*;
+
+    let cursor = unsafe { cursor_mut(cursor) };
+    let dupsort = cursor.txn().is_dupsort(cursor.dbi);
+
+    let result: Result<Option<Slot>, c_int> = match op {
+        MDB_FIRST => Ok(cursor.first()),
+        MDB_LAST => Ok(cursor.last()),
+        MDB_NEXT => Ok(cursor.next(false, false)),
+        MDB_PREV => Ok(cursor.prev(false, false)),
+        MDB_NEXT_DUP if dupsort || matches!(cursor.pos, Pos::Unset) => Ok(cursor.next(true, false)),
+        MDB_PREV_DUP if dupsort || matches!(cursor.pos, Pos::Unset) => Ok(cursor.prev(true, false)),
+        MDB_NEXT_DUP | MDB_PREV_DUP => Ok(None),
+        MDB_NEXT_NODUP => Ok(cursor.next(false, true)),
+        MDB_PREV_NODUP => Ok(cursor.prev(false, true)),
+        MDB_FIRST_DUP | MDB_LAST_DUP if !dupsort => Err(MDB_INCOMPATIBLE),
+        MDB_FIRST_DUP | MDB_LAST_DUP => match cursor.slot() {
+            Some((index, _)) if op == MDB_FIRST_DUP => Ok(cursor.go((index, 0))),
+            Some((index, _)) => {
+                let last = cursor.entries()[index].dups.len() - 1;
+                Ok(cursor.go((index, last)))
+            }
+            None => Err(EINVAL),
+        },
+        MDB_GET_CURRENT => match &cursor.pos {
+            Pos::Unset => Err(EINVAL),
+            Pos::End => Ok(None),
+            Pos::At { key, dup } => {
+                let slot = cursor
+                    .slot()
+                    .unwrap_or_else(|| cursor.lower_bound(&key.clone(), &dup.clone()));
+                Ok(cursor.go(slot))
+            }
+        },
+        MDB_GET_MULTIPLE | MDB_NEXT_MULTIPLE => {
+            if cursor.txn().db(cursor.dbi).unwrap().flags & MDB_DUPFIXED == 0 {
+                return MDB_INCOMPATIBLE;
+            }
+            let from = match op {
+                MDB_GET_MULTIPLE => cursor.slot().ok_or(EINVAL),
+                _ => cursor.next(true, false).ok_or(MDB_NOTFOUND),
+            };
+            let (index, dup) = match from {
+                Ok(slot) => slot,
+                Err(err) => return err,
+            };
+
+            // One page holding the rest of the key's values
+            let dups = &cursor.entries()[index].dups;
+            let page = dups[dup..].concat();
+            let last = dups.len() - 1;
+            cursor.go((index, last));
+            let pages = unsafe { &mut (*cursor.txn).pages };
+            pages.push(page);
+            unsafe { set(data, pages.last().unwrap()) };
+            return 0;
+        }
+        MDB_SET | MDB_SET_KEY | MDB_SET_RANGE | MDB_GET_BOTH | MDB_GET_BOTH_RANGE => {
+            let wanted = unsafe { bytes(key) }.to_vec();
+            if wanted.is_empty() || wanted.len() > MAX_KEY_SIZE {
+                return MDB_BAD_VALSIZE;
+            }
+            match op {
+                MDB_SET_RANGE => {
+                    let slot = cursor.lower_bound(&wanted, &[]);
+                    let found = cursor.go(slot);
+                    if found.is_none() {
+                        cursor.pos = Pos::End;
+                    }
+                    Ok(found)
+                }
+                MDB_GET_BOTH | MDB_GET_BOTH_RANGE => {
+                    let dup = unsafe { bytes(data) }.to_vec();
+                    Ok(cursor.seek(&wanted, Some((&dup, op == MDB_GET_BOTH_RANGE))))
+                }
+                _ => Ok(cursor.seek(&wanted, None)),
+            }
+        }
+        _ => Err(EINVAL),
+    };
+
+    let (index, dup) = match result {
+        Ok(Some(slot)) => slot,
+        Ok(None) => return MDB_NOTFOUND,
+        Err(err) => return err,
+    };
+    let entry = &cursor.entries()[index];
+    // Like LMDB, these ops leave the caller's key alone
+    if !matches!(
+        op,
+        MDB_FIRST_DUP | MDB_LAST_DUP | MDB_SET | MDB_GET_BOTH | MDB_GET_BOTH_RANGE
+    ) {
+        unsafe { set(key, &entry.key) };
+    }
+    unsafe { set(data, &entry.dups[dup]) };
+    0
+}
+
+pub unsafe extern "C" fn mdb_cursor_put(
+    cursor: *mut MDB_cursor,
+    key: *mut MDB_val,
+    data: *mut MDB_val,
+    flags: c_uint,
+) -> c_int {
+    let cursor = unsafe { cursor_mut(cursor) };
+    let key = unsafe { bytes(key) }.to_vec();
+    let dbi = cursor.dbi;
+    let ret = unsafe { &mut *cursor.txn }.put(dbi, &key, unsafe { &mut *data }, flags);
+    if ret != 0 {
+        return ret;
+    }
+
+    // The cursor moves to the pair just written
+    let txn = cursor.txn();
+    let index = txn.find(dbi, &key).unwrap().unwrap();
+    let dup = if txn.is_dupsort(dbi) {
+        txn.find_dup(dbi, index, unsafe { bytes(data) })
+            .unwrap_or_else(|dup| dup)
+    } else {
+        0
+    };
+    cursor.go((index, dup));
+    0
+}
+
+pub unsafe extern "C" fn mdb_cursor_del(cursor: *mut MDB_cursor, flags: c_uint) -> c_int {
+    let cursor = unsafe { cursor_mut(cursor) };
+    if cursor.txn().read_only {
+        return EACCES;
+    }
+    // After a delete LMDB sits on the pair that followed the deleted one
+    let (index, dup) = match (&cursor.pos, cursor.slot()) {
+        (_, Some(slot)) => slot,
+        (Pos::At { key, dup }, None) => match cursor.lower_bound(key, dup) {
+            slot if slot.0 < cursor.entries().len() => slot,
+            _ => return EINVAL,
+        },
+        _ => return EINVAL,
+    };
+
+    let whole_key = flags & MDB_NODUPDATA != 0 || !cursor.txn().is_dupsort(cursor.dbi);
+    let dbi = cursor.dbi;
+    // The position is kept, so the next step finds what follows the pair
+    unsafe { &mut *cursor.txn }.remove(dbi, index, (!whole_key).then_some(dup));
+    0
+}
+
+pub unsafe extern "C" fn mdb_cursor_count(
+    cursor: *mut MDB_cursor,
+    countp: *mut mdb_size_t,
+) -> c_int {
+    let cursor = unsafe { cursor_mut(cursor) };
+    if !cursor.txn().is_dupsort(cursor.dbi) {
+        return MDB_INCOMPATIBLE;
+    }
+    match cursor.pos {
+        Pos::Unset => EINVAL,
+        Pos::End => MDB_NOTFOUND,
+        // Still counts the key's duplicates after the current one was deleted
+        Pos::At { ref key, .. } => match cursor.txn().find(cursor.dbi, key) {
+            Ok(Ok(index)) => {
+                unsafe { *countp = cursor.entries()[index].dups.len() };
+                0
+            }
+            _ => MDB_NOTFOUND,
+        },
+    }
+}
@@ -1,22 +1,114 @@
 use std::{
+    cell::Cell,
+    cmp::Ordering,
     ffi, fmt,
-    marker::PhantomData,
-    mem::{self, ManuallyDrop},
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::Deref,
+    process,
     ptr::NonNull,
+    sync::{
+        RwLockReadGuard,
+        atomic::{self, AtomicU64},
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use bitflags::bitflags;
 
-use crate::{DBEnv, db::Database, sys};
+use crate::{
+    DBEnv, DBFlags, EnvFlags,
+    cursor::Cursor,
+    db::{self, Database},
+    dbenv,
+    error::MDBError,
+    sys,
+    value::{self, DecodeInto, FromVal, FromValRef, ValueGuard},
+};
 
+/// A read-only or read-write LMDB transaction.
+///
+/// A transaction borrows its environment and is aborted when dropped, so the
+/// environment can't be closed under it. Cursors and child transactions borrow
+/// the transaction in turn, which keeps `commit` and `abort` from running
+/// until they are gone. A transaction can't outlive its environment:
+///
+/// ```compile_fail
+/// # fn f() -> Result<(), rlmdb::LMDBError> {
+/// use rlmdb::{DBEnvBuilder, EnvFlags};
+///
+/// let txn = {
+///     let env = DBEnvBuilder::new("data.mdb").open(Some(EnvFlags::MDB_NOSUBDIR))?;
+///     env.begin_txn()?
+/// };
+/// txn.commit()?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct Transaction<'env> {
     ptr: ManuallyDrop<NonNull<sys::MDB_txn>>,
 
-    _marker: PhantomData<&'env DBEnv>,
+    // Keeps the environment open for as long as the transaction exists
+    env: &'env DBEnv,
+
+    /// Copied from the environment so key lengths can be checked up front.
+    max_key_size: usize,
+
+    /// Running totals behind `op_stats`.
+    op_counters: Cell<OpCounters>,
+
+    /// Whether this transaction has changed anything, for
+    /// `DBEnvBuilder::on_dirty_drop`.
+    pub(crate) dirty: Cell<bool>,
+
+    /// The parent's `dirty` flag, set when a child with changes commits.
+    parent_dirty: Option<&'env Cell<bool>>,
+
+    /// Share of `DBEnv::map_lock`, held by top-level transactions so the map
+    /// isn't resized under them.
+    map_guard: Option<RwLockReadGuard<'env, ()>>,
 
     pub txn_type: TransactionType,
 }
 
+/// Byte counters for the point reads and writes made through a transaction.
+///
+/// Returned by `Transaction::op_stats`. Only `get`-style lookups and
+/// `put`-style writes are counted; cursor scans are not.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpStats {
+    /// Value bytes returned by lookups that found their key.
+    pub bytes_read: u64,
+
+    /// Key and value bytes stored by writes.
+    pub bytes_written: u64,
+
+    /// Rough count of data pages the lookups touched: one leaf page per hit
+    /// plus the overflow pages needed to hold the values read.
+    pub page_faults_estimate: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct OpCounters {
+    reads: u64,
+
+    bytes_read: u64,
+
+    bytes_written: u64,
+}
+
+/// Outcome of `Transaction::get_detail`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GetResult<T> {
+    /// The key is not in the database.
+    Absent,
+
+    /// The key is present with a zero-length value.
+    Empty,
+
+    /// The key is present with a non-empty value.
+    Value(T),
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TransactionType {
     ReadOnly,
@@ -77,6 +169,48 @@ impl Default for PutFlags {
     }
 }
 
+impl fmt::Display for PutFlags {
+    /// Formats the set flags by name, e.g. `MDB_NOOVERWRITE | MDB_APPEND`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        bitflags::parser::to_writer(self, f)
+    }
+}
+
+/// How [`Transaction::put_policy`] treats a key or value that is already
+/// present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PutPolicy {
+    /// Replace the key's value. In an `MDB_DUPSORT` database the value is
+    /// added as another duplicate instead, and storing an existing pair is a
+    /// no-op.
+    #[default]
+    Overwrite,
+
+    /// Fail with `MDB_KEYEXIST` if the key is already present.
+    NoOverwrite,
+
+    /// Fail with `MDB_KEYEXIST` if this exact key/value pair is already
+    /// present. Only valid for `MDB_DUPSORT` databases.
+    NoDupData,
+
+    /// Add the pair at the end of the database without searching for its
+    /// place. Keys must arrive in ascending order; a key that doesn't sort
+    /// after the last one fails with `MDB_KEYEXIST`.
+    Append,
+}
+
+impl PutPolicy {
+    /// Returns the `mdb_put` flags this policy stands for.
+    pub fn flags(self) -> PutFlags {
+        match self {
+            PutPolicy::Overwrite => PutFlags::empty(),
+            PutPolicy::NoOverwrite => PutFlags::MDB_NOOVERWRITE,
+            PutPolicy::NoDupData => PutFlags::MDB_NODUPDATA,
+            PutPolicy::Append => PutFlags::MDB_APPEND,
+        }
+    }
+}
+
 #[allow(unused)]
 impl<'env> Transaction<'env> {
     pub(crate) fn new(
@@ -91,30 +225,63 @@ impl<'env> Transaction<'env> {
             TransactionType::ReadWrite => TransactionFlags::empty().bits(), // No flags for read-write transactions
         };
 
+        if parent.is_some()
+            && txn_type == TransactionType::ReadWrite
+            && env.flags()?.contains(EnvFlags::MDB_WRITEMAP)
+        {
+            return Err(crate::LMDBError::NestedTxnUnsupported);
+        }
+
         let parent_ptr = parent.map_or(std::ptr::null_mut(), |p| unsafe { p.as_raw_ptr() });
 
+        // A child is covered by its parent's share
+        let map_guard = parent.is_none().then(|| env.map_share());
+
         let ret =
             unsafe { sys::mdb_txn_begin(env.as_ptr().as_ptr(), parent_ptr, flags, &mut txn_ptr) };
+        if ret == sys::MDB_BAD_RSLOT
+            && txn_type == TransactionType::ReadOnly
+            && !env.flags()?.contains(EnvFlags::MDB_NOTLS)
+        {
+            // Without MDB_NOTLS the reader slot is bound to the thread, so this
+            // means another read-only transaction is still open on it.
+            return Err(crate::LMDBError::ReadTxnActive);
+        }
         crate::LMDBError::from_mdb_error(ret)?;
 
         // Ensure the pointer is not null and convert it to NonNull
         let ptr = NonNull::new(txn_ptr).ok_or_else(|| {
-            crate::LMDBError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            crate::LMDBError::Io(std::io::Error::other(
                 "mdb_txn_begin succeeded but returned a null transaction pointer",
             ))
         })?;
 
         Ok(Transaction {
             ptr: ManuallyDrop::new(ptr),
-            _marker: PhantomData,
+            env,
+            max_key_size: env.max_key_size(),
+            op_counters: Cell::default(),
+            dirty: Cell::new(false),
+            parent_dirty: None,
+            map_guard,
             txn_type,
         })
     }
 
     pub fn commit(mut self) -> Result<(), crate::LMDBError> {
         let ptr = unsafe { ManuallyDrop::take(&mut self.ptr) };
-        let ret = unsafe { sys::mdb_txn_commit(ptr.as_ptr()) };
+        let ret =
+            unsafe { db::with_compare_ctx(ptr.as_ptr(), || sys::mdb_txn_commit(ptr.as_ptr())) };
+
+        drop(self.map_guard.take());
+
+        // A child's changes now belong to its parent
+        if ret == 0
+            && self.dirty.get()
+            && let Some(parent_dirty) = self.parent_dirty
+        {
+            parent_dirty.set(true);
+        }
 
         // Prevent double drop/commit/abort
         mem::forget(self);
@@ -126,15 +293,62 @@ impl<'env> Transaction<'env> {
         let ptr = unsafe { ManuallyDrop::take(&mut self.ptr) };
         unsafe { sys::mdb_txn_abort(ptr.as_ptr()) };
 
+        drop(self.map_guard.take());
+
         // Prevent double drop/commit/abort
         mem::forget(self);
     }
 
-    pub fn get<K, V>(&self, db: &'env Database<K, V>, key: K) -> Result<Option<V>, crate::LMDBError>
+    /// Returns the transaction's id (`mdb_txn_id`).
+    ///
+    /// A read-only transaction reports the id of the snapshot it reads, i.e.
+    /// of the last write transaction committed before it began; a write
+    /// transaction reports the id its commit will have.
+    pub fn id(&self) -> usize {
+        unsafe { sys::mdb_txn_id(self.as_raw_ptr()) }
+    }
+
+    /// Begins a write transaction nested inside this one, e.g. as a savepoint.
+    ///
+    /// Committing the child folds its changes into `self`; aborting or dropping
+    /// it discards only the child's changes. The child borrows `self` mutably,
+    /// so the parent can't be used until the child has ended. Not available
+    /// with `MDB_WRITEMAP` or from a read-only transaction.
+    pub fn begin_child(&mut self) -> Result<Transaction<'_>, crate::LMDBError> {
+        if self.txn_type != TransactionType::ReadWrite {
+            return Err(crate::LMDBError::ChildOfReadOnlyTxn);
+        }
+
+        let parent = &*self;
+        let mut child = Transaction::new(parent.env, Some(parent), TransactionType::ReadWrite)?;
+        child.parent_dirty = Some(&parent.dirty);
+        Ok(child)
+    }
+
+    /// Looks up `key`, decoding the value as an owned `T` such as `Vec<u8>` or
+    /// `String`, or returns `None` if it is absent.
+    ///
+    /// Zero-copy reads into `&[u8]` or `&str` need a read-only transaction; see
+    /// [`ReadOnlyTransaction::get`]. Here they don't compile:
+    ///
+    /// ```compile_fail
+    /// # fn f(env: &rlmdb::DBEnv) -> Result<(), rlmdb::LMDBError> {
+    /// let txn = env.begin_txn()?;
+    /// let db = env.open_db::<&str, &str>(&txn, None)?;
+    /// let value: Option<&[u8]> = txn.get(&db, "key")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get<K, T>(
+        &self,
+        db: &'env Database<K, impl AsRef<[u8]>>,
+        key: K,
+    ) -> Result<Option<T>, crate::LMDBError>
     where
         K: AsRef<[u8]>,
-        V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+        T: FromVal,
     {
+        self.check_key_size(key.as_ref())?;
         let mut key = sys::MDB_val {
             mv_size: key.as_ref().len(),
             mv_data: key.as_ref().as_ptr() as *mut _,
@@ -144,12 +358,120 @@ impl<'env> Transaction<'env> {
             mv_data: std::ptr::null_mut(),
         };
 
-        let ret = unsafe { sys::mdb_get(self.as_raw_ptr(), db.id(), &mut key, &mut data) };
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_get(self.as_raw_ptr(), db.id(), &mut key, &mut data)
+        });
+        if ret == sys::MDB_NOTFOUND {
+            return Ok(None);
+        }
         crate::LMDBError::from_mdb_error(ret)?;
 
-        let value_slice =
-            unsafe { std::slice::from_raw_parts(data.mv_data as *const u8, data.mv_size) };
-        Ok(Some(V::from(value_slice)))
+        // LMDB may return a null pointer for an empty value
+        let value_slice = unsafe { value::from_mdb_val(&data) };
+        self.count_read(value_slice.len());
+        T::from_val(value_slice).map(Some)
+    }
+
+    /// Looks up `key`, telling a missing key apart from one stored with an
+    /// empty value, which some schemas use as a presence marker.
+    ///
+    /// `get` reports an empty value as `Some` of an empty `T`; this makes the
+    /// distinction explicit. `T` works as for `get`.
+    pub fn get_detail<K, T>(
+        &self,
+        db: &'env Database<K, impl AsRef<[u8]>>,
+        key: K,
+    ) -> Result<GetResult<T>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        T: FromVal,
+    {
+        let result = match self.get_raw(db.id(), key.as_ref())? {
+            None => GetResult::Absent,
+            Some([]) => GetResult::Empty,
+            Some(bytes) => GetResult::Value(T::from_val(bytes)?),
+        };
+        Ok(result)
+    }
+
+    /// Looks up `key` in each of `dbs` in turn and returns the first hit, e.g.
+    /// an overrides database shadowing a defaults one.
+    ///
+    /// All lookups read the same snapshot. `T` works as for `get`.
+    pub fn get_layered<K, V, T>(
+        &self,
+        dbs: &[&Database<K, V>],
+        key: K,
+    ) -> Result<Option<T>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        T: FromVal,
+    {
+        for db in dbs {
+            if let Some(bytes) = self.get_raw(db.id(), key.as_ref())? {
+                return T::from_val(bytes).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Same as `get`; the name documents intent in read-modify-write code.
+    ///
+    /// In a write transaction LMDB reads see the transaction's own uncommitted
+    /// writes, so a value read here reflects earlier `put`s and `delete`s in
+    /// `self`. Since LMDB allows a single writer at a time, no other
+    /// transaction can change it before `self` commits.
+    pub fn get_for_update<K, T>(
+        &self,
+        db: &'env Database<K, impl AsRef<[u8]>>,
+        key: K,
+    ) -> Result<Option<T>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        T: FromVal,
+    {
+        self.get(db, key)
+    }
+
+    /// Looks up `key` and decodes its value into `out`, reusing `out`'s buffer.
+    ///
+    /// Returns `Ok(false)` and leaves `out` untouched if the key does not exist.
+    pub fn get_reuse<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        out: &mut V,
+    ) -> Result<bool, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]> + DecodeInto,
+    {
+        match self.get_raw(db.id(), key.as_ref())? {
+            Some(bytes) => {
+                out.decode_into(bytes)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns whether `key` exists, without copying its value.
+    ///
+    /// This behaves the same on unique and `MDB_DUPSORT` databases.
+    pub fn contains_key<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+    ) -> Result<bool, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        // `mdb_get` is a `MDB_SET` lookup on a stack-allocated cursor, so a
+        // transient `mdb_cursor_open` would only add an allocation, even for
+        // DUPSORT databases where both paths stop at the first duplicate.
+        Ok(self.get_raw(db.id(), key.as_ref())?.is_some())
     }
 
     pub fn put<K, V>(
@@ -163,62 +485,855 @@ impl<'env> Transaction<'env> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
-        let flags = flags.unwrap_or(PutFlags::default());
-        let mut key = sys::MDB_val {
-            mv_size: key.as_ref().len(),
-            mv_data: key.as_ref().as_ptr() as *mut _,
-        };
-        let mut value = sys::MDB_val {
-            mv_size: data.as_ref().len(),
-            mv_data: data.as_ref().as_ptr() as *mut _,
-        };
+        self.put_raw(
+            db.id(),
+            key.as_ref(),
+            data.as_ref(),
+            flags.unwrap_or_default(),
+        )
+    }
 
-        let ret = unsafe {
+    /// Stores `data` under `key`, handling an existing entry according to
+    /// `policy`.
+    ///
+    /// Unlike passing [`PutFlags`] to `put`, the policy is checked against the
+    /// database's flags first, so `NoDupData` on a database without
+    /// `MDB_DUPSORT` fails with `LMDBError::DupSortRequired` rather than being
+    /// ignored.
+    pub fn put_policy<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        data: V,
+        policy: PutPolicy,
+    ) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        if policy == PutPolicy::NoDupData && !self.db_flags(db)?.contains(DBFlags::MDB_DUPSORT) {
+            return Err(crate::LMDBError::DupSortRequired);
+        }
+
+        self.put_raw(db.id(), key.as_ref(), data.as_ref(), policy.flags())
+    }
+
+    /// Stores `data` under `key` unless the key already exists, in which case
+    /// nothing is written and the existing value is returned.
+    ///
+    /// Returns `None` if `data` was inserted. The check and the write are a
+    /// single `MDB_NOOVERWRITE` put. In an `MDB_DUPSORT` database the existing
+    /// value is the key's first duplicate.
+    pub fn put_no_overwrite<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        data: V,
+    ) -> Result<Option<V>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+    {
+        self.check_key_size(key.as_ref())?;
+        let mut key = value::to_mdb_val(key.as_ref());
+        let mut value = value::to_mdb_val(data.as_ref());
+
+        let ret = self.with_compare_ctx(|| unsafe {
             sys::mdb_put(
                 self.as_raw_ptr(),
                 db.id(),
                 &mut key,
                 &mut value,
-                flags.bits(),
+                PutFlags::MDB_NOOVERWRITE.bits(),
             )
+        });
+        if ret == sys::MDB_KEYEXIST {
+            // LMDB points `value` at the stored data
+            let existing = unsafe { value::from_mdb_val(&value) };
+            self.count_read(existing.len());
+            return Ok(Some(V::from(existing)));
+        }
+        crate::LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+
+        self.count_write(key.mv_size + value.mv_size);
+        Ok(None)
+    }
+
+    /// Stores `data` under `key` and returns the stored value as read back from
+    /// the database, e.g. to checksum what was actually written.
+    ///
+    /// This takes `&mut self` so the transaction can't write again, and move
+    /// the value, while the returned slice is alive. In an `MDB_DUPSORT`
+    /// database the slice is the key's first duplicate, not necessarily `data`.
+    pub fn put_and_read<K, V>(
+        &mut self,
+        db: &Database<K, V>,
+        key: K,
+        data: V,
+    ) -> Result<&[u8], crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.put_raw(db.id(), key.as_ref(), data.as_ref(), PutFlags::empty())?;
+        self.get_raw(db.id(), key.as_ref())?.ok_or_else(|| {
+            crate::LMDBError::Io(std::io::Error::other(
+                "value not found right after it was stored",
+            ))
+        })
+    }
+
+    /// Stores every pair from `items` in order.
+    ///
+    /// With `MDB_APPEND` or `MDB_APPENDDUP` in `flags`, a pair that doesn't sort
+    /// after the previous one fails with `LMDBError::AppendOutOfOrder`, naming
+    /// its position in `items` and its key. Pairs before it have been written,
+    /// so abort the transaction to discard a partial load.
+    pub fn put_many<K, V, I>(
+        &self,
+        db: &'env Database<K, V>,
+        items: I,
+        flags: Option<PutFlags>,
+    ) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let flags = flags.unwrap_or_default();
+        let appending = flags.intersects(PutFlags::MDB_APPEND | PutFlags::MDB_APPENDDUP);
+
+        for (position, (key, data)) in items.into_iter().enumerate() {
+            match self.put_raw(db.id(), key.as_ref(), data.as_ref(), flags) {
+                Err(crate::LMDBError::MDB(MDBError::KeyExists)) if appending => {
+                    return Err(crate::LMDBError::AppendOutOfOrder {
+                        position,
+                        key: key.as_ref().to_vec(),
+                    });
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Bulk-loads pre-sorted `items` with `MDB_APPEND`, which skips the usual
+    /// tree search and packs pages densely.
+    ///
+    /// See `put_many` for how out-of-order input is reported.
+    pub fn append<K, V, I>(
+        &self,
+        db: &'env Database<K, V>,
+        items: I,
+    ) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.put_many(db, items, Some(PutFlags::MDB_APPEND))
+    }
+
+    /// Reserves `len` bytes for `key` with `MDB_RESERVE` and lets `fill` write
+    /// the value in place, saving a copy when the value is generated on the fly.
+    ///
+    /// `fill` should write all `len` bytes; anything it leaves is unspecified.
+    /// Not allowed on `MDB_DUPSORT` databases.
+    ///
+    /// This takes `&mut self` for the same reason as `put_reserve`: another
+    /// write from inside `fill` could move the space it is writing to.
+    ///
+    /// ```compile_fail
+    /// # fn f(env: &rlmdb::DBEnv) -> Result<(), rlmdb::LMDBError> {
+    /// let mut txn = env.begin_txn()?;
+    /// let db = env.open_db::<&str, Vec<u8>>(&txn, None)?;
+    /// txn.put_with(&db, "k", 4, |buf| {
+    ///     txn.put(&db, "other", vec![0; 4096], None).unwrap();
+    ///     buf.fill(1);
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn put_with<K, V, F>(
+        &mut self,
+        db: &Database<K, V>,
+        key: K,
+        len: usize,
+        fill: F,
+    ) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        F: FnOnce(&mut [u8]),
+    {
+        let data = self.reserve_raw(db.id(), key.as_ref(), len)?;
+        // `&mut self` keeps other writes out until `fill` returns
+        fill(unsafe { reserved_slice(&data) });
+        Ok(())
+    }
+
+    /// Reserves `size` bytes for `key` with `MDB_RESERVE` and returns the
+    /// space inside the map for the caller to write the value into.
+    ///
+    /// This takes `&mut self` because the next write in the transaction may
+    /// move the space; the buffer must be filled before the transaction is
+    /// used again. Bytes left unwritten are unspecified. Not allowed on
+    /// `MDB_DUPSORT` databases.
+    pub fn put_reserve<K, V>(
+        &mut self,
+        db: &Database<K, V>,
+        key: K,
+        size: usize,
+    ) -> Result<&mut [u8], crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let data = self.reserve_raw(db.id(), key.as_ref(), size)?;
+        Ok(unsafe { reserved_slice(&data) })
+    }
+
+    /// Runs an `MDB_RESERVE` put of `len` bytes and returns the reserved space.
+    fn reserve_raw(
+        &self,
+        dbi: sys::MDB_dbi,
+        key: &[u8],
+        len: usize,
+    ) -> Result<sys::MDB_val, crate::LMDBError> {
+        self.check_key_size(key)?;
+        let mut key = value::to_mdb_val(key);
+        let mut data = sys::MDB_val {
+            mv_size: len,
+            mv_data: std::ptr::null_mut(),
         };
-        crate::LMDBError::from_mdb_error(ret)
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_put(
+                self.as_raw_ptr(),
+                dbi,
+                &mut key,
+                &mut data,
+                PutFlags::MDB_RESERVE.bits(),
+            )
+        });
+        crate::LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+
+        self.count_write(key.mv_size + len);
+        Ok(data)
+    }
+
+    /// Appends `data` under the next integer key of an `MDB_INTEGERKEY`
+    /// database and returns that key.
+    ///
+    /// Keys are stored as native-endian `u64` bytes (e.g. `[u8; 8]` keys); the
+    /// first record of an empty database gets key 0 and each later one the
+    /// current largest key plus one.
+    pub fn append_auto<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        data: V,
+    ) -> Result<u64, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        if !self.db_flags(db)?.contains(DBFlags::MDB_INTEGERKEY) {
+            return Err(crate::LMDBError::NotIntegerKeyDb);
+        }
+
+        let mut cursor = Cursor::new(self, db)?;
+        let next = match cursor.get_raw(sys::MDB_cursor_op::MDB_LAST, None, None)? {
+            None => 0,
+            Some((key, _)) => {
+                let last = <[u8; 8]>::try_from(key)
+                    .map_err(|_| crate::LMDBError::MalformedIntegerKey { len: key.len() })?;
+                u64::from_ne_bytes(last)
+                    .checked_add(1)
+                    .ok_or(crate::LMDBError::IntegerKeySpaceExhausted)?
+            }
+        };
+        drop(cursor);
+
+        self.put_raw(
+            db.id(),
+            &next.to_ne_bytes(),
+            data.as_ref(),
+            PutFlags::MDB_APPEND,
+        )?;
+        Ok(next)
+    }
+
+    /// Stores the concatenation of `chunks` under `key` without building it
+    /// in a temporary buffer first, by copying each chunk into space reserved
+    /// with `MDB_RESERVE`.
+    ///
+    /// `total_len` must equal the combined length of the chunks, or the call
+    /// fails with `LMDBError::ChunkLengthMismatch` before anything is written.
+    pub fn put_chunks<'a, K, V, I>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        total_len: usize,
+        chunks: I,
+    ) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let chunks: Vec<&[u8]> = chunks.into_iter().collect();
+        let actual_len: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+        if actual_len != total_len {
+            return Err(crate::LMDBError::ChunkLengthMismatch {
+                expected: total_len,
+                actual: actual_len,
+            });
+        }
+
+        let data = self.reserve_raw(db.id(), key.as_ref(), total_len)?;
+        // Only the copies below run before the space could move
+        let buf = unsafe { reserved_slice(&data) };
+        let mut offset = 0;
+        for chunk in chunks {
+            buf[offset..offset + chunk.len()].copy_from_slice(chunk);
+            offset += chunk.len();
+        }
+        Ok(())
     }
 
+    /// Stores `data` under `key`, returning `true` if the key was newly created
+    /// and `false` if an existing value was overwritten.
+    pub fn upsert<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        data: V,
+    ) -> Result<bool, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let (key, data) = (key.as_ref(), data.as_ref());
+
+        match self.put_raw(db.id(), key, data, PutFlags::MDB_NOOVERWRITE) {
+            Ok(()) => Ok(true),
+            Err(crate::LMDBError::MDB(MDBError::KeyExists)) => {
+                self.put_raw(db.id(), key, data, PutFlags::empty())?;
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Deletes `key`, or only its `data` duplicate if given, returning
+    /// whether there was anything to delete.
     pub fn delete<K, V>(
         &self,
         db: &'env Database<K, V>,
         key: K,
         data: Option<V>,
-    ) -> Result<(), crate::LMDBError>
+    ) -> Result<bool, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.del_raw(
+            db.id(),
+            key.as_ref(),
+            data.as_ref().map(|data| data.as_ref()),
+        )
+    }
+
+    /// Deletes `key` with all of its duplicates, returning whether it existed.
+    /// Same as `delete` without `data`.
+    pub fn remove<K, V>(&self, db: &'env Database<K, V>, key: K) -> Result<bool, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.del_raw(db.id(), key.as_ref(), None)
+    }
+
+    /// Deletes `key` and returns the value it had, or `None` if it was absent.
+    ///
+    /// In an `MDB_DUPSORT` database only the first duplicate is removed and
+    /// returned.
+    pub fn take<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+    ) -> Result<Option<V>, crate::LMDBError>
     where
-        K: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+        K: AsRef<[u8]>,
         V: AsRef<[u8]> + for<'a> From<&'a [u8]>,
     {
-        let mut key = sys::MDB_val {
-            mv_size: key.as_ref().len(),
-            mv_data: key.as_ref().as_ptr() as *mut _,
+        self.check_key_size(key.as_ref())?;
+        let mut cursor = Cursor::new(self, db)?;
+
+        // Decode before deleting, which may free the page the value is on
+        let Some(value) = cursor
+            .get_raw(sys::MDB_cursor_op::MDB_SET, Some(key.as_ref()), None)?
+            .map(|(_, value)| V::from(value))
+        else {
+            return Ok(None);
         };
-        let mut data = match data {
-            Some(d) => Some(sys::MDB_val {
-                mv_size: d.as_ref().len(),
-                mv_data: d.as_ref().as_ptr() as *mut _,
-            }),
-            None => None,
+
+        cursor.del_raw(0)?;
+        Ok(Some(value))
+    }
+
+    /// Replaces the value of `key` with `new` only if its current value equals
+    /// `expected`, returning whether the swap happened.
+    ///
+    /// `None` stands for an absent key on both sides: `expected: None` only
+    /// matches when the key doesn't exist, and `new: None` deletes the key.
+    pub fn compare_and_swap<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+
+        let current = self.get_raw(db.id(), key)?;
+        if current != expected {
+            return Ok(false);
+        }
+
+        match new {
+            Some(new) => self.put_raw(db.id(), key, new, PutFlags::empty())?,
+            None if current.is_some() => {
+                self.del_raw(db.id(), key, None)?;
+            }
+            None => {}
+        }
+        Ok(true)
+    }
+
+    /// Exchanges the values of `key_a` and `key_b`.
+    ///
+    /// If only one of the keys exists, its value moves to the other key and it
+    /// is deleted; if neither exists nothing changes. Meant for databases
+    /// without `MDB_DUPSORT`.
+    pub fn swap<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key_a: K,
+        key_b: K,
+    ) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let (key_a, key_b) = (key_a.as_ref(), key_b.as_ref());
+        let value_a = self.get_raw(db.id(), key_a)?.map(<[u8]>::to_vec);
+        let value_b = self.get_raw(db.id(), key_b)?.map(<[u8]>::to_vec);
+
+        for (key, value) in [(key_a, value_b), (key_b, value_a)] {
+            match value {
+                Some(value) => self.put_raw(db.id(), key, &value, PutFlags::empty())?,
+                None => {
+                    self.del_raw(db.id(), key, None)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a value written by `put_versioned`, returning its version and
+    /// data, or `None` if `key` is absent.
+    pub fn get_versioned<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+    ) -> Result<Option<(u64, Vec<u8>)>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let Some(stored) = self.get_raw(db.id(), key.as_ref())? else {
+            return Ok(None);
         };
-        let data_ptr = data.as_mut().map_or(std::ptr::null_mut(), |d| d as *mut _);
+        let (version, data) = split_version(stored)?;
+        Ok(Some((version, data.to_vec())))
+    }
 
-        let ret = unsafe { sys::mdb_del(self.as_raw_ptr(), db.id(), &mut key, data_ptr) };
-        crate::LMDBError::from_mdb_error(ret)
+    /// Stores `data` under `key` if the key's current version is
+    /// `expected_version`, and returns the new version.
+    ///
+    /// Values are stored behind an 8-byte big-endian version number, starting
+    /// at 1 for a new key; an absent key has version 0. On a mismatch nothing is
+    /// written and `LMDBError::VersionConflict` carries the current version, so
+    /// the caller can re-read and retry. Read such values with `get_versioned`.
+    pub fn put_versioned<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        key: K,
+        data: V,
+        expected_version: u64,
+    ) -> Result<u64, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+
+        let actual = match self.get_raw(db.id(), key)? {
+            Some(stored) => split_version(stored)?.0,
+            None => 0,
+        };
+        if actual != expected_version {
+            return Err(crate::LMDBError::VersionConflict {
+                expected: expected_version,
+                actual,
+            });
+        }
+
+        let version = actual + 1;
+        let stored = [&version.to_be_bytes()[..], data.as_ref()].concat();
+        self.put_raw(db.id(), key, &stored, PutFlags::empty())?;
+        Ok(version)
     }
 
-    pub fn cursor<K, V>(
+    /// Counts the entries whose keys fall in `[start, end)`.
+    ///
+    /// LMDB keeps no rank information, so this walks the range with a cursor
+    /// and costs O(entries in range). Keys are compared using the database's
+    /// own ordering.
+    pub fn range_count<K, V, Q>(
         &self,
         db: &'env Database<K, V>,
-    ) -> Result<sys::MDB_cursor, crate::LMDBError> {
-        todo!()
+        start: Q,
+        end: Q,
+    ) -> Result<usize, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        Q: AsRef<[u8]>,
+    {
+        let mut cursor = Cursor::new(self, db)?;
+        let mut count = 0;
+
+        let mut entry = cursor.seek_raw(start.as_ref())?;
+        while let Some((key, _)) = entry {
+            if self.compare_keys(db.id(), key, end.as_ref()) != Ordering::Less {
+                break;
+            }
+            count += 1;
+            entry = cursor.get_raw(sys::MDB_cursor_op::MDB_NEXT, None, None)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Deletes every entry whose key starts with `prefix` and returns how many
+    /// were deleted, counting each duplicate in `MDB_DUPSORT` databases.
+    ///
+    /// An empty prefix deletes everything.
+    pub fn delete_prefix<K, V>(
+        &self,
+        db: &'env Database<K, V>,
+        prefix: &[u8],
+    ) -> Result<usize, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.check_key_size(prefix)?;
+        let mut cursor = Cursor::new(self, db)?;
+        let mut count = 0;
+
+        // Matching keys are contiguous, so stop at the first one that doesn't
+        // match; this needs no upper bound, which an all-0xFF prefix lacks
+        let mut matched =
+            matches!(cursor.seek_raw(prefix)?, Some((key, _)) if key.starts_with(prefix));
+        while matched {
+            cursor.del_raw(0)?;
+            count += 1;
+            matched = matches!(
+                cursor.get_raw(sys::MDB_cursor_op::MDB_NEXT, None, None)?,
+                Some((key, _)) if key.starts_with(prefix)
+            );
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the flags the database was opened with.
+    pub fn db_flags<K, V>(&self, db: &'env Database<K, V>) -> Result<DBFlags, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut flags: ffi::c_uint = 0;
+
+        let ret = unsafe { sys::mdb_dbi_flags(self.as_raw_ptr(), db.id(), &mut flags) };
+        crate::LMDBError::from_mdb_error(ret)?;
+
+        Ok(DBFlags::from_bits_truncate(flags))
+    }
+
+    /// Returns the bytes moved by this transaction's lookups and writes so far.
+    pub fn op_stats(&self) -> OpStats {
+        let counters = self.op_counters.get();
+
+        let page_size = unsafe {
+            let env = sys::mdb_txn_env(self.as_raw_ptr());
+            let mut stat = MaybeUninit::<sys::MDB_stat>::uninit();
+            if sys::mdb_env_stat(env, stat.as_mut_ptr()) == sys::MDB_SUCCESS as i32 {
+                u64::from(stat.assume_init().ms_psize)
+            } else {
+                0
+            }
+        };
+        let overflow_pages = counters.bytes_read.checked_div(page_size).unwrap_or(0);
+
+        OpStats {
+            bytes_read: counters.bytes_read,
+            bytes_written: counters.bytes_written,
+            page_faults_estimate: counters.reads + overflow_pages,
+        }
+    }
+
+    /// Deletes every entry of `db`, keeping the database itself, and returns
+    /// how many entries were removed.
+    pub fn clear<K, V>(&self, db: &'env Database<K, V>) -> Result<usize, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let entries = self.db_stat_raw(db.id())?.ms_entries;
+        self.clear_db(db)?;
+
+        Ok(entries)
+    }
+
+    /// Deletes every entry of `db`, keeping the database and its handle.
+    pub fn clear_db<K, V>(&self, db: &Database<'env, K, V>) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let ret = unsafe { sys::mdb_drop(self.as_raw_ptr(), db.id(), 0) };
+        crate::LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Deletes `db` from the environment and closes its handle.
+    ///
+    /// The deletion is undone if the transaction aborts, but the handle stays
+    /// closed either way; open the database again to keep using it.
+    pub fn drop_db<K, V>(&self, db: Database<'env, K, V>) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let ret = unsafe { sys::mdb_drop(self.as_raw_ptr(), db.id(), 1) };
+        crate::LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Creates a uniquely named scratch database for intermediate results.
+    ///
+    /// The database is real and counts against `max_dbs`; remove it with
+    /// `drop_temp_db` when done, or abort the transaction to discard it.
+    pub fn create_temp_db<K, V>(&self) -> Result<Database<'env, K, V>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let name = format!(
+            "__rlmdb_tmp_{}_{}_{}",
+            process::id(),
+            nanos,
+            NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed)
+        );
+        let name_cstr = ffi::CString::new(name).expect("generated name has no NUL bytes");
+
+        let mut dbi: sys::MDB_dbi = Default::default();
+        let ret = unsafe {
+            dbenv::dbi_open(
+                self.as_raw_ptr(),
+                name_cstr.as_ptr(),
+                DBFlags::MDB_CREATE.bits(),
+                &mut dbi,
+            )
+        };
+        crate::LMDBError::from_mdb_error(ret)?;
+
+        Ok(Database::from_dbi(dbi, name_cstr.into_string().ok()))
+    }
+
+    /// Deletes a database made by `create_temp_db` and closes its handle.
+    pub fn drop_temp_db<K, V>(&self, db: Database<'env, K, V>) -> Result<(), crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.drop_db(db)
+    }
+
+    /// Opens a cursor on `db`. It borrows the transaction, so it is closed
+    /// before the transaction can end.
+    pub fn cursor<'txn, K, V>(
+        &'txn self,
+        db: &Database<K, V>,
+    ) -> Result<Cursor<'txn, K, V>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        Cursor::new(self, db)
+    }
+
+    /// Compares two keys using the ordering of the database `dbi`.
+    pub(crate) fn compare_keys(&self, dbi: sys::MDB_dbi, a: &[u8], b: &[u8]) -> Ordering {
+        let a = value::to_mdb_val(a);
+        let b = value::to_mdb_val(b);
+
+        self.with_compare_ctx(|| unsafe { sys::mdb_cmp(self.as_raw_ptr(), dbi, &a, &b) })
+            .cmp(&0)
     }
 
+    /// Returns `mdb_stat` for the database `dbi` as seen by this transaction.
+    pub(crate) fn db_stat_raw(&self, dbi: sys::MDB_dbi) -> Result<sys::MDB_stat, crate::LMDBError> {
+        let mut stat = MaybeUninit::<sys::MDB_stat>::uninit();
+
+        let ret = unsafe { sys::mdb_stat(self.as_raw_ptr(), dbi, stat.as_mut_ptr()) };
+        crate::LMDBError::from_mdb_error(ret)?;
+
+        Ok(unsafe { stat.assume_init() })
+    }
+
+    /// Rejects keys LMDB would refuse with `MDB_BAD_VALSIZE`, naming both sizes.
+    fn check_key_size(&self, key: &[u8]) -> Result<(), crate::LMDBError> {
+        if key.len() > self.max_key_size {
+            return Err(crate::LMDBError::KeyTooLarge {
+                len: key.len(),
+                max: self.max_key_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fetches the raw value for `key`, mapping `MDB_NOTFOUND` to `None`.
+    pub(crate) fn get_raw(
+        &self,
+        dbi: sys::MDB_dbi,
+        key: &[u8],
+    ) -> Result<Option<&[u8]>, crate::LMDBError> {
+        self.check_key_size(key)?;
+        let mut key = value::to_mdb_val(key);
+        let mut data = sys::MDB_val {
+            mv_size: 0,
+            mv_data: std::ptr::null_mut(),
+        };
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_get(self.as_raw_ptr(), dbi, &mut key, &mut data)
+        });
+        if ret == sys::MDB_NOTFOUND {
+            return Ok(None);
+        }
+        crate::LMDBError::from_mdb_error(ret)?;
+
+        self.count_read(data.mv_size);
+        Ok(Some(unsafe { value::from_mdb_val(&data) }))
+    }
+
+    /// Stores `data` under `key` in the database `dbi`.
+    pub(crate) fn put_raw(
+        &self,
+        dbi: sys::MDB_dbi,
+        key: &[u8],
+        data: &[u8],
+        flags: PutFlags,
+    ) -> Result<(), crate::LMDBError> {
+        self.check_key_size(key)?;
+        let mut key = value::to_mdb_val(key);
+        let mut value = value::to_mdb_val(data);
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_put(self.as_raw_ptr(), dbi, &mut key, &mut value, flags.bits())
+        });
+        crate::LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+
+        self.count_write(key.mv_size + value.mv_size);
+        Ok(())
+    }
+
+    fn count_read(&self, len: usize) {
+        let mut counters = self.op_counters.get();
+        counters.reads += 1;
+        counters.bytes_read += len as u64;
+        self.op_counters.set(counters);
+    }
+
+    fn count_write(&self, len: usize) {
+        let mut counters = self.op_counters.get();
+        counters.bytes_written += len as u64;
+        self.op_counters.set(counters);
+    }
+
+    /// Deletes `key` (or only the `data` duplicate of it) from the database `dbi`.
+    ///
+    /// Returns `Ok(false)` if there was nothing to delete.
+    pub(crate) fn del_raw(
+        &self,
+        dbi: sys::MDB_dbi,
+        key: &[u8],
+        data: Option<&[u8]>,
+    ) -> Result<bool, crate::LMDBError> {
+        self.check_key_size(key)?;
+        let mut key = value::to_mdb_val(key);
+        let mut data = data.map(value::to_mdb_val);
+        let data_ptr = data.as_mut().map_or(std::ptr::null_mut(), |d| d as *mut _);
+
+        let ret = self.with_compare_ctx(|| unsafe {
+            sys::mdb_del(self.as_raw_ptr(), dbi, &mut key, data_ptr)
+        });
+        if ret == sys::MDB_NOTFOUND {
+            return Ok(false);
+        }
+        crate::LMDBError::from_mdb_error(ret)?;
+        self.mark_dirty();
+
+        Ok(true)
+    }
+
+    /// Runs `f`, an LMDB call on this transaction, with the environment's user
+    /// context available to custom comparators.
+    fn with_compare_ctx<R>(&self, f: impl FnOnce() -> R) -> R {
+        unsafe { db::with_compare_ctx(self.as_raw_ptr(), f) }
+    }
+
+    /// Records a successful change for the dropped-transaction warning.
+    /// Read-only transactions never count as changed.
+    fn mark_dirty(&self) {
+        if self.txn_type == TransactionType::ReadWrite {
+            self.dirty.set(true);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The returned pointer is only valid while this `Transaction` is alive and
+    /// must not be committed or aborted directly.
     pub unsafe fn as_raw_ptr(&self) -> *mut sys::MDB_txn {
         self.ptr.as_ptr()
     }
@@ -226,6 +1341,14 @@ impl<'env> Transaction<'env> {
 
 impl<'env> Drop for Transaction<'env> {
     fn drop(&mut self) {
+        // Usually a forgotten `commit`; an explicit `abort` doesn't get here
+        if self.dirty.get()
+            && !std::thread::panicking()
+            && let Some(hook) = self.env.dirty_drop_hook()
+        {
+            hook(self.id());
+        }
+
         unsafe { sys::mdb_txn_abort(self.as_raw_ptr()) }
     }
 }
@@ -238,3 +1361,157 @@ impl<'env> fmt::Debug for Transaction<'env> {
             .finish()
     }
 }
+
+/// A read-only transaction, returned by [`DBEnv::begin_txn_read_only`].
+///
+/// It derefs to [`Transaction`] for everything else, and adds reads that
+/// borrow straight from the memory map. Nothing can be written through a
+/// read-only transaction, so those bytes stay put for as long as it is
+/// borrowed; in a write transaction a later `put` could move them, which is
+/// why `Transaction::get` only decodes into owned types.
+pub struct ReadOnlyTransaction<'env> {
+    txn: Transaction<'env>,
+}
+
+impl<'env> ReadOnlyTransaction<'env> {
+    pub(crate) fn new(txn: Transaction<'env>) -> Self {
+        debug_assert_eq!(txn.txn_type, TransactionType::ReadOnly);
+        Self { txn }
+    }
+
+    /// Looks up `key`, decoding the value as `T`, or returns `None` if it is
+    /// absent.
+    ///
+    /// Unlike `Transaction::get`, `T` may borrow from the memory map for as
+    /// long as the transaction is borrowed, e.g. `txn.get::<_, &[u8]>(&db,
+    /// key)` for a zero-copy read. Owned types such as `Vec<u8>` work too.
+    pub fn get<'txn, K, T>(
+        &'txn self,
+        db: &'env Database<K, impl AsRef<[u8]>>,
+        key: K,
+    ) -> Result<Option<T>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+        T: FromValRef<'txn>,
+    {
+        self.txn
+            .get_raw(db.id(), key.as_ref())?
+            .map(T::from_val_ref)
+            .transpose()
+    }
+
+    /// Looks up `key` and returns the value as a slice of the memory map,
+    /// without copying it. The slice can't outlive the transaction.
+    ///
+    /// Write transactions have no `get_ref`, since a later write could move
+    /// the bytes:
+    ///
+    /// ```compile_fail
+    /// # fn f(env: &rlmdb::DBEnv) -> Result<(), rlmdb::LMDBError> {
+    /// let txn = env.begin_txn()?;
+    /// let db = env.open_db::<&str, Vec<u8>>(&txn, None)?;
+    /// let value = txn.get_ref(&db, "key")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_ref<'txn, K>(
+        &'txn self,
+        db: &'env Database<K, impl AsRef<[u8]>>,
+        key: K,
+    ) -> Result<Option<&'txn [u8]>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.txn.get_raw(db.id(), key.as_ref())
+    }
+
+    /// Looks up `key` and returns the value in place, wrapped in a guard that
+    /// keeps the transaction borrowed until it is dropped, so the transaction
+    /// can't end under it:
+    ///
+    /// ```compile_fail
+    /// # fn f(env: &rlmdb::DBEnv) -> Result<(), rlmdb::LMDBError> {
+    /// let txn = env.begin_txn_read_only()?;
+    /// let db = env.open_db::<&str, Vec<u8>>(&txn, None)?;
+    /// let value = txn.get_guarded(&db, "key")?;
+    /// txn.commit()?;
+    /// println!("{value:?}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_guarded<'txn, K>(
+        &'txn self,
+        db: &'env Database<K, impl AsRef<[u8]>>,
+        key: K,
+    ) -> Result<Option<ValueGuard<'txn>>, crate::LMDBError>
+    where
+        K: AsRef<[u8]>,
+    {
+        Ok(self
+            .txn
+            .get_raw(db.id(), key.as_ref())?
+            .map(ValueGuard::new))
+    }
+
+    /// Ends the transaction, releasing its snapshot. Same as `abort` for a
+    /// read-only transaction, apart from reporting errors.
+    pub fn commit(self) -> Result<(), crate::LMDBError> {
+        self.txn.commit()
+    }
+
+    pub fn abort(self) {
+        self.txn.abort()
+    }
+}
+
+impl<'env> Deref for ReadOnlyTransaction<'env> {
+    type Target = Transaction<'env>;
+
+    fn deref(&self) -> &Transaction<'env> {
+        &self.txn
+    }
+}
+
+impl<'env> fmt::Debug for ReadOnlyTransaction<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.txn.fmt(f)
+    }
+}
+
+/// Splits a value written by `put_versioned` into its version and data.
+fn split_version(stored: &[u8]) -> Result<(u64, &[u8]), crate::LMDBError> {
+    match stored.split_first_chunk::<8>() {
+        Some((version, data)) => Ok((u64::from_be_bytes(*version), data)),
+        None => Err(crate::LMDBError::MissingVersion { len: stored.len() }),
+    }
+}
+
+/// Views space reserved by an `MDB_RESERVE` put as a writable slice.
+///
+/// # Safety
+///
+/// `data` must come from a reserve put whose space is not moved by another
+/// write, or otherwise accessed, while the slice is alive.
+unsafe fn reserved_slice<'a>(data: &sys::MDB_val) -> &'a mut [u8] {
+    if data.mv_size == 0 {
+        &mut []
+    } else {
+        unsafe { std::slice::from_raw_parts_mut(data.mv_data as *mut u8, data.mv_size) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DBEnvBuilder;
+
+    #[test]
+    fn read_only_txn_cannot_begin_child() {
+        let env = DBEnvBuilder::ephemeral().open(None).unwrap();
+        let mut txn = Transaction::new(&env, None, TransactionType::ReadOnly).unwrap();
+        assert!(matches!(
+            txn.begin_child(),
+            Err(crate::LMDBError::ChildOfReadOnlyTxn)
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use std::{io, ops::Deref, slice, str};
+
+use crate::{LMDBError, sys};
+
+/// Decodes a stored value into an existing buffer, reusing its allocation.
+///
+/// This lets tight read loops avoid allocating a fresh `V` for every lookup.
+pub trait DecodeInto {
+    /// Replaces the contents of `self` with the decoded `bytes`.
+    fn decode_into(&mut self, bytes: &[u8]) -> Result<(), LMDBError>;
+}
+
+impl DecodeInto for Vec<u8> {
+    fn decode_into(&mut self, bytes: &[u8]) -> Result<(), LMDBError> {
+        self.clear();
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl DecodeInto for String {
+    fn decode_into(&mut self, bytes: &[u8]) -> Result<(), LMDBError> {
+        let s = str::from_utf8(bytes)
+            .map_err(|e| LMDBError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        self.clear();
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+/// Converts a value read from the database into an owned output type, copying
+/// the bytes out.
+///
+/// Used by [`Transaction::get`](crate::Transaction::get), which works in any
+/// transaction.
+pub trait FromVal: Sized {
+    fn from_val(bytes: &[u8]) -> Result<Self, LMDBError>;
+}
+
+impl FromVal for Vec<u8> {
+    fn from_val(bytes: &[u8]) -> Result<Self, LMDBError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl FromVal for String {
+    fn from_val(bytes: &[u8]) -> Result<Self, LMDBError> {
+        <&str>::from_val_ref(bytes).map(str::to_owned)
+    }
+}
+
+/// Converts a value read from the database into an output that may borrow
+/// from the memory map for `'a`, such as `&'a [u8]` or `&'a str`. Every
+/// [`FromVal`] type is one too.
+///
+/// Used by [`ReadOnlyTransaction::get`](crate::ReadOnlyTransaction::get):
+/// borrowed reads need a read-only transaction, since a later write in the
+/// same transaction could move the value.
+pub trait FromValRef<'a>: Sized {
+    fn from_val_ref(bytes: &'a [u8]) -> Result<Self, LMDBError>;
+}
+
+impl<'a> FromValRef<'a> for &'a [u8] {
+    fn from_val_ref(bytes: &'a [u8]) -> Result<Self, LMDBError> {
+        Ok(bytes)
+    }
+}
+
+impl<'a> FromValRef<'a> for &'a str {
+    fn from_val_ref(bytes: &'a [u8]) -> Result<Self, LMDBError> {
+        str::from_utf8(bytes)
+            .map_err(|e| LMDBError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+}
+
+impl<T: FromVal> FromValRef<'_> for T {
+    fn from_val_ref(bytes: &[u8]) -> Result<Self, LMDBError> {
+        T::from_val(bytes)
+    }
+}
+
+/// A value read in place from the memory map, returned by
+/// [`ReadOnlyTransaction::get_guarded`](crate::ReadOnlyTransaction::get_guarded).
+///
+/// The guard borrows the transaction it was read from, so that transaction
+/// can't be committed or aborted while the guard is alive. It derefs to the
+/// stored bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueGuard<'txn> {
+    bytes: &'txn [u8],
+}
+
+impl<'txn> ValueGuard<'txn> {
+    pub(crate) fn new(bytes: &'txn [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl Deref for ValueGuard<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+impl AsRef<[u8]> for ValueGuard<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+/// Builds an `MDB_val` pointing at `bytes`.
+pub(crate) fn to_mdb_val(bytes: &[u8]) -> sys::MDB_val {
+    sys::MDB_val {
+        mv_size: bytes.len(),
+        mv_data: bytes.as_ptr() as *mut _,
+    }
+}
+
+/// Views an `MDB_val` returned by LMDB as a byte slice.
+///
+/// # Safety
+///
+/// `val` must describe memory that stays valid and unmodified for `'a`,
+/// e.g. a value returned by LMDB within a transaction that outlives `'a`.
+pub(crate) unsafe fn from_mdb_val<'a>(val: &sys::MDB_val) -> &'a [u8] {
+    if val.mv_size == 0 {
+        // LMDB may hand back a null pointer for empty data.
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(val.mv_data as *const u8, val.mv_size) }
+    }
+}
@@ -0,0 +1,78 @@
+//! Allocation counts, measured with a counting global allocator; kept in its
+//! own test binary so the allocator doesn't affect other tests.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+mod common;
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Allocations made on this thread while running `f`.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn get_reuse_does_not_allocate() {
+    let env = common::open_env("get_reuse_alloc");
+    let txn = env.begin_txn().unwrap();
+    let bytes = env
+        .open_named_db::<_, &str, Vec<u8>>(&txn, "bytes", Some(rlmdb::DBFlags::MDB_CREATE))
+        .unwrap();
+    let text = env
+        .open_named_db::<_, &str, String>(&txn, "text", Some(rlmdb::DBFlags::MDB_CREATE))
+        .unwrap();
+    for key in ["a", "b", "c"] {
+        txn.put(&bytes, key, key.repeat(64).into_bytes(), None)
+            .unwrap();
+        txn.put(&text, key, key.repeat(64), None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut buf = Vec::new();
+    let mut s = String::new();
+    // Warm up, so both buffers already have the capacity they need
+    assert!(txn.get_reuse(&bytes, "a", &mut buf).unwrap());
+    assert!(txn.get_reuse(&text, "a", &mut s).unwrap());
+
+    let count = allocations(|| {
+        for _ in 0..1000 {
+            for key in ["a", "b", "c", "missing"] {
+                txn.get_reuse(&bytes, key, &mut buf).unwrap();
+                txn.get_reuse(&text, key, &mut s).unwrap();
+            }
+        }
+    });
+    assert_eq!(count, 0);
+    assert_eq!(buf, b"c".repeat(64));
+    assert_eq!(s, "c".repeat(64));
+}
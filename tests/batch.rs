@@ -0,0 +1,39 @@
+use rlmdb::{PutFlags, WriteBatch};
+
+mod common;
+
+#[test]
+fn apply_batch_is_all_or_nothing() {
+    let env = common::open_env("write_batch");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "old", "1", None).unwrap();
+    txn.commit().unwrap();
+
+    let mut batch = WriteBatch::new();
+    batch
+        .put(&db, "a", "1", None)
+        .put(&db, "b", "2", None)
+        .delete(&db, "old")
+        .delete(&db, "missing");
+    assert_eq!(batch.len(), 4);
+    env.apply_batch(&batch).unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(txn.get(&db, "a").unwrap(), Some("1"));
+    assert_eq!(txn.get(&db, "b").unwrap(), Some("2"));
+    assert!(!txn.contains_key(&db, "old").unwrap());
+    drop(txn);
+
+    // The second put fails, so the first one and the delete are undone too
+    let mut bad = WriteBatch::new();
+    bad.put(&db, "c", "3", None)
+        .put(&db, "a", "x", Some(PutFlags::MDB_NOOVERWRITE))
+        .delete(&db, "b");
+    assert!(env.apply_batch(&bad).unwrap_err().is_key_exists());
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert!(!txn.contains_key(&db, "c").unwrap());
+    assert_eq!(txn.get(&db, "a").unwrap(), Some("1"));
+    assert_eq!(txn.get(&db, "b").unwrap(), Some("2"));
+}
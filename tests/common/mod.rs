@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+
+use std::{
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use rlmdb::{DBEnv, DBEnvBuilder, EnvFlags};
+
+/// A path under the temp directory that no other test uses.
+pub fn temp_path(name: &str) -> PathBuf {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("rlmdb-test-{}-{name}-{id}.mdb", process::id()))
+}
+
+/// A builder for a fresh environment with room for a few named databases,
+/// whose files are removed when it is dropped.
+pub fn builder(name: &str) -> DBEnvBuilder {
+    let mut builder = DBEnvBuilder::new(temp_path(name));
+    builder
+        .set_map_size(16 << 20)
+        .set_max_dbs(8)
+        .set_remove_on_drop(true);
+    builder
+}
+
+/// Opens a fresh environment from `builder`.
+pub fn open_env(name: &str) -> DBEnv {
+    builder(name).open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap()
+}
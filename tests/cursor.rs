@@ -0,0 +1,767 @@
+use rlmdb::{DBFlags, LMDBError, PutFlags, error::MDBError};
+
+mod common;
+
+#[test]
+fn current_sizes_reports_entry_lengths() {
+    let env = common::open_env("current_sizes");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, vec![1; 3], vec![2; 17], None).unwrap();
+    txn.put(&db, vec![5; 8], vec![], None).unwrap();
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert!(cursor.current_sizes().is_err());
+    cursor.seek_exact(vec![1; 3]).unwrap();
+    assert_eq!(cursor.current_sizes().unwrap(), Some((3, 17)));
+    cursor.next().unwrap();
+    assert_eq!(cursor.current_sizes().unwrap(), Some((8, 0)));
+}
+
+#[test]
+fn reset_position_rewinds_to_the_start() {
+    let env = common::open_env("reset_position");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for key in [b"a", b"b", b"c", b"d"] {
+        txn.put(&db, key.to_vec(), key.to_vec(), None).unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    cursor.next().unwrap();
+    assert_eq!(cursor.next().unwrap().unwrap().0, b"b");
+    cursor.reset_position().unwrap();
+    assert!(cursor.current_sizes().is_err());
+
+    let keys: Vec<_> = std::iter::from_fn(|| cursor.next().unwrap())
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, [b"a", b"b", b"c", b"d"]);
+}
+
+#[test]
+fn dup_item_size_reads_fixed_size() {
+    let env = common::open_env("dup_item_size");
+    let txn = env.begin_txn().unwrap();
+    let fixed = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "fixed",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT | DBFlags::MDB_DUPFIXED),
+        )
+        .unwrap();
+    let plain = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "plain", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    for i in 0u64..4 {
+        txn.put(&fixed, b"k".to_vec(), i.to_be_bytes().to_vec(), None)
+            .unwrap();
+    }
+    txn.put(&plain, b"k".to_vec(), vec![0; 8], None).unwrap();
+
+    let mut cursor = txn.cursor(&fixed).unwrap();
+    assert!(cursor.dup_item_size().is_err());
+    cursor.seek_exact(b"k".to_vec()).unwrap();
+    assert_eq!(cursor.dup_item_size().unwrap(), Some(8));
+
+    let mut cursor = txn.cursor(&plain).unwrap();
+    cursor.seek_exact(b"k".to_vec()).unwrap();
+    assert_eq!(cursor.dup_item_size().unwrap(), None);
+}
+
+#[test]
+fn nth_dup_indexes_into_duplicates() {
+    let env = common::open_env("nth_dup");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    for value in ["v0", "v1", "v2", "v3", "v4"] {
+        txn.put(&db, b"k".to_vec(), value.into(), None).unwrap();
+    }
+    txn.put(&db, b"l".to_vec(), b"other".to_vec(), None)
+        .unwrap();
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(
+        cursor.nth_dup(b"k".to_vec(), 0).unwrap(),
+        Some(b"v0".to_vec())
+    );
+    assert_eq!(
+        cursor.nth_dup(b"k".to_vec(), 4).unwrap(),
+        Some(b"v4".to_vec())
+    );
+    assert_eq!(cursor.nth_dup(b"k".to_vec(), 5).unwrap(), None);
+    assert_eq!(cursor.nth_dup(b"missing".to_vec(), 0).unwrap(), None);
+}
+
+#[test]
+fn txn_cursor_opens_in_both_txn_types() {
+    let env = common::open_env("txn_cursor");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"a".to_vec(), b"1".to_vec(), None).unwrap();
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(
+        cursor.get_first().unwrap(),
+        Some((b"a".to_vec(), b"1".to_vec()))
+    );
+    drop(cursor);
+    txn.commit().unwrap();
+
+    // Read-only cursors are closed as each one is dropped
+    let txn = env.begin_txn_read_only().unwrap();
+    for _ in 0..1000 {
+        let mut cursor = txn.cursor(&db).unwrap();
+        assert_eq!(
+            cursor.get_first().unwrap(),
+            Some((b"a".to_vec(), b"1".to_vec()))
+        );
+    }
+    txn.abort();
+}
+
+#[test]
+fn get_first_and_last() {
+    let env = common::open_env("first_last");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(cursor.get_first().unwrap(), None);
+    assert_eq!(cursor.get_last().unwrap(), None);
+    drop(cursor);
+
+    for key in ["m", "a", "z"] {
+        txn.put(&db, key.into(), key.repeat(2).into(), None)
+            .unwrap();
+    }
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(
+        cursor.get_first().unwrap(),
+        Some((b"a".to_vec(), b"aa".to_vec()))
+    );
+    assert_eq!(
+        cursor.get_last().unwrap(),
+        Some((b"z".to_vec(), b"zz".to_vec()))
+    );
+}
+
+#[test]
+fn next_and_prev_walk_the_whole_database() {
+    let env = common::open_env("next_prev");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for key in ["c", "a", "b"] {
+        txn.put(&db, key.into(), key.into(), None).unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    let mut forward = Vec::new();
+    while let Some((key, value)) = cursor.next().unwrap() {
+        assert_eq!(key, value);
+        forward.push(key);
+    }
+    assert_eq!(forward, [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    let mut backward = Vec::new();
+    while let Some((key, _)) = cursor.prev().unwrap() {
+        backward.push(key);
+    }
+    forward.reverse();
+    assert_eq!(backward, forward);
+}
+
+#[test]
+fn iter_starts_from_the_first_entry() {
+    let env = common::open_env("cursor_iter");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for key in ["b", "a", "c"] {
+        txn.put(&db, key.into(), key.into(), None).unwrap();
+    }
+
+    // Even a positioned cursor is iterated from the start
+    let mut cursor = txn.cursor(&db).unwrap();
+    cursor.get_last().unwrap();
+    let keys: Vec<_> = cursor.iter().map(|entry| entry.unwrap().0).collect();
+    assert_eq!(keys, [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+
+    let mut count = 0;
+    for entry in txn.cursor(&db).unwrap().iter() {
+        let (key, value) = entry.unwrap();
+        assert_eq!(key, value);
+        count += 1;
+    }
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn seek_range_and_seek_exact() {
+    let env = common::open_env("seek");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for key in ["apple", "banana", "cherry"] {
+        txn.put(&db, key.into(), key.to_uppercase().into(), None)
+            .unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(
+        cursor.seek_range(b"b".to_vec()).unwrap(),
+        Some((b"banana".to_vec(), b"BANANA".to_vec()))
+    );
+    assert_eq!(
+        cursor.next().unwrap(),
+        Some((b"cherry".to_vec(), b"CHERRY".to_vec()))
+    );
+    assert_eq!(cursor.seek_range(b"d".to_vec()).unwrap(), None);
+
+    assert_eq!(
+        cursor.seek_exact(b"apple".to_vec()).unwrap(),
+        Some((b"apple".to_vec(), b"APPLE".to_vec()))
+    );
+    assert_eq!(cursor.seek_exact(b"app".to_vec()).unwrap(), None);
+}
+
+#[test]
+fn seek_range_on_dupsort_lands_on_smallest_dup() {
+    let env = common::open_env("seek_range_dup");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    for (key, value) in [("a", "3"), ("a", "1"), ("a", "5"), ("c", "2")] {
+        txn.put(&db, key.into(), value.into(), None).unwrap();
+    }
+    let entry = |key: &str, value: &str| Some((key.as_bytes().to_vec(), value.as_bytes().to_vec()));
+
+    // By key: the first duplicate, then the rest in order
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(cursor.seek_range(b"a".to_vec()).unwrap(), entry("a", "1"));
+    assert_eq!(cursor.next().unwrap(), entry("a", "3"));
+    assert_eq!(cursor.seek_range(b"b".to_vec()).unwrap(), entry("c", "2"));
+
+    // By key and data: the first duplicate at or above the data, same key only
+    assert_eq!(
+        cursor
+            .seek_range_both(b"a".to_vec(), b"2".to_vec())
+            .unwrap(),
+        entry("a", "3")
+    );
+    assert_eq!(cursor.next().unwrap(), entry("a", "5"));
+    assert_eq!(
+        cursor
+            .seek_range_both(b"a".to_vec(), b"6".to_vec())
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        cursor
+            .seek_range_both(b"b".to_vec(), b"0".to_vec())
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn remaining_counts_entries_after_the_cursor() {
+    let env = common::open_env("cursor_remaining");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for i in 0u32..100 {
+        txn.put(&db, i.to_be_bytes().to_vec(), vec![1], None)
+            .unwrap();
+    }
+
+    // An unpositioned cursor has every entry ahead of it
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(cursor.remaining_exact().unwrap(), 100);
+    assert_eq!(cursor.remaining_estimate().unwrap(), 100);
+
+    for _ in 0..25 {
+        cursor.next().unwrap().unwrap();
+    }
+    assert_eq!(cursor.remaining_exact().unwrap(), 75);
+    let estimate = cursor.remaining_estimate().unwrap();
+    assert!((73..=77).contains(&estimate), "estimate {estimate}");
+    // Counting doesn't move the cursor
+    assert_eq!(cursor.next().unwrap().unwrap().0, 25u32.to_be_bytes());
+
+    cursor.get_last().unwrap();
+    assert_eq!(cursor.remaining_exact().unwrap(), 0);
+    assert_eq!(cursor.remaining_estimate().unwrap(), 0);
+    cursor.get_first().unwrap();
+    assert_eq!(cursor.remaining_exact().unwrap(), 99);
+    assert_eq!(cursor.remaining_estimate().unwrap(), 99);
+}
+
+#[test]
+fn remaining_exact_counts_duplicates() {
+    let env = common::open_env("cursor_remaining_dups");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_db::<Vec<u8>, Vec<u8>>(&txn, Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT))
+        .unwrap();
+    for value in [b"1", b"2", b"3"] {
+        txn.put(&db, b"k".to_vec(), value.to_vec(), None).unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    cursor.next().unwrap();
+    cursor.next().unwrap();
+    assert_eq!(cursor.remaining_exact().unwrap(), 1);
+}
+
+#[test]
+fn count_returns_duplicates_of_current_key() {
+    let env = common::open_env("cursor_count");
+    let txn = env.begin_txn().unwrap();
+    let dups = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    let plain = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "plain", None)
+        .unwrap();
+    for value in ["1", "2", "3"] {
+        txn.put(&dups, b"a".to_vec(), value.as_bytes().to_vec(), None)
+            .unwrap();
+    }
+    txn.put(&dups, b"b".to_vec(), b"1".to_vec(), None).unwrap();
+    txn.put(&plain, b"a".to_vec(), b"1".to_vec(), None).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&dups).unwrap();
+    assert!(cursor.count().is_err());
+    cursor.seek_exact(b"a".to_vec()).unwrap();
+    assert_eq!(cursor.count().unwrap(), 3);
+    cursor.seek_exact(b"b".to_vec()).unwrap();
+    assert_eq!(cursor.count().unwrap(), 1);
+
+    let mut cursor = txn.cursor(&plain).unwrap();
+    cursor.get_first().unwrap();
+    assert!(matches!(
+        cursor.count(),
+        Err(LMDBError::MDB(MDBError::Incompatible))
+    ));
+}
+
+#[test]
+fn dup_navigation_walks_values_of_a_key() {
+    let env = common::open_env("cursor_dup_nav");
+    let txn = env.begin_txn().unwrap();
+    let dups = env
+        .open_db::<Vec<u8>, Vec<u8>>(&txn, Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT))
+        .unwrap();
+    for (key, value) in [("a", "1"), ("a", "2"), ("a", "3"), ("b", "9")] {
+        txn.put(&dups, key.into(), value.into(), None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let entry = |key: &str, value: &str| Some((key.into(), value.into()));
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&dups).unwrap();
+    cursor.seek_exact(b"a".to_vec()).unwrap();
+    assert_eq!(cursor.last_dup().unwrap(), entry("a", "3"));
+    assert_eq!(cursor.prev_dup().unwrap(), entry("a", "2"));
+    assert_eq!(cursor.first_dup().unwrap(), entry("a", "1"));
+    assert_eq!(cursor.next_dup().unwrap(), entry("a", "2"));
+    assert_eq!(cursor.next_dup().unwrap(), entry("a", "3"));
+    assert_eq!(cursor.next_dup().unwrap(), None);
+
+    assert_eq!(cursor.first_dup().unwrap(), entry("a", "1"));
+    assert_eq!(cursor.next_nodup().unwrap(), entry("b", "9"));
+    assert_eq!(cursor.prev_nodup().unwrap(), entry("a", "3"));
+    assert_eq!(cursor.prev_dup().unwrap(), entry("a", "2"));
+}
+
+#[test]
+fn get_both_probes_a_duplicate_set() {
+    let env = common::open_env("cursor_get_both");
+    let txn = env.begin_txn().unwrap();
+    let dups = env
+        .open_db::<Vec<u8>, Vec<u8>>(&txn, Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT))
+        .unwrap();
+    for (key, value) in [("a", "2"), ("a", "4"), ("b", "1")] {
+        txn.put(&dups, key.into(), value.into(), None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let bytes = |s: &str| s.as_bytes().to_vec();
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&dups).unwrap();
+    assert_eq!(
+        cursor.get_both(bytes("a"), bytes("4")).unwrap(),
+        Some((bytes("a"), bytes("4")))
+    );
+    assert_eq!(cursor.get_both(bytes("a"), bytes("3")).unwrap(), None);
+    assert_eq!(cursor.get_both(bytes("c"), bytes("1")).unwrap(), None);
+
+    assert_eq!(
+        cursor.get_both_range(bytes("a"), bytes("3")).unwrap(),
+        Some((bytes("a"), bytes("4")))
+    );
+    // The cursor is left on the located pair
+    assert_eq!(cursor.next().unwrap(), Some((bytes("b"), bytes("1"))));
+    assert_eq!(cursor.get_both_range(bytes("a"), bytes("5")).unwrap(), None);
+}
+
+#[test]
+fn cursors_txns_and_env_drop_in_any_allowed_order() {
+    let env = common::open_env("drop_orders");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"k".to_vec(), b"v".to_vec(), None).unwrap();
+    let mut cursor = txn.cursor(&db).unwrap();
+    cursor.get_first().unwrap();
+    drop(cursor);
+    txn.commit().unwrap();
+
+    // Cursors dropped in declaration order, then in reverse at scope end
+    for reverse in [false, true] {
+        let txn = env.begin_txn_read_only().unwrap();
+        let mut first = txn.cursor(&db).unwrap();
+        let mut second = txn.cursor(&db).unwrap();
+        assert!(first.get_first().unwrap().is_some());
+        assert!(second.get_last().unwrap().is_some());
+        if !reverse {
+            drop(first);
+            drop(second);
+        }
+    }
+
+    // A write txn aborted, committed or dropped once its cursors are gone
+    for end in 0..3 {
+        let txn = env.begin_txn().unwrap();
+        let mut cursor = txn.cursor(&db).unwrap();
+        cursor.put(b"k2".to_vec(), b"v".to_vec(), None).unwrap();
+        let iter = db.iter(&txn).unwrap();
+        drop(cursor);
+        drop(iter);
+        match end {
+            0 => txn.abort(),
+            1 => txn.commit().unwrap(),
+            _ => drop(txn),
+        }
+    }
+
+    // The database handle may outlive the txn that opened it, and the
+    // environment closes last
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(cursor.get_last().unwrap().unwrap().0, b"k2");
+    drop(cursor);
+    drop(txn);
+    drop(db);
+    env.close();
+}
+
+#[test]
+fn get_multiple_pages_through_fixed_size_duplicates() {
+    let env = common::open_env("cursor_get_multiple");
+    let txn = env.begin_txn().unwrap();
+    let fixed = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "fixed",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT | DBFlags::MDB_DUPFIXED),
+        )
+        .unwrap();
+    let dups = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    // Enough values to span several pages
+    for i in 0u32..3000 {
+        txn.put(&fixed, b"a".to_vec(), i.to_be_bytes().to_vec(), None)
+            .unwrap();
+    }
+    txn.put(&fixed, b"b".to_vec(), 7u32.to_be_bytes().to_vec(), None)
+        .unwrap();
+    txn.put(&dups, b"a".to_vec(), b"x".to_vec(), None).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&fixed).unwrap();
+    assert!(cursor.get_multiple().is_err());
+    cursor.seek_exact(b"a".to_vec()).unwrap();
+    let size = cursor.dup_item_size().unwrap().unwrap();
+    let mut values = Vec::new();
+    let mut pages = 0;
+    let mut page = cursor.get_multiple().unwrap();
+    while let Some(bytes) = page {
+        pages += 1;
+        values.extend(
+            bytes
+                .chunks(size)
+                .map(|value| u32::from_be_bytes(value.try_into().unwrap())),
+        );
+        page = cursor.next_multiple().unwrap();
+    }
+    assert!(pages > 1, "{pages} pages");
+    assert_eq!(values, (0..3000).collect::<Vec<_>>());
+    assert_eq!(
+        cursor.next().unwrap(),
+        Some((b"b".to_vec(), 7u32.to_be_bytes().to_vec()))
+    );
+
+    let mut cursor = txn.cursor(&dups).unwrap();
+    cursor.get_first().unwrap();
+    assert!(matches!(
+        cursor.get_multiple(),
+        Err(LMDBError::MDB(MDBError::Incompatible))
+    ));
+
+    let write_txn = env.begin_txn().unwrap();
+    let mut cursor = write_txn.cursor(&fixed).unwrap();
+    cursor.seek_exact(b"a".to_vec()).unwrap();
+    assert!(matches!(
+        cursor.get_multiple(),
+        Err(LMDBError::BorrowedReadInWriteTxn)
+    ));
+}
+
+#[test]
+fn range_stops_before_end_key() {
+    let env = common::open_env("cursor_range");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for key in ["a", "b", "c", "d"] {
+        txn.put(&db, key.into(), key.into(), None).unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    let keys: Vec<_> = cursor
+        .range(b"b".to_vec(), b"d".to_vec())
+        .map(|entry| entry.unwrap().0)
+        .collect();
+    assert_eq!(keys, [b"b", b"c"]);
+    assert_eq!(cursor.range(b"bb".to_vec(), b"z".to_vec()).count(), 2);
+    assert_eq!(cursor.range(b"e".to_vec(), b"z".to_vec()).count(), 0);
+    assert_eq!(cursor.range(b"d".to_vec(), b"a".to_vec()).count(), 0);
+}
+
+#[test]
+fn range_follows_integer_key_order() {
+    let env = common::open_env("cursor_range_integer");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_db::<Vec<u8>, Vec<u8>>(&txn, Some(DBFlags::MDB_CREATE | DBFlags::MDB_INTEGERKEY))
+        .unwrap();
+    // In native byte order 256 compares below 2 bytewise on little-endian
+    // machines, so this only passes if the range uses the database's order
+    for key in [1usize, 2, 256, 300] {
+        txn.put(&db, key.to_ne_bytes().to_vec(), vec![], None)
+            .unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    let keys: Vec<_> = cursor
+        .range(
+            1usize.to_ne_bytes().to_vec(),
+            300usize.to_ne_bytes().to_vec(),
+        )
+        .map(|entry| usize::from_ne_bytes(entry.unwrap().0.try_into().unwrap()))
+        .collect();
+    assert_eq!(keys, [1, 2, 256]);
+}
+
+#[test]
+fn prefix_yields_keys_starting_with_prefix() {
+    let env = common::open_env("cursor_prefix");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for key in [
+        &b"ab"[..],
+        b"abc",
+        b"abd",
+        b"ac",
+        b"\xff\xff",
+        b"\xff\xff\x01",
+    ] {
+        txn.put(&db, key.to_vec(), vec![1], None).unwrap();
+    }
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    let keys: Vec<_> = cursor
+        .prefix(b"ab".to_vec())
+        .map(|entry| entry.unwrap().0)
+        .collect();
+    assert_eq!(keys, [&b"ab"[..], b"abc", b"abd"]);
+    assert_eq!(cursor.prefix(b"b".to_vec()).count(), 0);
+    // An empty prefix matches everything
+    assert_eq!(cursor.prefix(Vec::new()).count(), 6);
+    // All-0xFF prefixes run to the end of the database
+    assert_eq!(cursor.prefix(b"\xff\xff".to_vec()).count(), 2);
+    assert_eq!(cursor.prefix(b"\xff\xff\xff".to_vec()).count(), 0);
+}
+
+#[test]
+fn put_appends_through_cursor() {
+    let env = common::open_env("cursor_put_append");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "a", None)
+        .unwrap();
+    let dups = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+
+    {
+        let mut cursor = txn.cursor(&db).unwrap();
+        for i in 0u32..100 {
+            cursor
+                .put(
+                    i.to_be_bytes().to_vec(),
+                    vec![i as u8],
+                    Some(PutFlags::MDB_APPEND),
+                )
+                .unwrap();
+        }
+        assert!(matches!(
+            cursor.put(
+                5u32.to_be_bytes().to_vec(),
+                vec![],
+                Some(PutFlags::MDB_APPEND)
+            ),
+            Err(LMDBError::MDB(MDBError::KeyExists))
+        ));
+        // The cursor is left on the entry it wrote
+        cursor.put(b"m".to_vec(), b"x".to_vec(), None).unwrap();
+        assert_eq!(cursor.current_sizes().unwrap(), Some((1, 1)));
+
+        let mut cursor = txn.cursor(&dups).unwrap();
+        for value in [1u8, 2, 3] {
+            cursor
+                .put(b"k".to_vec(), vec![value], Some(PutFlags::MDB_APPENDDUP))
+                .unwrap();
+        }
+        assert_eq!(cursor.count().unwrap(), 3);
+    }
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(
+        cursor.get_last().unwrap(),
+        Some((b"m".to_vec(), b"x".to_vec()))
+    );
+    assert_eq!(db.iter(&txn).unwrap().count(), 101);
+    assert!(matches!(
+        cursor.put(b"z".to_vec(), vec![], None),
+        Err(LMDBError::Io(_))
+    ));
+}
+
+#[test]
+fn del_leaves_next_on_the_following_entry() {
+    let env = common::open_env("cursor_del");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "a", None)
+        .unwrap();
+    let dups = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    for i in 0u8..10 {
+        txn.put(&db, vec![i], vec![i], None).unwrap();
+    }
+    for (key, value) in [(1u8, 1u8), (1, 2), (1, 3), (2, 1), (2, 2)] {
+        txn.put(&dups, vec![key], vec![value], None).unwrap();
+    }
+
+    {
+        let mut cursor = txn.cursor(&db).unwrap();
+        assert!(cursor.del(None).is_err());
+        // Deleting every even key while walking forward skips nothing
+        let mut entry = cursor.get_first().unwrap();
+        while let Some((key, _)) = entry {
+            if key[0] % 2 == 0 {
+                cursor.del(None).unwrap();
+            }
+            entry = cursor.next().unwrap();
+        }
+
+        let mut cursor = txn.cursor(&dups).unwrap();
+        cursor.get_first().unwrap();
+        cursor.del(None).unwrap();
+        assert_eq!(cursor.count().unwrap(), 2);
+        cursor.del(Some(PutFlags::MDB_NODUPDATA)).unwrap();
+        assert_eq!(cursor.next().unwrap(), Some((vec![2], vec![1])));
+    }
+
+    let keys: Vec<_> = db
+        .iter(&txn)
+        .unwrap()
+        .map(|entry| entry.unwrap().0[0])
+        .collect();
+    assert_eq!(keys, [1, 3, 5, 7, 9]);
+    assert_eq!(txn.get::<_, Vec<u8>>(&dups, vec![1]).unwrap(), None);
+}
+
+/// A value type that can be stored but not decoded, so only key scans can
+/// read a database of it.
+struct WriteOnly(Vec<u8>);
+
+impl AsRef<[u8]> for WriteOnly {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[test]
+fn iter_keys_and_values_decode_one_half() {
+    let env = common::open_env("cursor_keys_values");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, WriteOnly>(&txn, "a", None)
+        .unwrap();
+    for i in [3u8, 1, 2] {
+        txn.put(&db, vec![i], WriteOnly(vec![i * 10]), None)
+            .unwrap();
+    }
+
+    // Keys can be scanned without being able to decode the values
+    let keys: Vec<_> = txn
+        .cursor(&db)
+        .unwrap()
+        .iter_keys()
+        .map(|key| key.unwrap())
+        .collect();
+    assert_eq!(keys, [[1], [2], [3]]);
+
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "a", None)
+        .unwrap();
+    let values: Vec<_> = txn
+        .cursor(&db)
+        .unwrap()
+        .iter_values()
+        .map(|value| value.unwrap())
+        .collect();
+    assert_eq!(values, [[10], [20], [30]]);
+}
@@ -0,0 +1,331 @@
+use std::{collections::BTreeMap, io, ops::Bound};
+
+use rlmdb::{CompositeKey, DBFlags, LMDBError, SetDatabase, StrDatabase};
+
+mod common;
+
+#[test]
+fn str_database_round_trips_unicode_keys() {
+    let env = common::open_env("str_db");
+    let txn = env.begin_txn().unwrap();
+    let db = StrDatabase::new(env.open_db::<String, Vec<u8>>(&txn, None).unwrap());
+
+    for (key, value) in [("héllo", "1"), ("hé", "2"), ("hêllo", "3"), ("日本", "4")] {
+        db.put(&txn, key, value.into()).unwrap();
+    }
+    assert_eq!(db.get(&txn, "日本").unwrap(), Some(b"4".to_vec()));
+    assert_eq!(db.get(&txn, "日").unwrap(), None);
+
+    let keys: Vec<_> = db
+        .scan_prefix(&txn, "hé")
+        .unwrap()
+        .map(|entry| entry.unwrap().0)
+        .collect();
+    assert_eq!(keys, ["hé", "héllo"]);
+    assert_eq!(db.scan_prefix(&txn, "").unwrap().count(), 4);
+
+    assert!(matches!(
+        db.put(&txn, "a\0b", vec![]),
+        Err(LMDBError::NulInStrKey)
+    ));
+    assert!(matches!(db.get(&txn, "a\0"), Err(LMDBError::NulInStrKey)));
+    assert!(db.delete(&txn, "hé").unwrap());
+    assert!(!db.delete(&txn, "hé").unwrap());
+    assert_eq!(db.scan_prefix(&txn, "").unwrap().count(), 3);
+}
+
+#[test]
+fn str_database_rejects_invalid_utf8_keys() {
+    let env = common::open_env("str_db_utf8");
+    let txn = env.begin_txn().unwrap();
+    let raw = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    // Sorted by bytes: "k1", then the invalid "k\x80", then "ké"
+    for key in [&b"k1"[..], b"k\x80", "ké".as_bytes()] {
+        txn.put(&raw, key.to_vec(), b"v".to_vec(), None).unwrap();
+    }
+
+    let db = StrDatabase::new(env.open_db::<String, Vec<u8>>(&txn, None).unwrap());
+    let entries: Vec<_> = db.scan_prefix(&txn, "k").unwrap().collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].as_ref().unwrap().0, "k1");
+    assert!(matches!(
+        &entries[1],
+        Err(LMDBError::Io(e)) if e.kind() == io::ErrorKind::InvalidData
+    ));
+    // The scan carries on past the bad key
+    assert_eq!(entries[2].as_ref().unwrap().0, "ké");
+}
+
+#[test]
+fn iter_visits_every_entry_in_key_order() {
+    let env = common::open_env("db_iter");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    let mut inserted = Vec::new();
+    for key in ["b", "a", "c", "ab"] {
+        let entry = (key.as_bytes().to_vec(), key.repeat(2).into_bytes());
+        txn.put(&db, entry.0.clone(), entry.1.clone(), None)
+            .unwrap();
+        inserted.push(entry);
+    }
+    inserted.sort();
+
+    let mut seen = Vec::new();
+    for entry in db.iter(&txn).unwrap() {
+        seen.push(entry.unwrap());
+    }
+    assert_eq!(seen, inserted);
+}
+
+#[test]
+fn namespaces_are_isolated() {
+    let env = common::open_env("namespaced");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    let a = db.namespaced(b"a/".to_vec());
+    let b = db.namespaced(b"b/".to_vec());
+    for key in ["1", "2", "3"] {
+        a.put(&txn, key.into(), b"A".to_vec()).unwrap();
+    }
+    b.put(&txn, b"1".to_vec(), b"B".to_vec()).unwrap();
+    // A raw key that sorts right after the "a/" namespace
+    txn.put(&db, b"a0".to_vec(), b"x".to_vec(), None).unwrap();
+
+    assert_eq!(a.get(&txn, b"1".to_vec()).unwrap(), Some(b"A".to_vec()));
+    assert_eq!(b.get(&txn, b"1".to_vec()).unwrap(), Some(b"B".to_vec()));
+    assert_eq!(b.get(&txn, b"2".to_vec()).unwrap(), None);
+
+    let keys: Vec<_> = a.iter(&txn).unwrap().map(|e| e.unwrap().0).collect();
+    assert_eq!(keys, [b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+    let keys: Vec<_> = a
+        .range(&txn, &b"2"[..], &b"9"[..])
+        .unwrap()
+        .map(|e| e.unwrap().0)
+        .collect();
+    assert_eq!(keys, [b"2".to_vec(), b"3".to_vec()]);
+    assert_eq!(b.iter(&txn).unwrap().count(), 1);
+
+    assert!(b.delete(&txn, b"1".to_vec()).unwrap());
+    assert_eq!(b.iter(&txn).unwrap().count(), 0);
+    assert_eq!(a.iter(&txn).unwrap().count(), 3);
+}
+
+#[test]
+fn content_hash_tracks_contents() {
+    let env = common::open_env("content_hash");
+    let txn = env.begin_txn().unwrap();
+    let open = |name| {
+        env.open_named_db::<_, &str, &str>(&txn, name, Some(DBFlags::MDB_CREATE))
+            .unwrap()
+    };
+    let (a, b, c) = (open("a"), open("b"), open("c"));
+    for (key, value) in [("x", "1"), ("y", "2"), ("z", "3")] {
+        txn.put(&a, key, value, None).unwrap();
+        txn.put(&b, key, value, None).unwrap();
+    }
+    txn.put(&c, "xy", "1", None).unwrap();
+
+    assert_eq!(a.content_hash(&txn).unwrap(), b.content_hash(&txn).unwrap());
+    assert_ne!(a.content_hash(&txn).unwrap(), c.content_hash(&txn).unwrap());
+
+    txn.put(&b, "y", "9", None).unwrap();
+    assert_ne!(a.content_hash(&txn).unwrap(), b.content_hash(&txn).unwrap());
+}
+
+#[test]
+fn set_database_keeps_sorted_keys() {
+    let env = common::open_env("set_db");
+    let txn = env.begin_txn().unwrap();
+    let set = SetDatabase::new(env.open_db::<Vec<u8>, [u8; 0]>(&txn, None).unwrap());
+    for key in ["pear", "apple", "fig"] {
+        assert!(set.insert(&txn, key.into()).unwrap());
+    }
+    assert!(!set.insert(&txn, "fig".into()).unwrap());
+
+    assert!(set.contains(&txn, "apple".into()).unwrap());
+    assert!(!set.contains(&txn, "kiwi".into()).unwrap());
+    assert!(set.remove(&txn, "pear".into()).unwrap());
+    assert!(!set.remove(&txn, "pear".into()).unwrap());
+
+    let keys: Vec<_> = set.iter(&txn).unwrap().map(Result::unwrap).collect();
+    assert_eq!(keys, [b"apple".to_vec(), b"fig".to_vec()]);
+}
+
+#[test]
+fn ordered_map_matches_btreemap() {
+    let env = common::open_env("ordered_map");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    let map = db.as_map(&txn);
+    let mut model = BTreeMap::new();
+    let entry = |(k, v): (&Vec<u8>, &Vec<u8>)| (k.clone(), v.clone());
+
+    assert_eq!(map.first_key_value().unwrap(), None);
+    assert_eq!(map.last_key_value().unwrap(), None);
+    for key in ["b", "d", "a", "c", "e"] {
+        let (k, v) = (key.as_bytes().to_vec(), key.as_bytes().to_vec());
+        assert_eq!(
+            map.insert(k.clone(), v.clone()).unwrap(),
+            model.insert(k, v)
+        );
+    }
+    assert_eq!(
+        map.insert(b"a".to_vec(), b"A".to_vec()).unwrap(),
+        model.insert(b"a".to_vec(), b"A".to_vec())
+    );
+    assert_eq!(
+        map.first_key_value().unwrap(),
+        model.first_key_value().map(entry)
+    );
+    assert_eq!(
+        map.last_key_value().unwrap(),
+        model.last_key_value().map(entry)
+    );
+
+    type Range<'a> = (Bound<&'a [u8]>, Bound<&'a [u8]>);
+    let ranges: [Range; 6] = [
+        (Bound::Unbounded, Bound::Unbounded),
+        (Bound::Included(b"b"), Bound::Excluded(b"d")),
+        (Bound::Excluded(b"b"), Bound::Included(b"d")),
+        (Bound::Excluded(b"bb"), Bound::Unbounded),
+        (Bound::Unbounded, Bound::Included(b"c")),
+        (Bound::Excluded(b"e"), Bound::Unbounded),
+    ];
+    for range in ranges {
+        let got: Vec<_> = map
+            .range::<[u8], _>(range)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let want: Vec<_> = model.range::<[u8], _>(range).map(entry).collect();
+        assert_eq!(got, want, "{range:?}");
+    }
+
+    assert_eq!(
+        map.remove(b"c".to_vec()).unwrap(),
+        model.remove(b"c".as_slice())
+    );
+    assert_eq!(map.remove(b"c".to_vec()).unwrap(), None);
+    assert_eq!(map.get(b"a".to_vec()).unwrap(), Some(b"A".to_vec()));
+}
+
+#[test]
+fn ndjson_round_trips_into_a_fresh_db() {
+    let env = common::open_env("ndjson");
+    let txn = env.begin_txn().unwrap();
+    let source = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "source", None)
+        .unwrap();
+    let copy = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "copy", None)
+        .unwrap();
+    let entries = [
+        (b"k".to_vec(), Vec::new()),
+        (b"k1".to_vec(), vec![0xff]),
+        (b"k2".to_vec(), vec![1, 2]),
+        (b"k3".to_vec(), (0..=255).collect()),
+        (vec![0, b',', b':'], b"{\"x\"}".to_vec()),
+    ];
+    for (key, value) in &entries {
+        txn.put(&source, key.clone(), value.clone(), None).unwrap();
+    }
+
+    let mut out = Vec::new();
+    assert_eq!(source.dump_ndjson(&txn, &mut out).unwrap(), 5);
+    let text = String::from_utf8(out.clone()).unwrap();
+    assert!(
+        text.lines()
+            .any(|line| line == r#"{"key":"azE=","value":"/w=="}"#),
+        "{text}"
+    );
+
+    assert_eq!(copy.load_ndjson(&txn, &out[..]).unwrap(), 5);
+    let copied: Vec<_> = copy.iter(&txn).unwrap().map(Result::unwrap).collect();
+    let mut expected = entries.to_vec();
+    expected.sort();
+    assert_eq!(copied, expected);
+}
+
+#[test]
+fn load_ndjson_reports_bad_lines() {
+    let env = common::open_env("ndjson_errors");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+
+    // Whitespace and field order don't matter
+    let spaced = "\n{ \"value\" : \"AQI=\" , \"key\" : \"eA==\" }\n";
+    assert_eq!(db.load_ndjson(&txn, spaced.as_bytes()).unwrap(), 1);
+    let got: Option<Vec<u8>> = txn.get(&db, b"x".to_vec()).unwrap();
+    assert_eq!(got, Some(vec![1, 2]));
+
+    let missing_value = "{\"key\":\"eA==\"}\n";
+    let err = db.load_ndjson(&txn, missing_value.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{err}");
+
+    let bad_base64 = "{\"key\":\"eA=a\",\"value\":\"\"}";
+    let err = db.load_ndjson(&txn, bad_base64.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("invalid base64"), "{err}");
+}
+
+#[test]
+fn composite_keys_sort_like_tuples() {
+    let env = common::open_env("composite");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    // Separator bytes and prefixes of other parts would sort wrongly if the
+    // parts were simply concatenated
+    let tuples: [[&[u8]; 2]; 8] = [
+        [b"a", b"z"],
+        [b"a\0", b"a"],
+        [b"aa", b""],
+        [b"b", b"a"],
+        [b"a", b"b"],
+        [b"", b"q"],
+        [b"a", b"b\0x"],
+        [b"a", b"b\xff"],
+    ];
+    for (i, tuple) in tuples.iter().enumerate() {
+        db.put_composite(&txn, tuple, vec![i as u8]).unwrap();
+        let key = CompositeKey::new(tuple);
+        assert_eq!(CompositeKey::decode(key.as_bytes()).unwrap(), tuple);
+    }
+    assert_eq!(
+        db.get_composite(&txn, &[b"b".as_slice(), b"a"]).unwrap(),
+        Some(vec![3])
+    );
+    assert_eq!(db.get_composite(&txn, &[b"b".as_slice()]).unwrap(), None);
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let keys: Vec<Vec<Vec<u8>>> = db
+        .scan_prefix_composite::<&[u8]>(&txn, &[])
+        .unwrap()
+        .map(|entry| entry.unwrap().0)
+        .collect();
+    let mut expected: Vec<Vec<Vec<u8>>> = tuples
+        .iter()
+        .map(|tuple| tuple.iter().map(|part| part.to_vec()).collect())
+        .collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+
+    let values: Vec<Vec<u8>> = db
+        .scan_prefix_composite(&txn, &[b"a"])
+        .unwrap()
+        .map(|entry| entry.unwrap().1)
+        .collect();
+    assert_eq!(values, [[4], [6], [7], [0]]);
+    assert_eq!(db.scan_prefix_composite(&txn, &[b"c"]).unwrap().count(), 0);
+}
+
+#[test]
+fn composite_key_rejects_truncated_encoding() {
+    assert!(matches!(
+        CompositeKey::decode(b"ab\0"),
+        Err(LMDBError::MalformedCompositeKey)
+    ));
+    assert!(matches!(
+        CompositeKey::decode(b"ab\0\x01cd"),
+        Err(LMDBError::MalformedCompositeKey)
+    ));
+}
@@ -0,0 +1,801 @@
+use std::{any::Any, cmp::Ordering, sync::Arc, thread, time::Duration};
+
+use rlmdb::{
+    Comparator, DBEnv, DBEnvBuilder, DBFlags, Database, EnvFlags, LMDBError, cursor::OwnedScan,
+    error::MDBError,
+};
+
+mod common;
+
+#[test]
+fn close_releases_files() {
+    let path = common::temp_path("close");
+    let env = DBEnvBuilder::new(&path)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+    env.close();
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(format!("{}-lock", path.display())).unwrap();
+}
+
+#[test]
+fn total_data_size_sums_keys_and_values() {
+    let env = common::open_env("total_data_size");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "ab", "cde", None).unwrap();
+    txn.put(&db, "x", "", None).unwrap();
+    let named = env
+        .open_named_db::<_, &str, &str>(&txn, "named", None)
+        .unwrap();
+    txn.put(&named, "1234", "5678", None).unwrap();
+
+    assert_eq!(env.total_data_size(&txn, true).unwrap(), 6 + 8);
+    // Without named databases, their records count as plain entries
+    let main_only = env.total_data_size(&txn, false).unwrap();
+    assert!(main_only > 6 + "named".len() as u64, "{main_only}");
+
+    txn.commit().unwrap();
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(env.total_data_size(&txn, true).unwrap(), 6 + 8);
+}
+
+fn scan_all<'env>(
+    env: &'env DBEnv,
+    db: &Database<Vec<u8>, Vec<u8>>,
+) -> OwnedScan<'env, Vec<u8>, Vec<u8>> {
+    env.scan(db).unwrap()
+}
+
+#[test]
+fn scan_can_be_returned_from_a_helper() {
+    let env = common::open_env("scan");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for i in 0..5u8 {
+        txn.put(&db, vec![i], vec![i * 2], None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let entries: Vec<_> = scan_all(&env, &db).collect::<Result<_, _>>().unwrap();
+    let expected: Vec<_> = (0..5u8).map(|i| (vec![i], vec![i * 2])).collect();
+    assert_eq!(entries, expected);
+    // The scan's read txn is gone, so this thread can start another
+    env.begin_txn_read_only().unwrap();
+}
+
+#[test]
+fn cached_stat_refreshes_after_ttl_or_invalidation() {
+    let mut builder = common::builder("cached_stat");
+    builder.set_stat_cache_ttl(Duration::from_millis(200));
+    let env = builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap();
+    assert_eq!(env.cached_stat().unwrap().entries(), 0);
+
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "a", "1", None).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(env.stat().unwrap().entries(), 1);
+    // Still within the TTL
+    assert_eq!(env.cached_stat().unwrap().entries(), 0);
+
+    thread::sleep(Duration::from_millis(250));
+    assert_eq!(env.cached_stat().unwrap().entries(), 1);
+
+    let txn = env.begin_txn().unwrap();
+    txn.put(&db, "b", "1", None).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(env.cached_stat().unwrap().entries(), 1);
+    env.invalidate_stat();
+    assert_eq!(env.cached_stat().unwrap().entries(), 2);
+}
+
+#[test]
+fn long_lived_reader_lag_is_reported() {
+    let env = common::open_env("reader_lag");
+    assert_eq!(env.oldest_reader_txnid().unwrap(), None);
+    assert_eq!(env.lag_vs_latest().unwrap(), None);
+
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.commit().unwrap();
+
+    let reader = env.begin_txn_read_only().unwrap();
+    let pinned = env.oldest_reader_txnid().unwrap();
+    assert_eq!(pinned, Some(reader.id()));
+    for value in ["a", "b", "c"] {
+        let txn = env.begin_txn().unwrap();
+        txn.put(&db, "k", value, None).unwrap();
+        txn.commit().unwrap();
+    }
+    assert_eq!(env.oldest_reader_txnid().unwrap(), pinned);
+    assert_eq!(env.lag_vs_latest().unwrap(), Some(3));
+
+    reader.abort();
+    assert_eq!(env.oldest_reader_txnid().unwrap(), None);
+}
+
+#[test]
+fn concurrent_forced_syncs_keep_data() {
+    let path = common::temp_path("sync");
+    let env = DBEnvBuilder::new(&path)
+        .open(Some(EnvFlags::MDB_NOSUBDIR | EnvFlags::MDB_NOSYNC))
+        .unwrap();
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<String, String>(&txn, None).unwrap();
+    txn.commit().unwrap();
+
+    thread::scope(|s| {
+        for i in 0..16 {
+            let (env, db) = (&env, &db);
+            s.spawn(move || {
+                let txn = env.begin_txn().unwrap();
+                txn.put(db, format!("k{i}"), "v".to_owned(), None).unwrap();
+                txn.commit().unwrap();
+                env.sync(true).unwrap();
+            });
+        }
+    });
+    env.close();
+
+    let mut builder = DBEnvBuilder::new(&path);
+    builder.set_remove_on_drop(true);
+    let env = builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap();
+    assert_eq!(env.stat().unwrap().entries(), 16);
+}
+
+#[test]
+fn opening_a_non_lmdb_file_reports_invalid() {
+    let path = common::temp_path("not_lmdb");
+    std::fs::write(&path, b"definitely not an lmdb file").unwrap();
+
+    let err = DBEnvBuilder::new(&path)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .err()
+        .unwrap();
+    assert!(matches!(&err, LMDBError::EnvInvalid { path: p } if *p == path));
+    let msg = err.to_string();
+    assert!(
+        msg.contains(&*path.to_string_lossy()) && msg.contains("MDB_INVALID"),
+        "{msg}"
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    let _ = std::fs::remove_file(format!("{}-lock", path.display()));
+}
+
+#[test]
+fn ephemeral_env_stores_many_keys() {
+    let env = DBEnvBuilder::ephemeral()
+        .set_map_size(64 << 20)
+        .open(None)
+        .unwrap();
+    let flags = env.flags().unwrap();
+    assert!(
+        flags.contains(EnvFlags::MDB_NOSYNC | EnvFlags::MDB_WRITEMAP | EnvFlags::MDB_NOMETASYNC)
+    );
+
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<String, String>(&txn, None).unwrap();
+    for i in 0..10_000 {
+        txn.put(&db, format!("{i:05}"), i.to_string(), None)
+            .unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(env.stat().unwrap().entries(), 10_000);
+    for i in (0..10_000).step_by(997) {
+        let value: Option<String> = txn.get(&db, format!("{i:05}")).unwrap();
+        assert_eq!(value, Some(i.to_string()));
+    }
+}
+
+#[test]
+fn ensure_databases_declares_them_up_front() {
+    let env = common::open_env("ensure_databases");
+    env.ensure_databases(&[
+        (Some("users"), DBFlags::empty()),
+        (Some("tags"), DBFlags::MDB_DUPSORT),
+        (None, DBFlags::empty()),
+    ])
+    .unwrap();
+
+    // Opening without MDB_CREATE in a read-only txn finds them
+    let txn = env.begin_txn_read_only().unwrap();
+    let tags = env
+        .open_named_db::<_, &str, &str>(&txn, "tags", Some(DBFlags::empty()))
+        .unwrap();
+    assert!(txn.db_flags(&tags).unwrap().contains(DBFlags::MDB_DUPSORT));
+    env.open_named_db::<_, &str, &str>(&txn, "users", Some(DBFlags::empty()))
+        .unwrap();
+    assert!(
+        env.open_named_db::<_, &str, &str>(&txn, "missing", Some(DBFlags::empty()))
+            .is_err()
+    );
+}
+
+#[test]
+fn lock_file_path_and_remove_on_drop() {
+    // Ephemeral environments remove their files by default
+    let env = DBEnvBuilder::ephemeral().open(None).unwrap();
+    let data = env.path().unwrap();
+    let lock = env.lock_file_path().unwrap();
+    assert_eq!(
+        lock.as_os_str(),
+        format!("{}-lock", data.display()).as_str()
+    );
+    assert!(data.exists() && lock.exists());
+    env.close();
+    assert!(!data.exists() && !lock.exists());
+
+    // Without it, both files stay
+    let env = DBEnvBuilder::ephemeral()
+        .set_remove_on_drop(false)
+        .open(None)
+        .unwrap();
+    let data = env.path().unwrap();
+    let lock = env.lock_file_path().unwrap();
+    drop(env);
+    assert!(data.exists() && lock.exists());
+    std::fs::remove_file(&data).unwrap();
+    std::fs::remove_file(&lock).unwrap();
+}
+
+/// Set in a child process started by `leave_stale_reader`.
+const STALE_READER_ENV: &str = "RLMDB_TEST_STALE_READER";
+
+/// In a child process started by `leave_stale_reader`, takes a reader slot
+/// and exits without releasing it.
+fn hold_reader_if_child() {
+    if let Some(path) = std::env::var_os(STALE_READER_ENV) {
+        let env = DBEnvBuilder::new(path)
+            .open(Some(EnvFlags::MDB_NOSUBDIR))
+            .unwrap();
+        let txn = env.begin_txn_read_only().unwrap();
+        std::mem::forget(txn);
+        std::process::exit(0);
+    }
+}
+
+/// Reruns `test` in a child process that dies holding a reader slot of `env`.
+fn leave_stale_reader(test: &str, env: &DBEnv) {
+    let status = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture"])
+        .env(STALE_READER_ENV, env.path().unwrap())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn reaper_clears_stale_reader() {
+    hold_reader_if_child();
+
+    let env = Arc::new(common::open_env("reaper"));
+    leave_stale_reader("reaper_clears_stale_reader", &env);
+    assert_eq!(env.reader_list().unwrap().lines().count(), 2);
+
+    let handle = env.spawn_reader_reaper(Duration::from_millis(5));
+    let start = std::time::Instant::now();
+    while handle.passes() == 0 {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(env.reader_check().unwrap(), 0);
+    assert_eq!(env.reader_list().unwrap().lines().count(), 1);
+
+    // Dropping the handle wakes the thread instead of waiting out the interval
+    let handle = env.spawn_reader_reaper(Duration::from_secs(3600));
+    let start = std::time::Instant::now();
+    drop(handle);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn open_db_from_many_threads() {
+    let env = common::open_env("concurrent-open");
+    let names = ["a", "b", "c", "d"];
+    env.write_transaction(|txn| {
+        for name in names {
+            env.open_named_db::<_, Vec<u8>, Vec<u8>>(txn, name, Some(DBFlags::MDB_CREATE))?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..50 {
+                    let txn = env.begin_txn_read_only().unwrap();
+                    for name in names {
+                        env.open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, name, None)
+                            .unwrap();
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn projected_full_divides_free_space_by_growth() {
+    let env = common::open_env("projected_full");
+    assert_eq!(env.projected_full(0.0).unwrap(), None);
+    assert_eq!(env.projected_full(-1.0).unwrap(), None);
+    assert_eq!(env.projected_full(f64::NAN).unwrap(), None);
+
+    // A fresh 16 MiB map only uses its two meta pages
+    let page_size = env.stat().unwrap().page_size() as f64;
+    assert_eq!(env.info().unwrap().last_pgno(), 1);
+    let expected = (16.0 * 1024.0 * 1024.0 - 2.0 * page_size) / (1024.0 * 1024.0);
+
+    let projected = env.projected_full(1024.0 * 1024.0).unwrap().unwrap();
+    assert!(
+        (projected.as_secs_f64() - expected).abs() < 1e-6,
+        "{projected:?}"
+    );
+}
+
+#[test]
+fn read_transaction_sees_one_snapshot() {
+    let env = common::open_env("read_transaction");
+    let txn = env.begin_txn().unwrap();
+    let a = env
+        .open_named_db::<_, &str, &str>(&txn, "a", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    let b = env
+        .open_named_db::<_, &str, &str>(&txn, "b", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    txn.put(&a, "k", "v1", None).unwrap();
+    txn.put(&b, "k", "v1", None).unwrap();
+    txn.commit().unwrap();
+
+    let seen = env
+        .read_transaction::<&str, &str, _, _>(&["a", "b"], |txn, dbs| {
+            let first = txn.get::<_, &str>(&dbs[0], "k")?;
+
+            // A writer commits to both databases in the middle of the read
+            thread::scope(|s| {
+                s.spawn(|| {
+                    let txn = env.begin_txn().unwrap();
+                    txn.put(&a, "k", "v2", None).unwrap();
+                    txn.put(&b, "k", "v2", None).unwrap();
+                    txn.commit().unwrap();
+                });
+            });
+
+            let second = txn.get::<_, &str>(&dbs[1], "k")?;
+            Ok((first.map(str::to_owned), second.map(str::to_owned)))
+        })
+        .unwrap();
+    assert_eq!(seen, (Some("v1".to_owned()), Some("v1".to_owned())));
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(txn.get::<_, &str>(&a, "k").unwrap(), Some("v2"));
+    assert_eq!(txn.get::<_, &str>(&b, "k").unwrap(), Some("v2"));
+    drop(txn);
+
+    assert!(
+        env.read_transaction::<&str, &str, (), _>(&["missing"], |_, _| Ok(()))
+            .unwrap_err()
+            .is_not_found()
+    );
+}
+
+#[test]
+fn reopen_with_max_dbs_recovers_from_dbs_full() {
+    let mut builder = common::builder("reopen_max_dbs");
+    builder.set_max_dbs(1);
+    let mut env = builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap();
+    env.set_userctx(42u32).unwrap();
+
+    let txn = env.begin_txn().unwrap();
+    env.open_named_db::<_, &str, &str>(&txn, "one", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    let err = env
+        .open_named_db::<_, &str, &str>(&txn, "two", Some(DBFlags::MDB_CREATE))
+        .err()
+        .unwrap();
+    assert!(matches!(err, LMDBError::MDB(MDBError::DbsFull)), "{err:?}");
+    txn.commit().unwrap();
+
+    let env = env.reopen_with_max_dbs(4).unwrap();
+    assert_eq!(env.flags().unwrap(), EnvFlags::MDB_NOSUBDIR);
+    assert_eq!(env.info().unwrap().map_size(), 16 << 20);
+    assert_eq!(env.get_userctx::<u32>(), Some(&42));
+
+    let txn = env.begin_txn().unwrap();
+    env.open_named_db::<_, &str, &str>(&txn, "one", None)
+        .unwrap();
+    env.open_named_db::<_, &str, &str>(&txn, "two", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    txn.commit().unwrap();
+}
+
+/// Per-environment state read by `ContextOrder`.
+struct Collation {
+    reverse: bool,
+}
+
+/// Bytewise order, reversed when the environment's `Collation` says so.
+struct ContextOrder;
+
+impl Comparator for ContextOrder {
+    fn compare(ctx: Option<&dyn Any>, a: &[u8], b: &[u8]) -> Ordering {
+        match ctx.and_then(|ctx| ctx.downcast_ref::<Collation>()) {
+            Some(Collation { reverse: true }) => b.cmp(a),
+            _ => a.cmp(b),
+        }
+    }
+}
+
+fn sorted_keys(env: &DBEnv) -> Vec<Vec<u8>> {
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "words", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    for key in ["a", "c", "b"] {
+        txn.put(&db, key.into(), Vec::new(), None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    db.iter(&txn)
+        .unwrap()
+        .map(|entry| entry.unwrap().0)
+        .collect()
+}
+
+#[test]
+fn comparator_reads_userctx() {
+    let mut env = common::open_env("userctx_reverse");
+    env.set_userctx(Collation { reverse: true }).unwrap();
+    env.register_comparator::<ContextOrder>(Some("words"));
+    assert_eq!(sorted_keys(&env), [b"c", b"b", b"a"]);
+
+    let mut env = common::open_env("userctx_forward");
+    env.set_userctx(Collation { reverse: false }).unwrap();
+    env.register_comparator::<ContextOrder>(Some("words"));
+    assert_eq!(sorted_keys(&env), [b"a", b"b", b"c"]);
+
+    // Without a context the comparator gets `None`
+    let mut env = common::open_env("userctx_none");
+    env.register_comparator::<ContextOrder>(Some("words"));
+    assert_eq!(sorted_keys(&env), [b"a", b"b", b"c"]);
+}
+
+#[test]
+fn userctx_is_freed_with_env() {
+    let payload = Arc::new(());
+    let mut env = common::open_env("userctx_drop");
+    env.set_userctx(Arc::clone(&payload)).unwrap();
+    assert!(env.get_userctx::<Arc<()>>().is_some());
+    assert!(env.get_userctx::<u32>().is_none());
+    assert_eq!(Arc::strong_count(&payload), 2);
+
+    drop(env);
+    assert_eq!(Arc::strong_count(&payload), 1);
+}
+
+#[test]
+fn avg_entries_per_leaf_of_populated_db() {
+    let env = common::open_env("avg_entries");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<String, String>(&txn, None).unwrap();
+    assert_eq!(env.avg_entries_per_leaf(&txn, &db).unwrap(), 0.0);
+
+    for i in 0..1000 {
+        txn.put(&db, format!("key{i:04}"), "v".to_owned(), None)
+            .unwrap();
+    }
+    let avg = env.avg_entries_per_leaf(&txn, &db).unwrap();
+    assert!(avg > 1.0 && avg <= 1000.0, "{avg}");
+}
+
+#[test]
+fn stat_reports_main_db_growth() {
+    let env = common::open_env("stat");
+    let stat = env.stat().unwrap();
+    assert!(stat.page_size().is_power_of_two());
+    assert_eq!(stat.depth(), 0);
+    assert_eq!(stat.leaf_pages(), 0);
+    assert_eq!(stat.entries(), 0);
+
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<String, Vec<u8>>(&txn, None).unwrap();
+    for i in 0..500 {
+        txn.put(&db, format!("key{i:03}"), vec![0; 64], None)
+            .unwrap();
+    }
+    txn.put(
+        &db,
+        "large".to_owned(),
+        vec![0; 3 * stat.page_size() as usize],
+        None,
+    )
+    .unwrap();
+    txn.commit().unwrap();
+
+    let stat = env.stat().unwrap();
+    assert_eq!(stat.entries(), 501);
+    assert!(stat.depth() >= 2);
+    assert!(stat.branch_pages() >= 1);
+    assert!(stat.leaf_pages() > 1);
+    assert!(stat.overflow_pages() >= 3);
+}
+
+/// Reversed bytewise order.
+struct Reverse;
+
+impl Comparator for Reverse {
+    fn compare(_: Option<&dyn Any>, a: &[u8], b: &[u8]) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+#[test]
+fn registered_comparator_survives_across_txns() {
+    let mut env = common::open_env("comparator");
+    env.register_comparator::<Reverse>(Some("first"));
+    env.register_comparator::<Reverse>(Some("second"));
+    env.register_dup_comparator::<Reverse>(Some("dups"));
+
+    let txn = env.begin_txn().unwrap();
+    let first = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "first", None)
+        .unwrap();
+    let second = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "second", None)
+        .unwrap();
+    for key in ["a", "c", "b"] {
+        txn.put(&first, key.into(), Vec::new(), None).unwrap();
+        txn.put(&second, key.into(), Vec::new(), None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    // The handles from the first transaction keep the ordering
+    let reversed = [b"c".to_vec(), b"b".to_vec(), b"a".to_vec()];
+    for _ in 0..2 {
+        let txn = env.begin_txn_read_only().unwrap();
+        for db in [&first, &second] {
+            let keys: Vec<_> = db.iter(&txn).unwrap().map(|e| e.unwrap().0).collect();
+            assert_eq!(keys, reversed);
+        }
+    }
+
+    // So do handles opened again later
+    let txn = env.begin_txn().unwrap();
+    let again = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "first", None)
+        .unwrap();
+    txn.put(&again, b"d".to_vec(), Vec::new(), None).unwrap();
+    let mut cursor = txn.cursor(&again).unwrap();
+    assert_eq!(cursor.get_first().unwrap().unwrap().0, b"d");
+    drop(cursor);
+
+    let dups = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    for value in ["1", "3", "2"] {
+        txn.put(&dups, b"k".to_vec(), value.into(), None).unwrap();
+    }
+    let mut cursor = txn.cursor(&dups).unwrap();
+    assert_eq!(cursor.get_first().unwrap().unwrap().1, b"3");
+}
+
+/// Opens a second handle on `path` with a larger map and fills it past the
+/// size of the map the other handles were opened with.
+fn grow_from_other_handle(path: &std::path::Path) {
+    let big = DBEnvBuilder::new(path)
+        .set_map_size(8 << 20)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+    let txn = big.begin_txn().unwrap();
+    let db = big.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for i in 0u32..200 {
+        txn.put(&db, i.to_be_bytes().to_vec(), vec![7; 10_000], None)
+            .unwrap();
+    }
+    txn.commit().unwrap();
+}
+
+#[test]
+fn writer_adopts_map_grown_by_another_handle() {
+    let path = common::temp_path("adopt_map_size");
+    let adopting = DBEnvBuilder::new(&path)
+        .set_map_size(1 << 20)
+        .auto_adopt_map_size(true)
+        .set_remove_on_drop(true)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+    let plain = DBEnvBuilder::new(&path)
+        .set_map_size(1 << 20)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+
+    grow_from_other_handle(&path);
+
+    assert!(matches!(
+        plain.begin_txn(),
+        Err(LMDBError::MDB(MDBError::MapResized))
+    ));
+
+    let txn = adopting.begin_txn().unwrap();
+    let db = adopting.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"x".to_vec(), vec![1; 10_000], None).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(adopting.stat().unwrap().entries(), 201);
+}
+
+#[test]
+fn map_size_is_not_adopted_under_open_txn() {
+    let path = common::temp_path("adopt_map_size_busy");
+    let env = DBEnvBuilder::new(&path)
+        .set_map_size(1 << 20)
+        .auto_adopt_map_size(true)
+        .set_remove_on_drop(true)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+    let reader = env.begin_txn_read_only().unwrap();
+
+    grow_from_other_handle(&path);
+
+    assert!(matches!(
+        env.begin_txn(),
+        Err(LMDBError::MDB(MDBError::MapResized))
+    ));
+    drop(reader);
+    env.begin_txn().unwrap().abort();
+}
+
+#[test]
+fn verify_integrity_reports_healthy_env_clean() {
+    let env = common::open_env("verify_integrity");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, Vec<u8>, Vec<u8>>(&txn, "a", None)
+        .unwrap();
+    for i in 0u32..500 {
+        txn.put(&db, i.to_be_bytes().to_vec(), vec![7; 100], None)
+            .unwrap();
+    }
+    txn.commit().unwrap();
+
+    let report = env.verify_integrity().unwrap();
+    assert!(report.is_clean(), "{report:?}");
+    // The main database holds one entry naming "a"
+    assert_eq!(report.databases, 2);
+    assert_eq!(report.entries, 501);
+}
+
+#[test]
+fn auto_grow_retries_write_after_map_full() {
+    assert!(matches!(
+        common::builder("auto_grow_factor")
+            .auto_grow(1.0)
+            .open(Some(EnvFlags::MDB_NOSUBDIR)),
+        Err(LMDBError::InvalidGrowFactor { factor: 1.0 })
+    ));
+
+    let env = common::builder("auto_grow")
+        .set_map_size(64 << 10)
+        .auto_grow(2.0)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.commit().unwrap();
+    let size_before = env.info().unwrap().map_size();
+
+    let mut attempts = 0;
+    env.write_transaction(|txn| {
+        attempts += 1;
+        for i in 0u32..2000 {
+            txn.put(&db, i.to_be_bytes().to_vec(), vec![7; 100], None)?;
+        }
+        Ok(())
+    })
+    .unwrap();
+    assert!(attempts > 1, "{attempts} attempts");
+    assert!(env.info().unwrap().map_size() > size_before);
+    assert_eq!(env.stat().unwrap().entries(), 2000);
+}
+
+#[test]
+fn set_map_size_waits_for_open_txns() {
+    let env = common::open_env("set_map_size");
+    let reader = env.begin_txn_read_only().unwrap();
+    assert!(matches!(
+        env.set_map_size(32 << 20),
+        Err(LMDBError::MapResizeBusy)
+    ));
+    drop(reader);
+
+    env.set_map_size(32 << 20).unwrap();
+    assert_eq!(env.info().unwrap().map_size(), 32 << 20);
+}
+
+#[test]
+fn info_tracks_commits_and_readers() {
+    let env = common::open_env("env_info");
+    let before = env.info().unwrap();
+    assert_eq!(before.map_size(), 16 << 20);
+    assert!(before.max_readers() > 0);
+
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"k".to_vec(), b"v".to_vec(), None).unwrap();
+    txn.commit().unwrap();
+
+    let _reader = env.begin_txn_read_only().unwrap();
+    let after = env.info().unwrap();
+    assert_eq!(after.last_txnid(), before.last_txnid() + 1);
+    assert!(after.last_pgno() > before.last_pgno());
+    assert!(after.num_readers() >= 1);
+}
+
+#[test]
+fn copy_to_writes_an_openable_snapshot() {
+    let env = common::open_env("copy_source");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for i in 0u32..500 {
+        txn.put(&db, i.to_be_bytes().to_vec(), vec![1; 200], None)
+            .unwrap();
+    }
+    txn.commit().unwrap();
+    // Leave free pages behind for the compacting copy to drop
+    let txn = env.begin_txn().unwrap();
+    for i in 0u32..400 {
+        txn.delete(&db, i.to_be_bytes().to_vec(), None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let plain = common::temp_path("copy_plain");
+    let compact = common::temp_path("copy_compact");
+    env.copy_to(&plain).unwrap();
+    env.copy_to_compact(&compact).unwrap();
+    // An existing target isn't overwritten
+    assert!(env.copy_to(&plain).is_err());
+    assert!(env.copy_to("bad\0path").is_err());
+    let size = |path| std::fs::metadata(path).unwrap().len();
+    assert!(size(&compact) < size(&plain));
+    std::fs::remove_file(&plain).unwrap();
+
+    let copy = DBEnvBuilder::new(&compact)
+        .set_remove_on_drop(true)
+        .open(Some(EnvFlags::MDB_NOSUBDIR))
+        .unwrap();
+    let txn = copy.begin_txn_read_only().unwrap();
+    let db = copy.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    assert_eq!(db.iter(&txn).unwrap().count(), 100);
+}
+
+#[test]
+fn reader_check_clears_slots_of_dead_processes() {
+    hold_reader_if_child();
+
+    let env = common::open_env("reader_check");
+    assert_eq!(env.reader_check().unwrap(), 0);
+    leave_stale_reader("reader_check_clears_slots_of_dead_processes", &env);
+
+    let reader = env.begin_txn_read_only().unwrap();
+    let list = env.reader_list().unwrap();
+    // A header line, then one line per slot naming its snapshot
+    assert_eq!(list.lines().count(), 3, "{list}");
+    assert!(list.contains("txnid"), "{list}");
+    assert!(list.contains(&std::process::id().to_string()), "{list}");
+
+    assert_eq!(env.reader_check().unwrap(), 1);
+    assert_eq!(env.reader_check().unwrap(), 0);
+    let list = env.reader_list().unwrap();
+    assert_eq!(list.lines().count(), 2, "{list}");
+    assert!(list.contains(&reader.id().to_string()), "{list}");
+}
@@ -0,0 +1,155 @@
+use std::{error::Error, io};
+
+use rlmdb::{
+    LMDBError,
+    error::{ErrnoError, ErrorCategory, MDBError},
+};
+
+const ENOENT: i32 = 2;
+
+#[test]
+fn unknown_code_keeps_os_error_as_source() {
+    let err = LMDBError::from_mdb_error(ENOENT).unwrap_err();
+
+    let source = err.source().expect("the OS error should be the source");
+    assert_eq!(
+        source.to_string(),
+        io::Error::from_raw_os_error(ENOENT).to_string()
+    );
+
+    let LMDBError::Io(io_err) = &err else {
+        panic!("expected an Io error, got {err:?}");
+    };
+    assert_eq!(io_err.kind(), io::ErrorKind::NotFound);
+    let errno = io_err.get_ref().unwrap().downcast_ref::<ErrnoError>();
+    assert_eq!(errno.map(ErrnoError::code), Some(ENOENT));
+    assert_eq!(err.raw_os_error(), Some(ENOENT));
+
+    // `?` from an io::Error still lands in Io
+    let converted: LMDBError = io::Error::from_raw_os_error(ENOENT).into();
+    assert_eq!(converted.raw_os_error(), Some(ENOENT));
+}
+
+#[test]
+fn category_sorts_each_variant() {
+    let cases = [
+        (MDBError::Corrupted, ErrorCategory::Corruption),
+        (MDBError::PageNotFound, ErrorCategory::Corruption),
+        (MDBError::Panic, ErrorCategory::Corruption),
+        (MDBError::Invalid, ErrorCategory::Corruption),
+        (MDBError::MapFull, ErrorCategory::Transient),
+        (MDBError::MapResized, ErrorCategory::Transient),
+        (MDBError::TxnFull, ErrorCategory::Transient),
+        (MDBError::ReadersFull, ErrorCategory::Transient),
+        (MDBError::KeyExists, ErrorCategory::UserError),
+        (MDBError::NotFound, ErrorCategory::UserError),
+        (MDBError::BadValSize, ErrorCategory::UserError),
+        (MDBError::BadTxn, ErrorCategory::Other),
+        (MDBError::VersionMismatch, ErrorCategory::Other),
+    ];
+    for (err, category) in cases {
+        assert_eq!(LMDBError::MDB(err).category(), category);
+    }
+
+    assert_eq!(
+        LMDBError::KeyTooLarge { len: 600, max: 511 }.category(),
+        ErrorCategory::UserError
+    );
+    assert_eq!(
+        LMDBError::NestedTxnUnsupported.category(),
+        ErrorCategory::UserError
+    );
+    assert_eq!(
+        LMDBError::ChildOfReadOnlyTxn.category(),
+        ErrorCategory::UserError
+    );
+    assert_eq!(
+        LMDBError::EnvInvalid {
+            path: "data.mdb".into()
+        }
+        .category(),
+        ErrorCategory::Corruption
+    );
+    assert_eq!(
+        LMDBError::Io(io::Error::other("disk")).category(),
+        ErrorCategory::Other
+    );
+}
+
+#[test]
+fn raw_code_round_trips_through_from_raw() {
+    let codes = [
+        rlmdb::sys::MDB_NOTFOUND,
+        rlmdb::sys::MDB_MAP_FULL,
+        rlmdb::sys::MDB_BAD_DBI,
+        ENOENT,
+        12345,
+    ];
+    for code in codes {
+        assert_eq!(LMDBError::from_raw(code).raw_code(), Some(code));
+    }
+
+    assert_eq!(
+        LMDBError::Io(io::Error::from_raw_os_error(5)).raw_code(),
+        Some(5)
+    );
+    assert_eq!(
+        LMDBError::EnvInvalid {
+            path: "data.mdb".into()
+        }
+        .raw_code(),
+        Some(rlmdb::sys::MDB_INVALID)
+    );
+    assert_eq!(LMDBError::ReadTxnActive.raw_code(), None);
+    assert_eq!(LMDBError::Io(io::Error::other("custom")).raw_code(), None);
+}
+
+#[test]
+fn description_uses_lmdb_text_for_codes() {
+    let err = LMDBError::from_raw(rlmdb::sys::MDB_NOTFOUND);
+    assert!(
+        err.description().contains("MDB_NOTFOUND"),
+        "{}",
+        err.description()
+    );
+
+    // System codes get strerror's text, in Display too
+    let err = LMDBError::from_raw(ENOENT);
+    assert!(
+        err.description().contains("No such file"),
+        "{}",
+        err.description()
+    );
+    assert!(err.to_string().contains("No such file"), "{err}");
+
+    // Codes this crate has no variant for still get LMDB's text
+    let err = LMDBError::from_raw(-30779);
+    assert!(err.to_string().contains("-30779"), "{err}");
+    assert!(!err.description().is_empty());
+
+    assert_eq!(
+        LMDBError::ReadTxnActive.description(),
+        LMDBError::ReadTxnActive.to_string()
+    );
+}
+
+#[test]
+fn predicates_match_only_their_code() {
+    let not_found = LMDBError::from_raw(rlmdb::sys::MDB_NOTFOUND);
+    let key_exists = LMDBError::from_raw(rlmdb::sys::MDB_KEYEXIST);
+    let map_full = LMDBError::from_raw(rlmdb::sys::MDB_MAP_FULL);
+    let other = LMDBError::from_raw(ENOENT);
+
+    assert!(not_found.is_not_found());
+    assert!(key_exists.is_key_exists());
+    assert!(map_full.is_map_full());
+    for err in [&key_exists, &map_full, &other] {
+        assert!(!err.is_not_found());
+    }
+    for err in [&not_found, &map_full, &other] {
+        assert!(!err.is_key_exists());
+    }
+    for err in [&not_found, &key_exists, &other] {
+        assert!(!err.is_map_full());
+    }
+}
@@ -0,0 +1,20 @@
+use rlmdb::{DBFlags, EnvFlags, PutFlags};
+
+#[test]
+fn db_flags_display() {
+    let flags = DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT;
+    assert_eq!(flags.to_string(), "MDB_DUPSORT | MDB_CREATE");
+    assert_eq!(DBFlags::empty().to_string(), "");
+}
+
+#[test]
+fn put_flags_display() {
+    let flags = PutFlags::MDB_APPEND | PutFlags::MDB_NOOVERWRITE;
+    assert_eq!(flags.to_string(), "MDB_NOOVERWRITE | MDB_APPEND");
+}
+
+#[test]
+fn env_flags_display() {
+    let flags = EnvFlags::MDB_RDONLY | EnvFlags::MDB_NOSUBDIR;
+    assert_eq!(flags.to_string(), "MDB_NOSUBDIR | MDB_RDONLY");
+}
@@ -0,0 +1,61 @@
+use rlmdb::{DBFlags, LMDBError, SecondaryIndex, error::MDBError};
+
+mod common;
+
+#[test]
+fn secondary_index_follows_inserts_updates_and_deletes() {
+    let env = common::open_env("secondary_index");
+    let txn = env.begin_txn().unwrap();
+    let users = env
+        .open_named_db::<_, Vec<u8>, &str>(&txn, "users", None)
+        .unwrap();
+    let by_city = env
+        .open_named_db::<_, &str, Vec<u8>>(
+            &txn,
+            "by_city",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+    let idx = SecondaryIndex::new(&users, &by_city);
+
+    idx.put(&txn, b"alice".to_vec(), "paris").unwrap();
+    idx.put(&txn, b"bob".to_vec(), "paris").unwrap();
+    idx.put(&txn, b"carol".to_vec(), "rome").unwrap();
+    assert_eq!(txn.range_count(&by_city, "a", "zz").unwrap(), 3);
+    assert_eq!(idx.lookup(&txn, "rome").unwrap(), Some(b"carol".to_vec()));
+
+    // Moving alice drops her old index entry
+    idx.put(&txn, b"alice".to_vec(), "oslo").unwrap();
+    assert_eq!(txn.range_count(&by_city, "a", "zz").unwrap(), 3);
+    assert_eq!(txn.range_count(&by_city, "paris", "paris\0").unwrap(), 1);
+    assert_eq!(idx.lookup(&txn, "paris").unwrap(), Some(b"bob".to_vec()));
+    assert_eq!(idx.lookup(&txn, "oslo").unwrap(), Some(b"alice".to_vec()));
+
+    assert!(idx.delete(&txn, b"bob".to_vec()).unwrap());
+    assert!(!idx.delete(&txn, b"bob".to_vec()).unwrap());
+    assert_eq!(txn.range_count(&by_city, "a", "zz").unwrap(), 2);
+    assert_eq!(idx.lookup(&txn, "paris").unwrap(), None);
+}
+
+#[test]
+fn unique_secondary_index_rejects_shared_values() {
+    let env = common::open_env("unique_index");
+    let txn = env.begin_txn().unwrap();
+    let users = env
+        .open_named_db::<_, Vec<u8>, &str>(&txn, "users", None)
+        .unwrap();
+    let emails = env
+        .open_named_db::<_, &str, Vec<u8>>(&txn, "emails", None)
+        .unwrap();
+    let idx = SecondaryIndex::new(&users, &emails);
+
+    idx.put(&txn, b"dave".to_vec(), "d@example.com").unwrap();
+    assert!(matches!(
+        idx.put(&txn, b"erin".to_vec(), "d@example.com"),
+        Err(LMDBError::MDB(MDBError::KeyExists))
+    ));
+    // Re-storing the same value under its owner is fine
+    idx.put(&txn, b"dave".to_vec(), "d@example.com").unwrap();
+    let erin: Option<Vec<u8>> = txn.get(&users, b"erin".to_vec()).unwrap();
+    assert_eq!(erin, None);
+}
@@ -0,0 +1,128 @@
+//! Checks the wrapper's pointer and slice handling for put, get, delete and
+//! cursor scans. Under Miri the crate runs against the in-memory stand-in for
+//! LMDB in `src/sys_mock.rs`, so these tests exercise only the safe wrapper;
+//! run them with
+//!
+//! ```text
+//! MIRIFLAGS=-Zmiri-disable-isolation cargo +nightly miri test --test miri
+//! ```
+//!
+//! Isolation has to be off because opening an environment reads the mount
+//! table. Outside Miri they run against LMDB like any other test.
+
+use rlmdb::{LMDBError, error::MDBError};
+
+mod common;
+
+#[test]
+fn put_get_delete_round_trip() {
+    let env = common::open_env("miri_round_trip");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+
+    txn.put(&db, b"key".to_vec(), b"value".to_vec(), None)
+        .unwrap();
+    assert_eq!(
+        txn.get::<_, Vec<u8>>(&db, b"key".to_vec()).unwrap(),
+        Some(b"value".to_vec())
+    );
+    txn.put(&db, b"key".to_vec(), b"longer value".to_vec(), None)
+        .unwrap();
+    assert_eq!(
+        txn.get::<_, Vec<u8>>(&db, b"key".to_vec()).unwrap(),
+        Some(b"longer value".to_vec())
+    );
+
+    assert!(txn.delete(&db, b"key".to_vec(), None).unwrap());
+    assert_eq!(txn.get::<_, Vec<u8>>(&db, b"key".to_vec()).unwrap(), None);
+    assert!(!txn.delete(&db, b"key".to_vec(), None).unwrap());
+    txn.commit().unwrap();
+}
+
+#[test]
+fn borrowed_reads_point_into_the_snapshot() {
+    let env = common::open_env("miri_borrowed");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "a", "1", None).unwrap();
+    txn.put(&db, "b", "2", None).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let a = txn.get_ref(&db, "a").unwrap().unwrap();
+    let b: &str = txn.get(&db, "b").unwrap().unwrap();
+    assert_eq!((a, b), (&b"1"[..], "2"));
+}
+
+fn is_bad_valsize<T>(result: Result<T, LMDBError>) -> bool {
+    matches!(result, Err(LMDBError::MDB(MDBError::BadValSize)))
+}
+
+#[test]
+fn zero_length_key_is_rejected() {
+    let env = common::open_env("miri_empty_key");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+
+    let put = txn.put(&db, vec![], b"value".to_vec(), None);
+    assert!(is_bad_valsize(put));
+    assert!(is_bad_valsize(txn.get::<_, Vec<u8>>(&db, vec![])));
+    assert!(is_bad_valsize(txn.delete(&db, vec![], None)));
+}
+
+#[test]
+fn zero_length_value_round_trips() {
+    // An empty slice's pointer is dangling, and LMDB may hand back a null or
+    // dangling one for an empty value; neither may be dereferenced
+    let env = common::open_env("miri_empty_value");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"empty".to_vec(), vec![], None).unwrap();
+    assert_eq!(
+        txn.get::<_, Vec<u8>>(&db, b"empty".to_vec()).unwrap(),
+        Some(vec![])
+    );
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(txn.get_ref(&db, b"empty".to_vec()).unwrap(), Some(&[][..]));
+    let mut cursor = txn.cursor(&db).unwrap();
+    assert_eq!(
+        cursor.get_first().unwrap(),
+        Some((b"empty".to_vec(), vec![]))
+    );
+}
+
+#[test]
+fn cursor_scans_forward_and_backward() {
+    let env = common::open_env("miri_cursor_scan");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    for i in 0u8..5 {
+        txn.put(&db, vec![i], vec![i; i as usize], None).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let mut cursor = txn.cursor(&db).unwrap();
+    let mut forward = Vec::new();
+    while let Some((key, value)) = cursor.next().unwrap() {
+        assert_eq!(value.len(), key[0] as usize);
+        forward.push(key[0]);
+    }
+    assert_eq!(forward, [0, 1, 2, 3, 4]);
+
+    let mut cursor = txn.cursor(&db).unwrap();
+    let mut backward = Vec::new();
+    while let Some((key, _)) = cursor.prev().unwrap() {
+        backward.push(key[0]);
+    }
+    assert_eq!(backward, [4, 3, 2, 1, 0]);
+
+    let keys: Vec<u8> = db
+        .iter(&txn)
+        .unwrap()
+        .map(|entry| entry.unwrap().0[0])
+        .collect();
+    assert_eq!(keys, [0, 1, 2, 3, 4]);
+}
@@ -0,0 +1,946 @@
+use std::sync::{Arc, Mutex};
+
+use rlmdb::{
+    DBFlags, EnvFlags, GetResult, LMDBError, OpStats, PutFlags, PutPolicy, error::MDBError,
+};
+
+mod common;
+
+#[test]
+fn second_read_txn_on_thread_needs_notls() {
+    let env = common::open_env("read_txn_active");
+    let first = env.begin_txn_read_only().unwrap();
+    assert!(matches!(
+        env.begin_txn_read_only(),
+        Err(LMDBError::ReadTxnActive)
+    ));
+    drop(first);
+    // The slot is free again once the first one ends
+    env.begin_txn_read_only().unwrap();
+
+    let env = common::builder("read_txn_notls")
+        .open(Some(EnvFlags::MDB_NOSUBDIR | EnvFlags::MDB_NOTLS))
+        .unwrap();
+    let first = env.begin_txn_read_only().unwrap();
+    let second = env.begin_txn_read_only().unwrap();
+    assert_eq!(first.id(), second.id());
+}
+
+#[test]
+fn range_count_counts_half_open_range() {
+    let env = common::open_env("range_count");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<String, Vec<u8>>(&txn, None).unwrap();
+    for i in 0..100 {
+        txn.put(&db, format!("k{i:03}"), vec![1], None).unwrap();
+    }
+
+    assert_eq!(txn.range_count(&db, "k010", "k020").unwrap(), 10);
+    assert_eq!(txn.range_count(&db, "a", "z").unwrap(), 100);
+    assert_eq!(txn.range_count(&db, "k050", "k050").unwrap(), 0);
+    assert_eq!(txn.range_count(&db, "k095", "zzz").unwrap(), 5);
+    assert_eq!(txn.range_count(&db, "", "k005").unwrap(), 5);
+}
+
+#[test]
+fn oversized_key_names_both_sizes() {
+    let env = common::open_env("key_too_large");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    let key = vec![0u8; 600];
+
+    let err = txn.put(&db, key.clone(), vec![1], None).unwrap_err();
+    assert!(matches!(err, LMDBError::KeyTooLarge { len: 600, max: 511 }));
+    let msg = err.to_string();
+    assert!(msg.contains("600") && msg.contains("511"), "{msg}");
+
+    let got: Result<Option<Vec<u8>>, _> = txn.get(&db, key.clone());
+    assert!(matches!(got, Err(LMDBError::KeyTooLarge { .. })));
+    assert!(matches!(
+        txn.delete(&db, key, None),
+        Err(LMDBError::KeyTooLarge { .. })
+    ));
+}
+
+#[test]
+fn upsert_reports_creation() {
+    let env = common::open_env("upsert");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    assert!(txn.upsert(&db, "a", vec![1]).unwrap());
+    assert!(!txn.upsert(&db, "a", vec![2]).unwrap());
+    assert_eq!(txn.get(&db, "a").unwrap(), Some(vec![2]));
+}
+
+#[test]
+fn contains_key_on_unique_and_dupsort_dbs() {
+    let env = common::open_env("contains_key");
+    let txn = env.begin_txn().unwrap();
+    let unique = env
+        .open_named_db::<_, &str, &str>(&txn, "unique", None)
+        .unwrap();
+    let dups = env
+        .open_named_db::<_, &str, &str>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+
+    for db in [&unique, &dups] {
+        assert!(!txn.contains_key(db, "a").unwrap());
+        txn.put(db, "a", "1", None).unwrap();
+        txn.put(db, "a", "2", None).unwrap();
+        assert!(txn.contains_key(db, "a").unwrap());
+        assert!(!txn.contains_key(db, "b").unwrap());
+    }
+}
+
+#[test]
+fn writemap_rejects_nested_write_txns() {
+    let mut builder = common::builder("writemap");
+    builder.set_write_map(true);
+    let env = builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap();
+    assert!(env.flags().unwrap().contains(EnvFlags::MDB_WRITEMAP));
+
+    let mut txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, "a", vec![1, 2], None).unwrap();
+    assert!(matches!(
+        txn.begin_child(),
+        Err(LMDBError::NestedTxnUnsupported)
+    ));
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(txn.get(&db, "a").unwrap(), Some(vec![1, 2]));
+    drop(txn);
+
+    assert!(matches!(
+        builder.open(Some(EnvFlags::MDB_NOSUBDIR | EnvFlags::MDB_RDONLY)),
+        Err(LMDBError::WriteMapReadOnly)
+    ));
+}
+
+#[test]
+fn remove_reports_whether_key_existed() {
+    let env = common::open_env("remove");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "a", "1", None).unwrap();
+
+    assert!(txn.remove(&db, "a").unwrap());
+    assert!(!txn.contains_key(&db, "a").unwrap());
+    assert!(!txn.remove(&db, "a").unwrap());
+    assert!(!txn.remove(&db, "never").unwrap());
+}
+
+#[test]
+fn compare_and_swap_checks_current_value() {
+    let env = common::open_env("compare_and_swap");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    // Create: expected absent
+    assert!(txn.compare_and_swap(&db, "a", None, Some(b"1")).unwrap());
+    assert!(!txn.compare_and_swap(&db, "a", None, Some(b"2")).unwrap());
+    // Mismatch leaves the value alone
+    assert!(
+        !txn.compare_and_swap(&db, "a", Some(b"x"), Some(b"2"))
+            .unwrap()
+    );
+    assert_eq!(txn.get(&db, "a").unwrap(), Some(b"1".to_vec()));
+    // Match swaps
+    assert!(
+        txn.compare_and_swap(&db, "a", Some(b"1"), Some(b"2"))
+            .unwrap()
+    );
+    assert_eq!(txn.get(&db, "a").unwrap(), Some(b"2".to_vec()));
+    // Delete: new absent
+    assert!(txn.compare_and_swap(&db, "a", Some(b"2"), None).unwrap());
+    assert!(!txn.contains_key(&db, "a").unwrap());
+    assert!(!txn.compare_and_swap(&db, "a", Some(b"2"), None).unwrap());
+    assert!(txn.compare_and_swap(&db, "a", None, None).unwrap());
+}
+
+#[test]
+fn read_txns_under_contention() {
+    let env = common::open_env("read_contention");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"n".to_vec(), 0u64.to_be_bytes().to_vec(), None)
+        .unwrap();
+    txn.commit().unwrap();
+
+    // Readers keep taking and releasing slots while a writer commits, so
+    // reader slots are recycled across threads
+    std::thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..200 {
+                    let txn = env.begin_txn_read_only().unwrap();
+                    let n: Option<Vec<u8>> = txn.get(&db, b"n".to_vec()).unwrap();
+                    assert_eq!(n.map(|n| n.len()), Some(8));
+                    // A second one on the same thread is refused at once
+                    assert!(matches!(
+                        env.begin_txn_read_only(),
+                        Err(LMDBError::ReadTxnActive)
+                    ));
+                }
+            });
+        }
+        s.spawn(|| {
+            for i in 1..50u64 {
+                let txn = env.begin_txn().unwrap();
+                txn.put(&db, b"n".to_vec(), i.to_be_bytes().to_vec(), None)
+                    .unwrap();
+                txn.commit().unwrap();
+            }
+        });
+    });
+}
+
+#[test]
+fn notls_read_txns_under_contention() {
+    let env = common::builder("read_contention_notls")
+        .open(Some(EnvFlags::MDB_NOSUBDIR | EnvFlags::MDB_NOTLS))
+        .unwrap();
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<Vec<u8>, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, b"n".to_vec(), 0u64.to_be_bytes().to_vec(), None)
+        .unwrap();
+    txn.commit().unwrap();
+
+    // Slots aren't tied to threads, so each reader may hold two at once and
+    // they are recycled between threads
+    std::thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..200 {
+                    let first = env.begin_txn_read_only().unwrap();
+                    let second = env.begin_txn_read_only().unwrap();
+                    assert!(first.id() <= second.id());
+                    assert!(second.get_ref(&db, b"n".to_vec()).unwrap().is_some());
+                }
+            });
+        }
+        s.spawn(|| {
+            for i in 1..50u64 {
+                let txn = env.begin_txn().unwrap();
+                txn.put(&db, b"n".to_vec(), i.to_be_bytes().to_vec(), None)
+                    .unwrap();
+                txn.commit().unwrap();
+            }
+        });
+    });
+}
+
+#[test]
+fn temp_dbs_are_unique_and_dropped() {
+    let env = common::open_env("temp_db");
+    let txn = env.begin_txn().unwrap();
+    let first = txn.create_temp_db::<&str, &str>().unwrap();
+    let second = txn.create_temp_db::<&str, &str>().unwrap();
+    assert_ne!(first.name(), second.name());
+
+    txn.put(&first, "a", "1", None).unwrap();
+    assert!(txn.contains_key(&first, "a").unwrap());
+    assert!(!txn.contains_key(&second, "a").unwrap());
+
+    txn.drop_temp_db(first).unwrap();
+    txn.drop_temp_db(second).unwrap();
+    txn.commit().unwrap();
+    // Nothing is left behind in the main database
+    assert_eq!(env.stat().unwrap().entries(), 0);
+}
+
+#[test]
+fn out_of_order_append_names_the_key() {
+    let env = common::open_env("append_out_of_order");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.append(&db, [("a", "1"), ("b", "2")]).unwrap();
+
+    let err = txn
+        .append(&db, [("c", "3"), ("bb", "4"), ("d", "5")])
+        .unwrap_err();
+    let LMDBError::AppendOutOfOrder { position, key } = &err else {
+        panic!("expected AppendOutOfOrder, got {err:?}");
+    };
+    assert_eq!(*position, 1);
+    assert_eq!(key, b"bb");
+    assert!(err.to_string().contains("\"bb\""), "{err}");
+}
+
+#[test]
+fn put_with_fills_reserved_space() {
+    let env = common::open_env("put_with");
+    let mut txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    txn.put_with(&db, "k", 16, |buf| {
+        assert_eq!(buf.len(), 16);
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+    })
+    .unwrap();
+    txn.put_with(&db, "empty", 0, |buf| assert!(buf.is_empty()))
+        .unwrap();
+
+    assert_eq!(
+        txn.get(&db, "k").unwrap(),
+        Some((0u8..16).collect::<Vec<_>>())
+    );
+    assert_eq!(txn.get(&db, "empty").unwrap(), Some(Vec::new()));
+}
+
+#[test]
+fn write_txn_reads_its_own_writes() {
+    let env = common::open_env("read_your_writes");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    txn.put(&db, "k", b"v1".to_vec(), None).unwrap();
+    assert_eq!(txn.get(&db, "k").unwrap(), Some(b"v1".to_vec()));
+    txn.put(&db, "k", b"v2".to_vec(), None).unwrap();
+    assert_eq!(txn.get_for_update(&db, "k").unwrap(), Some(b"v2".to_vec()));
+    txn.put(&db, "empty", Vec::new(), None).unwrap();
+    assert_eq!(txn.get(&db, "empty").unwrap(), Some(Vec::new()));
+
+    // Other transactions don't see them until commit
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let reader = env.begin_txn_read_only().unwrap();
+            assert!(!reader.contains_key(&db, "k").unwrap());
+        });
+    });
+}
+
+#[test]
+fn clear_returns_removed_count() {
+    let env = common::open_env("clear");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<String, String>(&txn, None).unwrap();
+    for i in 0..37 {
+        txn.put(&db, i.to_string(), String::new(), None).unwrap();
+    }
+
+    assert_eq!(txn.clear(&db).unwrap(), 37);
+    assert_eq!(txn.clear(&db).unwrap(), 0);
+    txn.commit().unwrap();
+    assert_eq!(env.stat().unwrap().entries(), 0);
+}
+
+#[test]
+fn op_stats_count_bytes_moved() {
+    let env = common::open_env("op_stats");
+    let mut txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    assert_eq!(txn.op_stats(), OpStats::default());
+
+    txn.put(&db, "ab", vec![0; 10], None).unwrap();
+    txn.put(&db, "cd", vec![0; 20_000], None).unwrap();
+    txn.put_with(&db, "e", 5, |buf| buf.fill(1)).unwrap();
+    let small: Vec<u8> = txn.get(&db, "ab").unwrap().unwrap();
+    let large: Vec<u8> = txn.get(&db, "cd").unwrap().unwrap();
+    assert_eq!((small.len(), large.len()), (10, 20_000));
+    let mut out = Vec::new();
+    assert!(!txn.get_reuse(&db, "missing", &mut out).unwrap());
+
+    let stats = txn.op_stats();
+    assert_eq!(stats.bytes_written, (2 + 10) + (2 + 20_000) + (1 + 5));
+    assert_eq!(stats.bytes_read, 10 + 20_000);
+    // Two leaf hits plus the overflow pages of the large value
+    assert_eq!(stats.page_faults_estimate, 2 + 20_010 / 4096);
+}
+
+#[test]
+fn append_auto_assigns_sequential_keys() {
+    let env = common::open_env("append_auto");
+    let txn = env.begin_txn().unwrap();
+    let log = env
+        .open_named_db::<_, [u8; 8], &str>(
+            &txn,
+            "log",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_INTEGERKEY),
+        )
+        .unwrap();
+
+    for i in 0..300u64 {
+        assert_eq!(txn.append_auto(&log, "record").unwrap(), i);
+    }
+    assert!(txn.contains_key(&log, 299u64.to_ne_bytes()).unwrap());
+    txn.commit().unwrap();
+
+    // Numbering carries on in a later transaction
+    let txn = env.begin_txn().unwrap();
+    assert_eq!(txn.append_auto(&log, "record").unwrap(), 300);
+
+    let plain = env
+        .open_named_db::<_, [u8; 8], &str>(&txn, "plain", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    assert!(matches!(
+        txn.append_auto(&plain, "record"),
+        Err(LMDBError::NotIntegerKeyDb)
+    ));
+
+    txn.put(&log, u64::MAX.to_ne_bytes(), "last", None).unwrap();
+    assert!(matches!(
+        txn.append_auto(&log, "record"),
+        Err(LMDBError::IntegerKeySpaceExhausted)
+    ));
+
+    // 4-byte integer keys are valid in LMDB, but not what append_auto assigns
+    let narrow = env
+        .open_named_db::<_, [u8; 4], &str>(
+            &txn,
+            "narrow",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_INTEGERKEY),
+        )
+        .unwrap();
+    txn.put(&narrow, 7u32.to_ne_bytes(), "seven", None).unwrap();
+    assert!(matches!(
+        txn.append_auto(&narrow, "record"),
+        Err(LMDBError::MalformedIntegerKey { len: 4 })
+    ));
+}
+
+#[test]
+fn get_decodes_owned_and_borrowed_values() {
+    let env = common::open_env("get_from_val");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, "k", b"hello".to_vec(), None).unwrap();
+    txn.put(&db, "bad", vec![0xff], None).unwrap();
+
+    // Write transactions decode into owned types
+    assert_eq!(
+        txn.get::<_, Vec<u8>>(&db, "k").unwrap(),
+        Some(b"hello".to_vec())
+    );
+    assert_eq!(
+        txn.get::<_, String>(&db, "k").unwrap().as_deref(),
+        Some("hello")
+    );
+    assert!(txn.get::<_, String>(&db, "bad").is_err());
+    txn.commit().unwrap();
+
+    // Read-only ones can also borrow from the map
+    let txn = env.begin_txn_read_only().unwrap();
+    let borrowed = txn.get::<_, &[u8]>(&db, "k").unwrap().unwrap();
+    let owned = txn.get::<_, Vec<u8>>(&db, "k").unwrap().unwrap();
+    assert_eq!(borrowed, owned.as_slice());
+    assert_eq!(txn.get::<_, &str>(&db, "k").unwrap(), Some("hello"));
+    assert_eq!(
+        txn.get::<_, String>(&db, "k").unwrap().as_deref(),
+        Some("hello")
+    );
+    assert!(txn.get::<_, &str>(&db, "bad").is_err());
+    assert_eq!(txn.get::<_, &[u8]>(&db, "missing").unwrap(), None);
+}
+
+#[test]
+fn put_chunks_writes_the_concatenation() {
+    let env = common::open_env("put_chunks");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    let parts: [&[u8]; 3] = [b"head:", b"", b"body"];
+
+    txn.put_chunks(&db, "k", 9, parts).unwrap();
+    let got: Option<Vec<u8>> = txn.get(&db, "k").unwrap();
+    assert_eq!(got.unwrap(), b"head:body");
+
+    // A length that doesn't match the chunks is rejected
+    assert!(matches!(
+        txn.put_chunks(&db, "k", 10, parts),
+        Err(LMDBError::ChunkLengthMismatch {
+            expected: 10,
+            actual: 9
+        })
+    ));
+    let got: Option<Vec<u8>> = txn.get(&db, "k").unwrap();
+    assert_eq!(got.unwrap(), b"head:body");
+}
+
+#[test]
+fn get_detail_tells_empty_from_absent() {
+    let env = common::open_env("get_detail");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "marker", "", None).unwrap();
+    txn.put(&db, "k", "v", None).unwrap();
+
+    assert_eq!(
+        txn.get_detail::<_, String>(&db, "missing").unwrap(),
+        GetResult::Absent
+    );
+    assert_eq!(
+        txn.get_detail::<_, String>(&db, "marker").unwrap(),
+        GetResult::Empty
+    );
+    assert_eq!(
+        txn.get_detail::<_, String>(&db, "k").unwrap(),
+        GetResult::Value("v".to_owned())
+    );
+}
+
+#[test]
+fn delete_prefix_removes_matching_keys() {
+    let env = common::open_env("delete_prefix");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&[u8], &[u8]>(&txn, None).unwrap();
+    let keys: [&[u8]; 8] = [
+        b"a",
+        b"t1/a",
+        b"t1/b",
+        b"t1/c",
+        b"t2/a",
+        b"\xff",
+        b"\xff\xff",
+        b"\xff\xff\x01",
+    ];
+    for key in keys {
+        txn.put(&db, key, b"v".as_slice(), None).unwrap();
+    }
+
+    assert_eq!(txn.delete_prefix(&db, b"t1/").unwrap(), 3);
+    assert_eq!(txn.delete_prefix(&db, b"t1/").unwrap(), 0);
+    assert!(txn.contains_key(&db, b"t2/a".as_slice()).unwrap());
+
+    // An all-0xFF prefix has no upper bound to stop at
+    assert_eq!(txn.delete_prefix(&db, b"\xff\xff").unwrap(), 2);
+    assert!(txn.contains_key(&db, b"\xff".as_slice()).unwrap());
+
+    // The empty prefix matches everything left
+    assert_eq!(txn.delete_prefix(&db, b"").unwrap(), 3);
+    txn.commit().unwrap();
+    assert_eq!(env.stat().unwrap().entries(), 0);
+}
+
+#[test]
+fn get_layered_prefers_earlier_databases() {
+    let env = common::open_env("get_layered");
+    let txn = env.begin_txn().unwrap();
+    let base = env
+        .open_named_db::<_, &str, &str>(&txn, "base", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    let overrides = env
+        .open_named_db::<_, &str, &str>(&txn, "overrides", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    txn.put(&base, "color", "blue", None).unwrap();
+    txn.put(&base, "size", "m", None).unwrap();
+    txn.put(&overrides, "color", "red", None).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let layers = [&overrides, &base];
+    let get = |key| txn.get_layered::<_, _, String>(&layers, key).unwrap();
+    assert_eq!(get("color").as_deref(), Some("red"));
+    assert_eq!(get("size").as_deref(), Some("m"));
+    assert_eq!(get("weight"), None);
+}
+
+#[test]
+fn take_returns_and_removes_value() {
+    let env = common::open_env("take");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, "k", b"v".to_vec(), None).unwrap();
+
+    assert_eq!(txn.take(&db, "k").unwrap(), Some(b"v".to_vec()));
+    assert!(!txn.contains_key(&db, "k").unwrap());
+    assert_eq!(txn.take(&db, "k").unwrap(), None);
+}
+
+#[test]
+fn put_and_read_returns_stored_bytes() {
+    let env = common::open_env("put_and_read");
+    let mut txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    let data = vec![0xab; 3000];
+    let stored = txn.put_and_read(&db, "k", data.clone()).unwrap();
+    assert_eq!(stored, data);
+    assert_eq!(txn.put_and_read(&db, "empty", Vec::new()).unwrap(), b"");
+}
+
+#[test]
+fn get_of_missing_key_is_none() {
+    let env = common::open_env("get_missing");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    let got: Option<Vec<u8>> = txn.get(&db, "missing").unwrap();
+    assert_eq!(got, None);
+
+    txn.put(&db, "present", b"v".to_vec(), None).unwrap();
+    let got: Option<Vec<u8>> = txn.get(&db, "present").unwrap();
+    assert_eq!(got.as_deref(), Some(b"v".as_slice()));
+}
+
+#[test]
+fn get_ref_borrows_from_read_only_txn() {
+    let env = common::open_env("get_ref");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, "k", b"value".to_vec(), None).unwrap();
+    txn.put(&db, "empty", Vec::new(), None).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let value = txn.get_ref(&db, "k").unwrap();
+    let empty = txn.get_ref(&db, "empty").unwrap();
+    assert_eq!(value, Some(b"value".as_slice()));
+    assert_eq!(empty, Some(b"".as_slice()));
+    assert_eq!(txn.get_ref(&db, "missing").unwrap(), None);
+}
+
+#[test]
+fn put_versioned_rejects_stale_versions() {
+    let env = common::open_env("put_versioned");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    assert_eq!(txn.get_versioned(&db, "k").unwrap(), None);
+
+    assert_eq!(txn.put_versioned(&db, "k", b"a".to_vec(), 0).unwrap(), 1);
+    assert_eq!(txn.put_versioned(&db, "k", b"b".to_vec(), 1).unwrap(), 2);
+    assert_eq!(
+        txn.get_versioned(&db, "k").unwrap(),
+        Some((2, b"b".to_vec()))
+    );
+
+    assert!(matches!(
+        txn.put_versioned(&db, "k", b"c".to_vec(), 1),
+        Err(LMDBError::VersionConflict {
+            expected: 1,
+            actual: 2
+        })
+    ));
+    assert!(matches!(
+        txn.put_versioned(&db, "new", b"c".to_vec(), 3),
+        Err(LMDBError::VersionConflict {
+            expected: 3,
+            actual: 0
+        })
+    ));
+    assert_eq!(
+        txn.get_versioned(&db, "k").unwrap(),
+        Some((2, b"b".to_vec()))
+    );
+
+    // A value not written by put_versioned
+    txn.put(&db, "plain", b"short".to_vec(), None).unwrap();
+    assert!(matches!(
+        txn.get_versioned(&db, "plain"),
+        Err(LMDBError::MissingVersion { len: 5 })
+    ));
+    assert!(matches!(
+        txn.put_versioned(&db, "plain", b"c".to_vec(), 0),
+        Err(LMDBError::MissingVersion { len: 5 })
+    ));
+}
+
+#[test]
+fn clear_db_keeps_handle_and_drop_db_deletes() {
+    let env = common::open_env("drop_db");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_named_db::<_, &str, &str>(&txn, "scratch", Some(DBFlags::MDB_CREATE))
+        .unwrap();
+    txn.put(&db, "k", "v", None).unwrap();
+
+    txn.clear_db(&db).unwrap();
+    assert!(!txn.contains_key(&db, "k").unwrap());
+    txn.put(&db, "k", "v", None).unwrap();
+    assert!(txn.contains_key(&db, "k").unwrap());
+
+    txn.drop_db(db).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn().unwrap();
+    let err = env
+        .open_named_db::<_, &str, &str>(&txn, "scratch", Some(DBFlags::empty()))
+        .err()
+        .unwrap();
+    assert!(matches!(err, LMDBError::MDB(MDBError::NotFound)), "{err:?}");
+}
+
+#[test]
+fn child_txn_commits_into_parent_or_is_discarded() {
+    let env = common::open_env("child_txn");
+    let mut parent = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&parent, None).unwrap();
+    parent.put(&db, "base", "1", None).unwrap();
+
+    let child = parent.begin_child().unwrap();
+    child.put(&db, "committed", "1", None).unwrap();
+    assert!(child.contains_key(&db, "base").unwrap());
+    child.commit().unwrap();
+    assert!(parent.contains_key(&db, "committed").unwrap());
+
+    let child = parent.begin_child().unwrap();
+    child.put(&db, "aborted", "1", None).unwrap();
+    child.delete(&db, "base", None).unwrap();
+    child.abort();
+    assert!(!parent.contains_key(&db, "aborted").unwrap());
+    assert!(parent.contains_key(&db, "base").unwrap());
+
+    let child = parent.begin_child().unwrap();
+    child.put(&db, "dropped", "1", None).unwrap();
+    drop(child);
+    assert!(!parent.contains_key(&db, "dropped").unwrap());
+
+    parent.commit().unwrap();
+    let txn = env.begin_txn_read_only().unwrap();
+    assert!(txn.contains_key(&db, "base").unwrap());
+    assert!(txn.contains_key(&db, "committed").unwrap());
+    assert!(!txn.contains_key(&db, "aborted").unwrap());
+    assert!(!txn.contains_key(&db, "dropped").unwrap());
+}
+
+#[test]
+fn put_no_overwrite_returns_existing_value() {
+    let env = common::open_env("put_no_overwrite");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    assert_eq!(
+        txn.put_no_overwrite(&db, "k", b"first".to_vec()).unwrap(),
+        None
+    );
+    assert_eq!(
+        txn.put_no_overwrite(&db, "k", b"second".to_vec()).unwrap(),
+        Some(b"first".to_vec())
+    );
+    assert_eq!(
+        txn.get::<_, Vec<u8>>(&db, "k").unwrap(),
+        Some(b"first".to_vec())
+    );
+}
+
+#[test]
+fn put_reserve_writes_into_the_map() {
+    let env = common::open_env("put_reserve");
+    let mut txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+
+    txn.put_reserve(&db, "k", 5)
+        .unwrap()
+        .copy_from_slice(b"hello");
+    assert!(txn.put_reserve(&db, "empty", 0).unwrap().is_empty());
+    txn.put_with(&db, "with", 3, |buf| buf.copy_from_slice(b"abc"))
+        .unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(txn.get_ref(&db, "k").unwrap(), Some(&b"hello"[..]));
+    assert_eq!(txn.get_ref(&db, "empty").unwrap(), Some(&b""[..]));
+    assert_eq!(txn.get_ref(&db, "with").unwrap(), Some(&b"abc"[..]));
+}
+
+#[test]
+fn swap_exchanges_present_and_absent_values() {
+    let env = common::open_env("swap");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    let get = |key| txn.get::<_, Vec<u8>>(&db, key).unwrap();
+    txn.put(&db, "a", b"1".to_vec(), None).unwrap();
+    txn.put(&db, "b", b"2".to_vec(), None).unwrap();
+
+    txn.swap(&db, "a", "b").unwrap();
+    assert_eq!(get("a"), Some(b"2".to_vec()));
+    assert_eq!(get("b"), Some(b"1".to_vec()));
+
+    // An absent value deletes the other key
+    txn.swap(&db, "a", "c").unwrap();
+    assert_eq!(get("a"), None);
+    assert_eq!(get("c"), Some(b"2".to_vec()));
+
+    txn.swap(&db, "x", "y").unwrap();
+    assert_eq!(get("x"), None);
+    assert_eq!(get("y"), None);
+
+    txn.swap(&db, "b", "b").unwrap();
+    assert_eq!(get("b"), Some(b"1".to_vec()));
+}
+
+#[test]
+fn delete_reports_whether_anything_was_removed() {
+    let env = common::open_env("delete_existed");
+    let txn = env.begin_txn().unwrap();
+    let db = env
+        .open_db::<Vec<u8>, Vec<u8>>(&txn, Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT))
+        .unwrap();
+    txn.put(&db, b"k".to_vec(), b"1".to_vec(), None).unwrap();
+    txn.put(&db, b"k".to_vec(), b"2".to_vec(), None).unwrap();
+
+    assert!(txn.delete(&db, b"k".to_vec(), Some(b"1".to_vec())).unwrap());
+    assert!(!txn.delete(&db, b"k".to_vec(), Some(b"1".to_vec())).unwrap());
+    assert!(txn.delete(&db, b"k".to_vec(), None).unwrap());
+    assert!(!txn.delete(&db, b"k".to_vec(), None).unwrap());
+}
+
+#[test]
+fn id_matches_the_snapshot_read() {
+    let env = common::open_env("txn_id");
+    let reader = env.begin_txn_read_only().unwrap();
+    let before = reader.id();
+    drop(reader);
+
+    // A write txn gets the next id, which readers see once it commits
+    let txn = env.begin_txn().unwrap();
+    assert_eq!(txn.id(), before + 1);
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, "k", vec![1], None).unwrap();
+    txn.commit().unwrap();
+
+    let reader = env.begin_txn_read_only().unwrap();
+    assert_eq!(reader.id(), before + 1);
+}
+
+#[test]
+fn put_policy_maps_to_flags_and_validates_db() {
+    let env = common::open_env("put_policy");
+    let txn = env.begin_txn().unwrap();
+    let plain = env
+        .open_named_db::<_, &str, &str>(&txn, "plain", None)
+        .unwrap();
+    let dups = env
+        .open_named_db::<_, &str, &str>(
+            &txn,
+            "dups",
+            Some(DBFlags::MDB_CREATE | DBFlags::MDB_DUPSORT),
+        )
+        .unwrap();
+
+    txn.put_policy(&plain, "a", "1", PutPolicy::Overwrite)
+        .unwrap();
+    txn.put_policy(&plain, "a", "2", PutPolicy::Overwrite)
+        .unwrap();
+    assert!(matches!(
+        txn.put_policy(&plain, "a", "3", PutPolicy::NoOverwrite),
+        Err(LMDBError::MDB(MDBError::KeyExists))
+    ));
+    txn.put_policy(&plain, "b", "x", PutPolicy::Append).unwrap();
+    assert!(matches!(
+        txn.put_policy(&plain, "a", "y", PutPolicy::Append),
+        Err(LMDBError::MDB(MDBError::KeyExists))
+    ));
+    // NoDupData only makes sense for MDB_DUPSORT databases
+    assert!(matches!(
+        txn.put_policy(&plain, "c", "1", PutPolicy::NoDupData),
+        Err(LMDBError::DupSortRequired)
+    ));
+
+    txn.put_policy(&dups, "k", "1", PutPolicy::NoDupData)
+        .unwrap();
+    txn.put_policy(&dups, "k", "2", PutPolicy::Overwrite)
+        .unwrap();
+    assert!(matches!(
+        txn.put_policy(&dups, "k", "2", PutPolicy::NoDupData),
+        Err(LMDBError::MDB(MDBError::KeyExists))
+    ));
+    assert!(matches!(
+        txn.put_policy(&dups, "k", "3", PutPolicy::NoOverwrite),
+        Err(LMDBError::MDB(MDBError::KeyExists))
+    ));
+    txn.put_policy(&dups, "z", "1", PutPolicy::Append).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    assert_eq!(txn.get(&plain, "a").unwrap(), Some("2"));
+    assert_eq!(txn.get::<_, &str>(&plain, "c").unwrap(), None);
+    assert_eq!(txn.get(&dups, "z").unwrap(), Some("1"));
+}
+
+#[test]
+fn get_guarded_reads_in_place() {
+    let env = common::open_env("get_guarded");
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, Vec<u8>>(&txn, None).unwrap();
+    txn.put(&db, "k", b"hello".to_vec(), None).unwrap();
+    txn.commit().unwrap();
+
+    let txn = env.begin_txn_read_only().unwrap();
+    let guard = txn.get_guarded(&db, "k").unwrap().unwrap();
+    assert_eq!(&*guard, b"hello");
+    assert_eq!(guard.len(), 5);
+    assert!(txn.get_guarded(&db, "missing").unwrap().is_none());
+    txn.commit().unwrap();
+}
+
+#[test]
+fn dirty_drop_hook_fires_only_for_dropped_txns_with_changes() {
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let env = {
+        let dropped = dropped.clone();
+        let mut builder = common::builder("dirty_drop_hook");
+        builder.on_dirty_drop(move |id| dropped.lock().unwrap().push(id));
+        builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap()
+    };
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.put(&db, "taken", "v", None).unwrap();
+    txn.commit().unwrap();
+
+    // Nothing changed
+    drop(env.begin_txn().unwrap());
+    drop(env.begin_txn_read_only().unwrap());
+
+    // The only write failed
+    let txn = env.begin_txn().unwrap();
+    assert!(
+        txn.put(&db, "taken", "w", Some(PutFlags::MDB_NOOVERWRITE))
+            .is_err()
+    );
+    assert!(!txn.delete(&db, "missing", None).unwrap());
+    drop(txn);
+
+    // Ended explicitly
+    let txn = env.begin_txn().unwrap();
+    txn.put(&db, "k", "v", None).unwrap();
+    txn.abort();
+    assert!(dropped.lock().unwrap().is_empty());
+
+    let txn = env.begin_txn().unwrap();
+    let id = txn.id();
+    txn.put(&db, "k", "v", None).unwrap();
+    drop(txn);
+    assert_eq!(*dropped.lock().unwrap(), [id]);
+
+    let txn = env.begin_txn().unwrap();
+    txn.cursor(&db).unwrap().put("k", "v", None).unwrap();
+    drop(txn);
+    assert_eq!(dropped.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn child_marks_parent_dirty_only_when_it_commits_changes() {
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let env = {
+        let dropped = dropped.clone();
+        let mut builder = common::builder("child_dirty");
+        builder.on_dirty_drop(move |id| dropped.lock().unwrap().push(id));
+        builder.open(Some(EnvFlags::MDB_NOSUBDIR)).unwrap()
+    };
+    let txn = env.begin_txn().unwrap();
+    let db = env.open_db::<&str, &str>(&txn, None).unwrap();
+    txn.commit().unwrap();
+
+    let mut parent = env.begin_txn().unwrap();
+    let child = parent.begin_child().unwrap();
+    child.put(&db, "k", "v", None).unwrap();
+    child.abort();
+    drop(parent.begin_child().unwrap());
+    drop(parent);
+    assert!(dropped.lock().unwrap().is_empty());
+
+    let mut parent = env.begin_txn().unwrap();
+    let id = parent.id();
+    let child = parent.begin_child().unwrap();
+    child.put(&db, "k", "v", None).unwrap();
+    child.commit().unwrap();
+    drop(parent);
+    assert_eq!(*dropped.lock().unwrap(), [id]);
+}